@@ -1,10 +1,31 @@
-use std::io::{BufRead, BufReader, Read};
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
+use std::ops::Range;
 use thiserror::Error;
 
+#[cfg(feature = "async")]
+mod stream;
+
+#[cfg(feature = "async")]
+pub use crate::stream::{DocumentStream, SpannedDocumentStream};
+
 #[derive(Error, Debug)]
 pub enum YamlSplitError {
     #[error(transparent)]
     IOError(#[from] std::io::Error),
+    /// A document was larger than the limit set by `DocumentIterator::max_document_size()`. The
+    /// document is skipped, and splitting continues with the next one.
+    #[error("document at line {line} exceeds the size limit of {max} bytes", line = .span.line)]
+    DocumentTooLargeError { max: usize, span: Span },
+}
+
+/// `invalid_utf8()` is the error for input which isn't valid UTF-8, the same as `read_line()`'s.
+pub(crate) fn invalid_utf8() -> YamlSplitError {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+    .into()
 }
 
 /// `DocumentIterator` is an iterator over individual YAML documents in a file or stream.
@@ -95,12 +116,54 @@ where
     R: Read,
 {
     reader: BufReader<R>,
-    disambiguated: bool,
-    in_header: bool,
-    prepend_next: Option<String>,
+    splitter: Splitter,
+}
+
+/// `Span` is the position in the file or stream at which a document starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// The line number, starting from 1.
+    pub line: usize,
+    /// The offset in bytes, starting from 0.
+    pub offset: usize,
 }
 
-impl<'a, R: Read + 'a> DocumentIterator<R> {
+/// `SplitterOptions` configures how `DocumentIterator` and `DocumentStream` split documents.
+/// ```
+/// use yaml_split::{DocumentIterator, SplitterOptions};
+///
+/// let options = SplitterOptions {
+///     keep_empty: false,
+///     ..SplitterOptions::default()
+/// };
+/// let yaml = "---\n---\na: b\n---\n# nothing here\n";
+/// let docs: Vec<_> = DocumentIterator::with_options(yaml.as_bytes(), options)
+///     .map(Result::unwrap)
+///     .collect();
+///
+/// assert_eq!(docs, vec!["---\na: b\n"]);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitterOptions {
+    /// Whether to return empty documents, i.e. those with nothing but comments and blank lines
+    /// after their `---`, such as the first document of `---\n---\na: b`. YAML parses these as
+    /// `null`. This defaults to `true`.
+    pub keep_empty: bool,
+    /// The size limit of each document in bytes, see `DocumentIterator::max_document_size()`.
+    /// This defaults to `None`, no limit.
+    pub max_document_size: Option<usize>,
+}
+
+impl Default for SplitterOptions {
+    fn default() -> Self {
+        SplitterOptions {
+            keep_empty: true,
+            max_document_size: None,
+        }
+    }
+}
+
+impl<R: Read> DocumentIterator<R> {
     /// `new()` creates a new DocumentIterator over a given `reader`'s contents.
     ///
     /// This reader can be a reader for a file:
@@ -150,148 +213,480 @@ impl<'a, R: Read + 'a> DocumentIterator<R> {
     /// }
     /// ```
     pub fn new(reader: R) -> DocumentIterator<R> {
+        Self::with_options(reader, SplitterOptions::default())
+    }
+
+    /// `with_options()` creates a new DocumentIterator over a given `reader`'s contents, which
+    /// splits documents according to `options`.
+    pub fn with_options(reader: R, options: SplitterOptions) -> DocumentIterator<R> {
         let br = BufReader::new(reader);
 
         DocumentIterator {
             reader: br,
-            disambiguated: false,
-            in_header: false,
-            prepend_next: None,
+            splitter: Splitter::new(options),
         }
     }
+
+    /// `with_spans()` turns this into an iterator which also returns the `Span` at which each
+    /// document starts, i.e. the position of the first character of the document's text. This
+    /// is useful for pointing users to the right place in their input.
+    ///
+    /// ```
+    /// use yaml_split::{DocumentIterator, Span};
+    /// let yaml = r#"hello: world
+    /// ---
+    /// hello: rust
+    /// "#;
+    ///
+    /// let mut doc_iter = DocumentIterator::new(yaml.as_bytes()).with_spans();
+    ///
+    /// let (_, span) = doc_iter.next().unwrap().unwrap();
+    /// assert_eq!(span, Span { line: 1, offset: 0 });
+    ///
+    /// let (doc, span) = doc_iter.next().unwrap().unwrap();
+    /// assert_eq!(doc, "---\nhello: rust\n");
+    /// assert_eq!(span, Span { line: 2, offset: 13 });
+    /// ```
+    pub fn with_spans(self) -> SpannedDocumentIterator<R> {
+        SpannedDocumentIterator { inner: self }
+    }
+
+    /// `with_raw()` turns this into an iterator over the exact text of each document in the
+    /// source, see `RawDocument`. This is useful for tools which edit some documents in place,
+    /// leaving the bytes of the others untouched.
+    ///
+    /// ```
+    /// use yaml_split::DocumentIterator;
+    /// let yaml = "a: b\n...\n# about c\n---\nc: d\n";
+    ///
+    /// let docs: Vec<_> = DocumentIterator::new(yaml.as_bytes())
+    ///     .with_raw()
+    ///     .map(Result::unwrap)
+    ///     .collect();
+    ///
+    /// assert_eq!(docs[0].raw(), "a: b\n...\n");
+    /// assert_eq!(docs[0].document(), "a: b\n");
+    /// assert_eq!(docs[1].raw(), "# about c\n---\nc: d\n");
+    /// assert_eq!(docs[1].range(), 9..28);
+    ///
+    /// let raw: String = docs.iter().map(|doc| doc.raw()).collect();
+    /// assert_eq!(raw, yaml);
+    /// ```
+    pub fn with_raw(self) -> RawDocumentIterator<R> {
+        RawDocumentIterator { inner: self }
+    }
+
+    /// `max_document_size()` limits the size of each document to `max` bytes. A larger document
+    /// is returned as `YamlSplitError::DocumentTooLargeError` instead, and the iterator moves on to
+    /// the next document. Only about `max` bytes of a document are ever held in memory, even if it
+    /// is a single line, so this protects against untrusted input. There is no limit by default.
+    ///
+    /// ```
+    /// use yaml_split::{DocumentIterator, YamlSplitError};
+    /// let yaml = "a: b\n---\nhello: world\n---\nc: d\n";
+    ///
+    /// let mut doc_iter = DocumentIterator::new(yaml.as_bytes()).max_document_size(10);
+    ///
+    /// assert_eq!("a: b\n", doc_iter.next().unwrap().unwrap());
+    /// assert!(matches!(
+    ///     doc_iter.next().unwrap(),
+    ///     Err(YamlSplitError::DocumentTooLargeError { max: 10, .. })
+    /// ));
+    /// assert_eq!("---\nc: d\n", doc_iter.next().unwrap().unwrap());
+    /// ```
+    pub fn max_document_size(mut self, max: usize) -> Self {
+        self.splitter.options.max_document_size = Some(max);
+        self
+    }
+}
+
+/// `SpannedDocumentIterator` is a `DocumentIterator` which also returns the `Span` at which each
+/// document starts. See `DocumentIterator::with_spans()`.
+pub struct SpannedDocumentIterator<R>
+where
+    R: Read,
+{
+    inner: DocumentIterator<R>,
+}
+
+impl<R: Read> Iterator for SpannedDocumentIterator<R> {
+    type Item = Result<(String, Span), YamlSplitError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next_document()
+    }
+}
+
+/// `RawDocument` is the exact text of a document in the source, see `DocumentIterator::with_raw()`.
+///
+/// This is the document as returned by `DocumentIterator`, followed by the `...` line which ended
+/// it, if any. The raw text of the documents in a source, in order, adds up to the whole source,
+/// apart from any byte order mark, comments after the last document and, when `keep_empty` is
+/// off, empty documents.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawDocument {
+    raw: String,
+    /// The length of the document within `raw`, before its `...` line.
+    document_len: usize,
+    span: Span,
+}
+
+impl RawDocument {
+    /// `raw()` returns the exact text of the document, including any comments, directives and
+    /// blank lines before it, and its `---` and `...` markers.
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    /// `document()` returns the document as `DocumentIterator` does, i.e. without its `...` line.
+    pub fn document(&self) -> &str {
+        &self.raw[..self.document_len]
+    }
+
+    /// `span()` returns the position at which the document starts.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// `range()` returns the range of bytes of the document in the source.
+    pub fn range(&self) -> Range<usize> {
+        self.span.offset..self.span.offset + self.raw.len()
+    }
+
+    /// `into_raw()` returns the exact text of the document, see `raw()`.
+    pub fn into_raw(self) -> String {
+        self.raw
+    }
+}
+
+/// `RawDocumentIterator` is a `DocumentIterator` which returns the exact text of each document.
+/// See `DocumentIterator::with_raw()`.
+pub struct RawDocumentIterator<R>
+where
+    R: Read,
+{
+    inner: DocumentIterator<R>,
+}
+
+impl<R: Read> Iterator for RawDocumentIterator<R> {
+    type Item = Result<RawDocument, YamlSplitError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = self.inner.next_document()?;
+        let end_marker = self.inner.splitter.end_marker.take();
+
+        Some(res.map(|(mut raw, span)| {
+            let document_len = raw.len();
+            raw.push_str(&end_marker.unwrap_or_default());
+
+            RawDocument {
+                raw,
+                document_len,
+                span,
+            }
+        }))
+    }
 }
 
 impl<R: Read> Iterator for DocumentIterator<R> {
     type Item = Result<String, YamlSplitError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut buf = String::new();
-        let mut current_file = match &self.prepend_next {
-            Some(next) => next.clone(),
-            None => String::new(),
-        };
-        self.prepend_next = None;
+        self.next_document()
+            .map(|res| res.map(|(document, _)| document))
+    }
+}
+
+impl<R: Read> DocumentIterator<R> {
+    fn next_document(&mut self) -> Option<Result<(String, Span), YamlSplitError>> {
+        loop {
+            let mut line = Vec::new();
+            let max_line = self.splitter.max_line() as u64;
+
+            let res = match (&mut self.reader)
+                .take(max_line)
+                .read_until(b'\n', &mut line)
+            {
+                Ok(0) => return self.splitter.end(),
+                // A line which doesn't fit is read in parts.
+                Ok(len) if len as u64 == max_line && !line.ends_with(b"\n") => {
+                    self.splitter.part(&line, false)
+                }
+                Ok(_) if self.splitter.in_line() => self.splitter.part(&line, true),
+                Ok(_) => match String::from_utf8(line) {
+                    Ok(line) => self.splitter.line(line),
+                    Err(_) => return Some(Err(invalid_utf8())),
+                },
+                Err(e) => return Some(Err(e.into())),
+            };
+
+            if res.is_some() {
+                return res;
+            }
+        }
+    }
+}
+
+/// `Splitter` is the state machine shared by `DocumentIterator` and `DocumentStream`. It is fed
+/// the input one line at a time, and returns each document as soon as its end is seen.
+pub(crate) struct Splitter {
+    pub(crate) options: SplitterOptions,
+    disambiguated: bool,
+    in_header: bool,
+    current: Option<(String, Span)>,
+    /// Whether the current document has any content, rather than only comments and markers.
+    content: bool,
+    position: Span,
+    /// Whether the current document has exceeded `max_document_size`, so its text is being
+    /// discarded.
+    oversized: bool,
+    /// Whether the last part of a line didn't include its line break, see `part()`.
+    in_line: bool,
+    /// The `...` line which ended the last document, which isn't part of the document's text.
+    end_marker: Option<String>,
+}
+
+/// `Document` is the result of splitting off a document.
+pub(crate) type Document = Result<(String, Span), YamlSplitError>;
+
+/// `BOM` is the UTF-8 byte order mark, which may start the input but isn't part of its text.
+const BOM: char = '\u{feff}';
+
+/// `marker()` returns the rest of `line` after a `---` or `...` `marker` at its start. A marker
+/// must be followed by whitespace or a line break, either `\n` or `\r\n`, or end the input.
+fn marker<'a>(line: &'a str, marker: &str) -> Option<&'a str> {
+    line.strip_prefix(marker)
+        .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t', '\r', '\n']))
+}
+
+/// `is_content()` returns whether `text` holds anything other than whitespace and a comment.
+fn is_content(text: &str) -> bool {
+    let text = text.trim_start();
+    !text.is_empty() && !text.starts_with('#')
+}
+
+impl Splitter {
+    pub(crate) fn new(options: SplitterOptions) -> Self {
+        Splitter {
+            options,
+            disambiguated: false,
+            in_header: false,
+            current: None,
+            content: false,
+            position: Span { line: 1, offset: 0 },
+            oversized: false,
+            in_line: false,
+            end_marker: None,
+        }
+    }
+
+    /// `max_line()` is the most that should be read of a line at once. Longer lines are passed to
+    /// `part()`, as they exceed `max_document_size` anyway.
+    pub(crate) fn max_line(&self) -> usize {
+        self.options
+            .max_document_size
+            .map_or(usize::MAX, |max| max.saturating_add(1))
+    }
+
+    /// `in_line()` returns whether the next input continues a line given to `part()`.
+    pub(crate) fn in_line(&self) -> bool {
+        self.in_line
+    }
+
+    /// `part()` consumes part of a line which is longer than `max_line()`, and whether it is the
+    /// last part. Such a line always makes its document too large, so only its start is needed,
+    /// to find out whether it starts a new document.
+    pub(crate) fn part(&mut self, part: &[u8], last: bool) -> Option<Document> {
+        if mem::replace(&mut self.in_line, !last) {
+            self.position.offset += part.len();
+            return None;
+        }
+
+        // The text of an oversized document is discarded, so it doesn't matter if the part ends
+        // within a character.
+        let start = self.position.offset;
+        let res = self.line(String::from_utf8_lossy(part).into_owned());
+        self.position.offset = start + part.len();
+        res
+    }
+
+    /// `line()` consumes the next line of input, including its line break, and returns the
+    /// document it ends, if any.
+    pub(crate) fn line(&mut self, mut line: String) -> Option<Document> {
+        let mut line_start = self.position;
+        self.position.line += 1;
+        self.position.offset += line.len();
+
+        // Editors on Windows often start files with a byte order mark, which would otherwise be
+        // taken as the start of a bare document. Spans still count it, to match the source.
+        if line_start.offset == 0 && line.starts_with(BOM) {
+            line.drain(..BOM.len_utf8());
+            line_start.offset += BOM.len_utf8();
+        }
+
+        let directives_end = marker(&line, "---");
+        let end_of_doc = marker(&line, "...").is_some();
 
         // First, we must disambiguate between a bare document and a directive at the top of the
-        // file (before any directive end "---" markers). To do this, we must look for a #, % or
-        // other non-whitespace character as the first character on a line:
+        // file, or after a "..." (before any directive end "---" markers). To do this, we must look
+        // for a #, % or other non-whitespace character as the first character on a line:
         //
         // - # indicates a comment, the line will be ignored
         // - % indicates a directive, we should assume the rest of the header is also a directive as
         //    % is not a valid character at the start of a line, before a --- is seen.
         // - anything else indicates we must currently be looking at a bare document's content
         //
-        // XXX: This loop also builds up buffers that are shared with the next loop. The reader
-        // is also shared and so the next loop will start off where this one ends and assume the
-        // buffers have the correct content.
-        loop {
-            if self.disambiguated {
-                break;
-            }
-
-            // Empty the buffer. read_line appends, and we don't want that.
-            buf.clear();
+        // A "..." here ends no document, so it is kept along with any comments before the next
+        // document, which YAML parsers ignore.
+        if !self.disambiguated {
+            let mut content = false;
 
-            match self.reader.read_line(&mut buf) {
-                Ok(l) => {
-                    if l == 0 {
-                        // We hit EOF already, and it's still not clear
-                        // this file must have only whitespace, comments or be completely empty.
-                        return None;
+            for c in line.chars() {
+                match c {
+                    // Spaces, tabs and carriage returns don't tell us anything,
+                    // keep searching the line.
+                    ' ' | '\t' | '\r' => continue,
+                    // # means this line is a comment, nothing to do.
+                    // \n is a newline, also nothing to do, this line didn't
+                    // tell us anything.
+                    '#' | '\n' => break,
+                    // another "..." before any document, also nothing to do.
+                    '.' if end_of_doc => break,
+                    // % means this line is a directive, we must be in a header
+                    '%' => {
+                        self.disambiguated = true;
+                        self.in_header = true;
+                        break;
                     }
-
-                    for c in buf.chars() {
-                        match c {
-                            // Spaces, tabs and carriage returns don't tell us anything,
-                            // keep searching the line.
-                            ' ' | '\t' | '\r' => continue,
-                            // # means this line is a comment, nothing to do.
-                            // \n is a newline, also nothing to do, this line didn't
-                            // tell us anything.
-                            '#' | '\n' => break,
-                            // % means this line is a directive, we must be in a header
-                            '%' => {
-                                self.disambiguated = true;
-                                self.in_header = true;
-                                break;
-                            }
-                            // anything else must mean we are in a bare document
-                            _ => {
-                                self.disambiguated = true;
-                                self.in_header = false;
-                                break;
-                            }
-                        };
+                    // anything else must mean we are in a bare document, or one which starts
+                    // with "---" and may be empty
+                    _ => {
+                        self.disambiguated = true;
+                        self.in_header = false;
+                        content = directives_end.is_none_or(is_content);
+                        break;
                     }
-
-                    // Append the current line to the document
-                    current_file = current_file + &buf;
-                }
-                Err(e) => {
-                    return Some(Err(e.into()));
-                }
+                };
             }
+
+            // Append the current line to the document
+            self.push(line, line_start, content);
+            return None;
         }
 
         // Now that we know whether we are starting off in a directive or a document, we can
-        // parse the rest of the YAML. In this loop we will look for the start and end of documents
-        // as our YAML parser does not support parsing multiple documents at once.
-        loop {
-            buf.clear();
+        // look for the start and end of documents, as our YAML parser does not support parsing
+        // multiple documents at once.
+        let content = match directives_end {
+            Some(rest) => is_content(rest),
+            None => !self.in_header && is_content(&line),
+        };
 
-            match self.reader.read_line(&mut buf) {
-                Ok(l) => {
-                    let hit_eof = l == 0;
-                    let cf_len = current_file.len();
+        if !self.in_header && directives_end.is_some() {
+            // a new document has started already. To not lose the current line, including any
+            // directives that might be on the line (after the "---"), it starts the next document.
+            let document = self.take(line_start);
+            self.push(line, line_start, content);
+            return document;
+        } else if end_of_doc {
+            // this document has ended, but we don't need this line. The next document may be
+            // bare, or start with a header or "---", so this must be disambiguated again.
+            self.disambiguated = false;
+            let document = self.take(line_start);
+            self.end_marker = Some(line);
+            return document;
+        } else if self.in_header && directives_end.is_some() {
+            self.in_header = false;
+        }
 
-                    // If there is absolutely nothing to do (i.e. the current file data is empty, and
-                    // we're at EOF), just exit the loop.
-                    if hit_eof && cf_len == 0 {
-                        return None;
-                    }
+        self.push(line, line_start, content);
+        None
+    }
 
-                    let end_of_doc = buf.starts_with("...");
-                    let directives_end = buf.starts_with("---");
+    /// `end()` is called at the end of the input, and returns the last document, if any. Input
+    /// with only whitespace and comments contains no documents, and neither do any comments after
+    /// the last "...".
+    pub(crate) fn end(&mut self) -> Option<Document> {
+        if !self.disambiguated {
+            self.current = None;
+            self.oversized = false;
+        }
 
-                    if !self.in_header && directives_end {
-                        // a new document has started already.
-                        self.in_header = false;
-                        // to not lose the current line, including any directives that might
-                        // be on the line (after the "---"), we need to prepend it
-                        // the next time someone calls next()
-                        self.prepend_next = Some(buf);
-                        return Some(Ok(current_file));
-                    } else if end_of_doc {
-                        // this document has ended, but we don't need this line.
-                        // the next line must be a header, or "---"
-                        self.in_header = true;
-                        return Some(Ok(current_file));
-                    } else if hit_eof {
-                        // this document has ended, and nothing will follow.
-                        return Some(Ok(current_file));
-                    } else if self.in_header && directives_end {
-                        self.in_header = false;
-                    }
+        let line_start = self.position;
+        self.current.as_ref()?;
+        self.take(line_start)
+    }
 
-                    current_file = current_file + &buf;
-                }
-                Err(e) => {
-                    return Some(Err(e.into()));
-                }
-            };
+    fn push(&mut self, line: String, line_start: Span, content: bool) {
+        self.content |= content;
+
+        if self.oversized {
+            return;
         }
+
+        let size = self
+            .current
+            .as_ref()
+            .map_or(0, |(document, _)| document.len())
+            + line.len();
+        if self.options.max_document_size.is_some_and(|max| size > max) {
+            // Keep the start of the document, but none of its text.
+            let span = self.current.take().map_or(line_start, |(_, span)| span);
+            self.current = Some((String::new(), span));
+            self.oversized = true;
+            return;
+        }
+
+        match &mut self.current {
+            Some((document, _)) => document.push_str(&line),
+            None => self.current = Some((line, line_start)),
+        }
+    }
+
+    /// `take()` returns the current document, unless it is empty and `keep_empty` is off.
+    fn take(&mut self, line_start: Span) -> Option<Document> {
+        self.end_marker = None;
+        let content = mem::take(&mut self.content);
+        let (document, span) = self
+            .current
+            .take()
+            .unwrap_or_else(|| (String::new(), line_start));
+
+        if mem::take(&mut self.oversized) {
+            return Some(Err(YamlSplitError::DocumentTooLargeError {
+                // Safe to unwrap, only set when there is a limit.
+                max: self.options.max_document_size.unwrap(),
+                span,
+            }));
+        }
+
+        (content || self.options.keep_empty).then_some(Ok((document, span)))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::DocumentIterator;
-    use std::io::BufReader;
+    use crate::{DocumentIterator, Span, SplitterOptions, YamlSplitError};
+    use std::io::{self, BufReader, Read};
 
     fn str_reader(s: &[u8]) -> BufReader<&[u8]> {
         BufReader::new(s)
     }
 
+    /// `Live` returns its chunks one read at a time, like a pipe which is still being written to,
+    /// and panics if it is read past them.
+    struct Live<'a> {
+        chunks: std::slice::Iter<'a, &'a str>,
+    }
+
+    impl Read for Live<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let chunk = self.chunks.next().expect("read past the available input");
+            buf[..chunk.len()].copy_from_slice(chunk.as_bytes());
+            Ok(chunk.len())
+        }
+    }
+
     #[test]
     fn bare_document() {
         let input = "abc: def";
@@ -303,7 +698,7 @@ mod tests {
         assert_eq!(&next, "abc: def");
 
         let fin = doc_iter.next().is_none();
-        assert_eq!(true, fin);
+        assert!(fin);
     }
 
     #[test]
@@ -326,7 +721,7 @@ abc: def
         );
 
         let fin = doc_iter.next().is_none();
-        assert_eq!(true, fin);
+        assert!(fin);
     }
 
     #[test]
@@ -351,7 +746,7 @@ abc: def
         );
 
         let fin = doc_iter.next().is_none();
-        assert_eq!(true, fin);
+        assert!(fin);
     }
 
     #[test]
@@ -376,7 +771,7 @@ aaa: bbb
         );
 
         let fin = doc_iter.next().is_none();
-        assert_eq!(true, fin);
+        assert!(fin);
     }
 
     #[test]
@@ -414,7 +809,7 @@ aaa: bbb
         );
 
         let fin = doc_iter.next().is_none();
-        assert_eq!(true, fin);
+        assert!(fin);
     }
 
     #[test]
@@ -476,6 +871,238 @@ final: "document"
         );
 
         let fin = doc_iter.next().is_none();
-        assert_eq!(true, fin);
+        assert!(fin);
+    }
+
+    #[test]
+    fn document_spans() {
+        let input = r#"# comment
+%YAML 1.2
+---
+abc: def
+...
+
+---
+aaa: bbb
+---
+final: "document"
+"#;
+
+        let reader = str_reader(input.as_bytes());
+        let mut doc_iter = DocumentIterator::new(reader).with_spans();
+
+        let (_, span) = doc_iter.next().unwrap().unwrap();
+        assert_eq!(span, Span { line: 1, offset: 0 });
+
+        let (next, span) = doc_iter.next().unwrap().unwrap();
+        assert_eq!(&next, "\n---\naaa: bbb\n");
+        assert_eq!(
+            span,
+            Span {
+                line: 6,
+                offset: 37
+            }
+        );
+        assert_eq!(&input[span.offset..span.offset + next.len()], next);
+
+        let (next, span) = doc_iter.next().unwrap().unwrap();
+        assert_eq!(
+            span,
+            Span {
+                line: 9,
+                offset: 51
+            }
+        );
+        assert_eq!(&input[span.offset..], next);
+
+        let fin = doc_iter.next().is_none();
+        assert!(fin);
+    }
+
+    #[test]
+    fn size_limit() {
+        let long_line = format!("b: {}\n", "x".repeat(100));
+        let input = format!(
+            "a: 1\n---\n{}c: 2\n---\n{}---\nd: 3\n...\n{}",
+            long_line, long_line, long_line
+        );
+
+        let reader = str_reader(input.as_bytes());
+        let mut doc_iter = DocumentIterator::new(reader)
+            .max_document_size(16)
+            .with_spans();
+
+        let (next, _) = doc_iter.next().unwrap().unwrap();
+        assert_eq!(&next, "a: 1\n");
+
+        for line in [2, 5] {
+            match doc_iter.next().unwrap() {
+                Err(YamlSplitError::DocumentTooLargeError { max: 16, span }) => {
+                    assert_eq!(line, span.line)
+                }
+                res => panic!("unexpected {:?}", res),
+            }
+        }
+
+        let (next, span) = doc_iter.next().unwrap().unwrap();
+        assert_eq!(&next, "---\nd: 3\n");
+        assert_eq!(&input[span.offset..span.offset + next.len()], next);
+
+        assert!(doc_iter.next().unwrap().is_err());
+        assert!(doc_iter.next().is_none());
+
+        // Exactly at the limit.
+        let reader = str_reader(b"a: 1\n---\nb: 2\n");
+        let docs: Vec<_> = DocumentIterator::new(reader)
+            .max_document_size(9)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec!["a: 1\n", "---\nb: 2\n"], docs);
+    }
+
+    #[test]
+    fn live_input() {
+        let chunks = ["a: 1\n", "---\n", "b: 2\n", "...\n", "---\nc: 3\n---\n"];
+        let reader = BufReader::new(Live {
+            chunks: chunks.iter(),
+        });
+        let mut doc_iter = DocumentIterator::new(reader);
+
+        // Each document is returned as soon as its end is read, without waiting for more input.
+        assert_eq!("a: 1\n", doc_iter.next().unwrap().unwrap());
+        assert_eq!("---\nb: 2\n", doc_iter.next().unwrap().unwrap());
+        assert_eq!("---\nc: 3\n", doc_iter.next().unwrap().unwrap());
+    }
+
+    #[test]
+    fn raw_documents() {
+        let inputs = [
+            "abc: def",
+            "\n---\nabc: def\n",
+            "# comment\r\n%YAML 1.2\r\n---\r\nabc: def\r\n... # end\r\n\r\n---\r\naaa: bbb\r\n",
+            "%YAML 1.2\n---\nabc: def\n---\n%YAML: x\n---\naaa: bbb\n...\n---\n...\n...\n# c\nb\n",
+        ];
+
+        for input in inputs {
+            let docs: Vec<_> = DocumentIterator::new(str_reader(input.as_bytes()))
+                .with_raw()
+                .map(Result::unwrap)
+                .collect();
+            let expected: Vec<_> = DocumentIterator::new(str_reader(input.as_bytes()))
+                .with_spans()
+                .map(Result::unwrap)
+                .collect();
+
+            let mut offset = 0;
+            for (doc, (document, span)) in docs.iter().zip(&expected) {
+                assert_eq!(document, doc.document());
+                assert_eq!(*span, doc.span());
+                assert_eq!(offset, doc.range().start);
+                assert_eq!(&input[doc.range()], doc.raw());
+                offset = doc.range().end;
+            }
+
+            assert_eq!(expected.len(), docs.len());
+            assert_eq!(input.len(), offset, "{:?}", input);
+        }
+
+        let input = "# only a comment\n";
+        let mut doc_iter = DocumentIterator::new(str_reader(input.as_bytes())).with_raw();
+        assert!(doc_iter.next().is_none());
+
+        // Comments after the last document belong to no document.
+        let input = "a: b\n... # end\n# tail\n";
+        let docs: Vec<_> = DocumentIterator::new(str_reader(input.as_bytes()))
+            .with_raw()
+            .map(|doc| doc.unwrap().into_raw())
+            .collect();
+        assert_eq!(vec!["a: b\n... # end\n"], docs);
+    }
+
+    #[test]
+    fn byte_order_mark() {
+        let input = "\u{feff}%YAML 1.2\n---\na: b\n---\nc: d\n";
+        let docs: Vec<_> = DocumentIterator::new(str_reader(input.as_bytes()))
+            .with_spans()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(2, docs.len());
+        assert_eq!("%YAML 1.2\n---\na: b\n", docs[0].0);
+        assert_eq!(Span { line: 1, offset: 3 }, docs[0].1);
+        assert_eq!(&input[docs[1].1.offset..], docs[1].0);
+
+        // Only at the start of the input.
+        let input = "\u{feff}a: b\n---\n\u{feff}c\n";
+        let docs: Vec<_> = DocumentIterator::new(str_reader(input.as_bytes()))
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec!["a: b\n", "---\n\u{feff}c\n"], docs);
+    }
+
+    #[test]
+    fn markers() {
+        let input =
+            "a: 1\r\n---\r\nb: 2\r\n----\r\n---b\r\n...c\r\n...\r\n--- c\r\n---\t# d\r\ne\r\n---";
+        let docs: Vec<_> = DocumentIterator::new(str_reader(input.as_bytes()))
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            vec![
+                "a: 1\r\n",
+                "---\r\nb: 2\r\n----\r\n---b\r\n...c\r\n",
+                "--- c\r\n",
+                "---\t# d\r\ne\r\n",
+                "---",
+            ],
+            docs
+        );
+    }
+
+    #[test]
+    fn end_markers() {
+        // A bare document may follow a "...", and ends at the next "---".
+        let input = "a: 1\n...\n# about b\nb: 2\n---\nc: 3\n...\n\n...\n---\nd: 4\n...\n# tail\n";
+        let docs: Vec<_> = DocumentIterator::new(str_reader(input.as_bytes()))
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            vec![
+                "a: 1\n",
+                "# about b\nb: 2\n",
+                "---\nc: 3\n",
+                "\n...\n---\nd: 4\n",
+            ],
+            docs
+        );
+    }
+
+    #[test]
+    fn empty_documents() {
+        let input =
+            "---\n---\n# nothing\n---\na: 1\n...\n--- # nothing\n...\n%YAML 1.2\n--- |\n  b\n";
+        let docs = |keep_empty| -> Vec<_> {
+            let options = SplitterOptions {
+                keep_empty,
+                ..SplitterOptions::default()
+            };
+            DocumentIterator::with_options(str_reader(input.as_bytes()), options)
+                .map(Result::unwrap)
+                .collect()
+        };
+
+        assert_eq!(
+            vec![
+                "---\n",
+                "---\n# nothing\n",
+                "---\na: 1\n",
+                "--- # nothing\n",
+                "%YAML 1.2\n--- |\n  b\n",
+            ],
+            docs(true)
+        );
+        assert_eq!(vec!["---\na: 1\n", "%YAML 1.2\n--- |\n  b\n"], docs(false));
     }
 }
@@ -0,0 +1,304 @@
+use crate::{invalid_utf8, Document, Span, Splitter, SplitterOptions, YamlSplitError};
+use futures_util::io::{AsyncBufRead, AsyncRead, BufReader};
+use futures_util::ready;
+use futures_util::stream::Stream;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// `DocumentStream` is the async equivalent of `DocumentIterator`, a `Stream` of the individual
+/// YAML documents read from an `AsyncRead`, e.g. a network connection. Reading never blocks the
+/// thread, and documents are split in exactly the same way as by `DocumentIterator`.
+///
+/// ```
+/// use futures_util::stream::StreamExt;
+/// use yaml_split::DocumentStream;
+///
+/// let yaml = "hello: world\n---\nhello: rust\n";
+/// let docs: Vec<_> = futures_executor::block_on(DocumentStream::new(yaml.as_bytes()).collect());
+///
+/// assert_eq!("hello: world\n", docs[0].as_ref().unwrap());
+/// assert_eq!("---\nhello: rust\n", docs[1].as_ref().unwrap());
+/// ```
+///
+/// Runtimes with their own `AsyncRead` trait, e.g. tokio, usually provide a compatibility layer
+/// for the futures traits.
+pub struct DocumentStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    reader: BufReader<R>,
+    splitter: Splitter,
+    line: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> DocumentStream<R> {
+    /// `new()` creates a new DocumentStream over a given `reader`'s contents.
+    pub fn new(reader: R) -> DocumentStream<R> {
+        Self::with_options(reader, SplitterOptions::default())
+    }
+
+    /// `with_options()` creates a new DocumentStream over a given `reader`'s contents, which
+    /// splits documents according to `options`, see `DocumentIterator::with_options()`.
+    pub fn with_options(reader: R, options: SplitterOptions) -> DocumentStream<R> {
+        DocumentStream {
+            reader: BufReader::new(reader),
+            splitter: Splitter::new(options),
+            line: Vec::new(),
+        }
+    }
+
+    /// `with_spans()` turns this into a stream which also returns the `Span` at which each
+    /// document starts, see `DocumentIterator::with_spans()`.
+    pub fn with_spans(self) -> SpannedDocumentStream<R> {
+        SpannedDocumentStream { inner: self }
+    }
+
+    /// `max_document_size()` limits the size of each document to `max` bytes, see
+    /// `DocumentIterator::max_document_size()`.
+    pub fn max_document_size(mut self, max: usize) -> Self {
+        self.splitter.options.max_document_size = Some(max);
+        self
+    }
+
+    fn poll_document(&mut self, cx: &mut Context<'_>) -> Poll<Option<Document>> {
+        loop {
+            let buf = match ready!(Pin::new(&mut self.reader).poll_fill_buf(cx)) {
+                Ok(buf) => buf,
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            };
+
+            if buf.is_empty() {
+                // The last line may not have a line break.
+                if !self.line.is_empty() {
+                    if let Some(document) = self.take_line(true) {
+                        return Poll::Ready(Some(document));
+                    }
+                }
+
+                return Poll::Ready(self.splitter.end());
+            }
+
+            let max_len = self.splitter.max_line() - self.line.len();
+            let (len, complete) = match buf.iter().take(max_len).position(|&b| b == b'\n') {
+                Some(i) => (i + 1, true),
+                None => (buf.len().min(max_len), false),
+            };
+            self.line.extend_from_slice(&buf[..len]);
+            Pin::new(&mut self.reader).consume(len);
+
+            // A line which doesn't fit is passed on in parts.
+            if complete || self.line.len() == self.splitter.max_line() {
+                if let Some(document) = self.take_line(complete) {
+                    return Poll::Ready(Some(document));
+                }
+            }
+        }
+    }
+
+    /// `take_line()` passes the line read so far to the splitter, and whether it is complete.
+    /// A complete line must be valid UTF-8.
+    fn take_line(&mut self, complete: bool) -> Option<Document> {
+        let line = mem::take(&mut self.line);
+
+        if !complete || self.splitter.in_line() {
+            return self.splitter.part(&line, complete);
+        }
+
+        match String::from_utf8(line) {
+            Ok(line) => self.splitter.line(line),
+            Err(_) => Some(Err(invalid_utf8())),
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for DocumentStream<R> {
+    type Item = Result<String, YamlSplitError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .poll_document(cx)
+            .map(|res| res.map(|res| res.map(|(document, _)| document)))
+    }
+}
+
+/// `SpannedDocumentStream` is a `DocumentStream` which also returns the `Span` at which each
+/// document starts. See `DocumentStream::with_spans()`.
+pub struct SpannedDocumentStream<R>
+where
+    R: AsyncRead + Unpin,
+{
+    inner: DocumentStream<R>,
+}
+
+impl<R: AsyncRead + Unpin> Stream for SpannedDocumentStream<R> {
+    type Item = Result<(String, Span), YamlSplitError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().inner.poll_document(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DocumentIterator, DocumentStream, SplitterOptions};
+    use futures_executor::block_on;
+    use futures_util::io::AsyncRead;
+    use futures_util::stream::StreamExt;
+    use futures_util::task::noop_waker;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    const INPUT: &str = r#"# comment
+%YAML 1.2
+---
+abc: def
+...
+
+---
+aaa: bbb
+---
+...
+---
+final: "document""#;
+
+    /// `Trickle` returns one byte at a time, and is only ready every other poll.
+    struct Trickle<'a> {
+        input: &'a [u8],
+        ready: bool,
+    }
+
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            match self.input.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.input = rest;
+                    Poll::Ready(Ok(1))
+                }
+                _ => Poll::Ready(Ok(0)),
+            }
+        }
+    }
+
+    #[test]
+    fn matches_iterator() {
+        let expected: Vec<_> = DocumentIterator::new(INPUT.as_bytes())
+            .with_spans()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(4, expected.len());
+
+        let stream = DocumentStream::new(INPUT.as_bytes()).with_spans();
+        let res: Vec<_> = block_on(stream.map(Result::unwrap).collect());
+        assert_eq!(expected, res);
+
+        let reader = Trickle {
+            input: INPUT.as_bytes(),
+            ready: false,
+        };
+        let stream = DocumentStream::new(reader).with_spans();
+        let res: Vec<_> = block_on(stream.map(Result::unwrap).collect());
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn live_input() {
+        /// `Live` returns one chunk per poll, and then never becomes ready again.
+        struct Live(Vec<&'static str>);
+
+        impl AsyncRead for Live {
+            fn poll_read(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+                buf: &mut [u8],
+            ) -> Poll<io::Result<usize>> {
+                if self.0.is_empty() {
+                    return Poll::Pending;
+                }
+
+                let chunk = self.0.remove(0);
+                buf[..chunk.len()].copy_from_slice(chunk.as_bytes());
+                Poll::Ready(Ok(chunk.len()))
+            }
+        }
+
+        let mut stream = DocumentStream::new(Live(vec!["a: 1\n", "---\n", "b: 2\n...\n"]));
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut next = || match stream.poll_next_unpin(&mut cx) {
+            Poll::Ready(Some(res)) => Some(res.unwrap()),
+            _ => None,
+        };
+        assert_eq!(Some("a: 1\n".to_string()), next());
+        assert_eq!(Some("---\nb: 2\n".to_string()), next());
+        assert_eq!(None, next());
+    }
+
+    #[test]
+    fn size_limit() {
+        let input = "a: b\n---\nc: [ddddddddddddddddddddddddd]\n---\ne: f\n---\ng: h";
+        let expected: Vec<_> = DocumentIterator::new(input.as_bytes())
+            .max_document_size(12)
+            .with_spans()
+            .map(|res| res.map_err(|e| e.to_string()))
+            .collect();
+        assert!(expected[1].is_err());
+
+        let reader = Trickle {
+            input: input.as_bytes(),
+            ready: false,
+        };
+        let stream = DocumentStream::new(reader)
+            .max_document_size(12)
+            .with_spans();
+        let res: Vec<_> = block_on(stream.map(|res| res.map_err(|e| e.to_string())).collect());
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn options() {
+        let input = "\u{feff}---\r\n...\r\na: b\r\n---\r\n# c\r\n---\r\nd: e\r\n";
+        let options = SplitterOptions {
+            keep_empty: false,
+            ..SplitterOptions::default()
+        };
+        let expected: Vec<_> = DocumentIterator::with_options(input.as_bytes(), options.clone())
+            .with_spans()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(2, expected.len());
+
+        let reader = Trickle {
+            input: input.as_bytes(),
+            ready: false,
+        };
+        let stream = DocumentStream::with_options(reader, options).with_spans();
+        let res: Vec<_> = block_on(stream.map(Result::unwrap).collect());
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn empty() {
+        let stream = DocumentStream::new(&b"# only a comment\n\n"[..]);
+        assert!(block_on(stream.collect::<Vec<_>>()).is_empty());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut stream = DocumentStream::new(&b"a: \xff\n"[..]);
+        assert!(block_on(stream.next()).unwrap().is_err());
+    }
+}
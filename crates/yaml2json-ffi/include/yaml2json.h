@@ -0,0 +1,36 @@
+#ifndef YAML2JSON_H
+#define YAML2JSON_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Converts every YAML document in the `len` bytes at `yaml` to JSON, with a newline after each
+ * document. `pretty` turns on pretty output. Unlike the command line utility, which reports each
+ * failed document and carries on, the first failure fails the whole conversion.
+ *
+ * Returns a NUL-terminated string, which must be freed with yaml2json_free(). On failure, returns
+ * NULL and, if `error` isn't NULL, points `*error` at a message describing the failure, which must
+ * also be freed with yaml2json_free(). `*error` is set to NULL on success.
+ *
+ * A panic within the library is also returned as a failure, unless it was built with
+ * `panic = 'abort'`, as the `release` profile is. Build with the `release-ffi` profile instead.
+ */
+char *yaml2json_convert(const uint8_t *yaml, size_t len, bool pretty, char **error);
+
+/*
+ * Frees a string returned by yaml2json_convert(), either the output or an error message. Passing
+ * NULL does nothing.
+ */
+void yaml2json_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* YAML2JSON_H */
@@ -0,0 +1,193 @@
+use std::any::Any;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+use yaml2json_rs::{Style, Yaml2Json};
+
+/// `yaml2json_convert()` converts every YAML document in the `len` bytes at `yaml` to JSON, with
+/// a newline after each document. `pretty` turns on pretty output. Unlike the command line
+/// utility, which reports each failed document and carries on, the first failure fails the whole
+/// conversion.
+///
+/// Returns a NUL-terminated string, which must be freed with `yaml2json_free()`. On failure,
+/// returns NULL and, if `error` isn't NULL, points `*error` at a message describing the failure,
+/// which must also be freed with `yaml2json_free()`. `*error` is set to NULL on success.
+///
+/// A panic is also returned as a failure, rather than unwinding into C, unless the library was
+/// built with `panic = 'abort'`, as the `release` profile is. Build with the `release-ffi`
+/// profile instead.
+///
+/// # Safety
+///
+/// `yaml` must point to `len` readable bytes, and may only be NULL if `len` is 0. `error` must be
+/// NULL or point to a writable `char *`.
+#[no_mangle]
+pub unsafe extern "C" fn yaml2json_convert(
+    yaml: *const u8,
+    len: usize,
+    pretty: bool,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let res = if yaml.is_null() && len > 0 {
+        Err("yaml is NULL".to_string())
+    } else {
+        let input = if len == 0 {
+            &[][..]
+        } else {
+            slice::from_raw_parts(yaml, len)
+        };
+
+        catch_panic(|| convert(input, pretty))
+    };
+
+    match res {
+        Ok(output) => {
+            if !error.is_null() {
+                *error = ptr::null_mut();
+            }
+            into_c_string(output)
+        }
+        Err(message) => {
+            if !error.is_null() {
+                *error = into_c_string(message.into_bytes());
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+fn convert(input: &[u8], pretty: bool) -> Result<Vec<u8>, String> {
+    let y2j = Yaml2Json::new(if pretty {
+        Style::PRETTY
+    } else {
+        Style::COMPACT
+    });
+    let mut output = Vec::new();
+
+    y2j.reader_to_writer(input, &mut output)
+        .map(|_| output)
+        .map_err(|e| e.to_string())
+}
+
+/// `catch_panic()` runs `f`, turning a panic into an error, as unwinding into C is undefined
+/// behaviour.
+fn catch_panic<F: FnOnce() -> Result<Vec<u8>, String>>(f: F) -> Result<Vec<u8>, String> {
+    // Nothing from `f` is used after a panic, so it can't be seen in a broken state.
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| Err(panic_message(payload)))
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str));
+
+    match message {
+        Some(message) => format!("panicked: {}", message),
+        None => "panicked".to_string(),
+    }
+}
+
+/// `yaml2json_free()` frees a string returned by `yaml2json_convert()`, either the output or an
+/// error message. Passing NULL does nothing.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by `yaml2json_convert()` which hasn't been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn yaml2json_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// `into_c_string()` hands `bytes` over to C. JSON output never contains NUL bytes, as they are
+/// escaped, but error messages can quote input which does, so these are dropped.
+fn into_c_string(bytes: Vec<u8>) -> *mut c_char {
+    let s = CString::new(bytes).unwrap_or_else(|e| {
+        let mut bytes = e.into_vec();
+        bytes.retain(|&b| b != 0);
+        CString::new(bytes).unwrap()
+    });
+
+    s.into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{catch_panic, yaml2json_convert, yaml2json_free};
+    use std::ffi::CStr;
+    use std::os::raw::c_char;
+    use std::ptr;
+
+    /// `convert()` calls `yaml2json_convert()` as C would, returning the output or error message.
+    fn convert(yaml: &[u8], pretty: bool) -> Result<String, String> {
+        let mut error: *mut c_char = ptr::null_mut();
+
+        unsafe {
+            let output = yaml2json_convert(yaml.as_ptr(), yaml.len(), pretty, &mut error);
+            let res = if output.is_null() {
+                assert!(!error.is_null());
+                Err(CStr::from_ptr(error).to_string_lossy().into_owned())
+            } else {
+                assert!(error.is_null());
+                Ok(CStr::from_ptr(output).to_string_lossy().into_owned())
+            };
+
+            yaml2json_free(output);
+            yaml2json_free(error);
+            res
+        }
+    }
+
+    #[test]
+    fn converts() {
+        assert_eq!(
+            Ok("{\"a\":1}\n[\"b\"]\n".to_string()),
+            convert(b"a: 1\n---\n- b", false)
+        );
+        assert_eq!(Ok("{\n  \"a\": 1\n}\n".to_string()), convert(b"a: 1", true));
+        assert_eq!(Ok(String::new()), convert(b"", false));
+        assert_eq!(Ok("{\"a\":2}\n".to_string()), convert(b"a: 1\na: 2", false));
+        assert_eq!(Ok("\"\\u0000\"\n".to_string()), convert(b"\"\\0\"", false));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Err("mapping values are not allowed in this context at line 2 column 3".to_string()),
+            convert(b"a: 1\n b: 2", false)
+        );
+        assert!(convert(b"a: [1", false).is_err());
+
+        unsafe {
+            assert!(yaml2json_convert(ptr::null(), 1, false, ptr::null_mut()).is_null());
+            yaml2json_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn panics() {
+        // Keep the expected panics out of the test output.
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(|_| {}));
+        let res = (
+            catch_panic(|| panic!("at the disco")),
+            catch_panic(|| panic!("{} {}", "at", "the disco")),
+            catch_panic(|| std::panic::panic_any(1)),
+        );
+        std::panic::set_hook(hook);
+
+        assert_eq!(
+            (
+                Err("panicked: at the disco".to_string()),
+                Err("panicked: at the disco".to_string()),
+                Err("panicked".to_string()),
+            ),
+            res
+        );
+        assert_eq!(Ok(b"a".to_vec()), catch_panic(|| Ok(b"a".to_vec())));
+    }
+}
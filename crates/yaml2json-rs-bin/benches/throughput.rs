@@ -0,0 +1,67 @@
+//! `throughput` measures how quickly the `yaml2json` binary converts a stream of many small
+//! documents, which is dominated by the cost of writing the output. Run it with
+//! `cargo bench -p yaml2json-rs-bin`, optionally passing the number of documents.
+
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Instant;
+
+const RUNS: usize = 3;
+
+fn main() {
+    let docs = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(200_000);
+    let input: String = (0..docs)
+        .map(|i| format!("---\nid: {}\nname: item\ntags: [a, b]\n", i))
+        .collect();
+
+    for args in [&[][..], &["--pretty"][..], &["--jobs", "0"][..]] {
+        let mut best = f64::MAX;
+        let mut output = 0;
+
+        for _ in 0..RUNS {
+            let (secs, len) = run(args, input.clone());
+            best = best.min(secs);
+            output = len;
+        }
+
+        println!(
+            "{:<12} {} docs in {:.3}s: {:.0} docs/s, {:.1} MiB/s of output",
+            format!("{:?}", args),
+            docs,
+            best,
+            docs as f64 / best,
+            output as f64 / best / (1024.0 * 1024.0),
+        );
+    }
+}
+
+/// `run()` converts `input` from stdin to a pipe, returning the time taken and the size of the
+/// output.
+fn run(args: &[&str], input: String) -> (f64, usize) {
+    let start = Instant::now();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_yaml2json"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run yaml2json");
+
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(input.as_bytes()).unwrap());
+
+    let mut output = Vec::new();
+    child
+        .stdout
+        .take()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+    writer.join().unwrap();
+    assert!(child.wait().unwrap().success());
+
+    (start.elapsed().as_secs_f64(), output.len())
+}
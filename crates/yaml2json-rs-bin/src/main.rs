@@ -1,15 +1,24 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::fs::File;
-use std::io::{Read, Stderr, Stdout};
+use std::io::{BufRead, BufReader, BufWriter, IsTerminal, Read, Stderr, StdoutLock, Write};
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Instant;
 use std::{io, process};
 
 use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
+use clap_complete::{generate, Shell};
 
 use anyhow::bail;
+use jsonschema::Validator;
+use serde_json::{json, Map, Value};
 use std::fmt::{Display, Formatter};
-use yaml2json_rs::{Style, Yaml2Json};
-use yaml_split::{DocumentIterator, YamlSplitError};
+use yaml2json_rs::{
+    merge, Binary, DuplicateKeys, EnvSubst, Format, Indent, Input, KeyMode, Limits, MergeArrays,
+    MergeKeys, PathPattern, Query, SpecialFloats, Style, UndefinedVars, UnknownTags, Yaml2Json,
+    Yaml2JsonError, Yaml2JsonOptions, YamlVersion,
+};
 
 #[derive(Clone, clap::ValueEnum)]
 enum ErrorStyle {
@@ -45,37 +54,387 @@ impl Display for ErrorStyle {
     }
 }
 
-/// `ErrorPrinter` allows you to configure how errors will be printed.
+/// `ErrorContext` describes where an error happened, so that users can track down its cause.
+#[derive(Clone, Copy, Default)]
+struct ErrorContext<'a> {
+    /// The file being read, or `None` for stdin.
+    file: Option<&'a str>,
+    /// The index of the document within the file, starting from 0.
+    doc: Option<usize>,
+    /// The line and column of the error within the file, starting from 1.
+    location: Option<(usize, usize)>,
+    /// The JSON Pointer to the value within the document which failed schema validation.
+    pointer: Option<&'a str>,
+    /// The conversion error, for the details it carries.
+    error: Option<&'a Yaml2JsonError>,
+}
+
+impl<'a> ErrorContext<'a> {
+    fn file(file: Option<&'a str>) -> Self {
+        ErrorContext {
+            file,
+            ..Default::default()
+        }
+    }
+
+    fn doc(self, doc: usize) -> Self {
+        ErrorContext {
+            doc: Some(doc),
+            ..self
+        }
+    }
+
+    fn pointer(self, pointer: &'a str) -> Self {
+        ErrorContext {
+            pointer: Some(pointer),
+            ..self
+        }
+    }
+
+    /// `error()` adds the location and other details carried by `e`.
+    fn error(self, e: &'a Yaml2JsonError) -> Self {
+        ErrorContext {
+            location: e.location().or(self.location),
+            error: Some(e),
+            ..self
+        }
+    }
+
+    fn file_name(&self) -> &str {
+        self.file.unwrap_or("<stdin>")
+    }
+
+    /// `to_json()` describes an error with `message` for `ErrorStyle::Json`, as an object with the
+    /// message, the kind of error, and whichever parts of the context are known.
+    fn to_json(self, kind: ErrorKind, message: String) -> Value {
+        let mut error = Map::new();
+        error.insert("message".to_string(), message.into());
+        error.insert("kind".to_string(), kind.name().into());
+
+        if let Some(e) = self.error {
+            error.insert("code".to_string(), e.code().into());
+            error.extend(details(e));
+        }
+
+        error.insert("file".to_string(), self.file_name().into());
+
+        if let Some(doc) = self.doc {
+            error.insert("doc".to_string(), doc.into());
+        }
+
+        if let Some((line, column)) = self.location {
+            error.insert("line".to_string(), line.into());
+            error.insert("column".to_string(), column.into());
+        }
+
+        if let Some(pointer) = self.pointer {
+            error.insert("pointer".to_string(), pointer.into());
+        }
+
+        json!({ "yaml-error": error })
+    }
+}
+
+/// `details()` returns the fields of `e` which aren't already part of its context or message.
+fn details(e: &Yaml2JsonError) -> Vec<(String, Value)> {
+    let details = match e {
+        Yaml2JsonError::NonStringKeyError { key, .. }
+        | Yaml2JsonError::DuplicateKeyError { key, .. } => vec![("key", json!(key))],
+        Yaml2JsonError::LimitExceededError { limit, max } => {
+            vec![("limit", json!(limit.code())), ("max", json!(max))]
+        }
+        Yaml2JsonError::InvalidQueryError(query) | Yaml2JsonError::QueryNotFoundError { query } => {
+            vec![("query", json!(query))]
+        }
+        Yaml2JsonError::InvalidPatternError(pattern) => vec![("pattern", json!(pattern))],
+        Yaml2JsonError::EncodeError { format, .. } => vec![("format", json!(format.to_string()))],
+        Yaml2JsonError::UndefinedVariableError { name, .. } => vec![("variable", json!(name))],
+        _ => Vec::new(),
+    };
+
+    details
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect()
+}
+
+impl Display for ErrorContext<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file_name())?;
+
+        if let Some(doc) = self.doc {
+            write!(f, " (document {})", doc)?;
+        }
+
+        if let Some(pointer) = self.pointer.filter(|p| !p.is_empty()) {
+            write!(f, " at {}", pointer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `ErrorKind` classifies errors by the exit code they result in.
+#[derive(Clone, Copy)]
+enum ErrorKind {
+    /// Reading or writing failed part way through.
+    IO = 1,
+    /// A document could not be converted.
+    Conversion = 3,
+    /// An input file does not exist or could not be opened.
+    Input = 4,
+    /// A document does not match the schema given with `--schema`.
+    Validation = 5,
+}
+
+impl ErrorKind {
+    /// `name()` is the name of the kind in `--error=json` output.
+    fn name(self) -> &'static str {
+        match self {
+            ErrorKind::IO => "io",
+            ErrorKind::Conversion => "conversion",
+            ErrorKind::Input => "input",
+            ErrorKind::Validation => "validation",
+        }
+    }
+}
+
+/// `ErrorPrinter` allows you to configure how errors will be printed. It also keeps track of
+/// the first error, which decides the exit code, and with `--summary`, of the documents of each
+/// input which were converted or failed.
+///
+/// It holds the only handle to stdout, which is locked and buffered for the whole run, so that
+/// converted documents and JSON errors are written in order without a system call each.
 struct ErrorPrinter {
     pretty: bool,
+    /// Formats JSON errors like the documents, with `--pretty` and `--ascii`.
+    json: Yaml2Json,
     print_style: ErrorStyle,
-    stdout: Stdout,
+    fail_fast: bool,
+    first_error: Option<ErrorKind>,
+    summary: Option<Summary>,
+    stdout: BufWriter<StdoutLock<'static>>,
     stderr: Stderr,
 }
 
 impl ErrorPrinter {
-    fn new(print_style: ErrorStyle, pretty: bool) -> Self {
+    fn new(
+        print_style: ErrorStyle,
+        pretty: bool,
+        ascii: bool,
+        fail_fast: bool,
+        summary: Option<SummaryStyle>,
+    ) -> Self {
         Self {
             pretty,
+            json: Yaml2Json::with_options(Yaml2JsonOptions::new().pretty(pretty).ascii(ascii)),
             print_style,
-            stdout: io::stdout(),
+            fail_fast,
+            first_error: None,
+            summary: summary.map(Summary::new),
+            stdout: BufWriter::with_capacity(64 * 1024, io::stdout().lock()),
             stderr: io::stderr(),
         }
     }
 
-    fn print(&mut self, d: impl Display) {
+    /// `start()` begins the `--summary` tally for the input in `ctx`.
+    fn start(&mut self, ctx: ErrorContext) {
+        if let Some(summary) = &mut self.summary {
+            summary.files.push(FileSummary::new(ctx.file_name()));
+        }
+    }
+
+    /// `converted()` counts a document of the input in `ctx` which was output, for `--summary`.
+    fn converted(&mut self, ctx: ErrorContext) {
+        if let Some(file) = self.summary.as_mut().and_then(|s| s.file(ctx)) {
+            file.converted += 1;
+        }
+    }
+
+    /// `stdout()` returns the buffered stdout, for writing converted documents.
+    fn stdout(&mut self) -> &mut impl Write {
+        &mut self.stdout
+    }
+
+    /// `flush()` writes anything buffered for stdout, exiting if that fails.
+    fn flush(&mut self) {
+        if self.stdout.flush().is_err() {
+            process::exit(1);
+        }
+    }
+
+    /// `exit()` ends the program, with a non-zero exit code if any errors were printed.
+    fn exit(&mut self) -> ! {
+        // process::exit() skips destructors, so anything still buffered must be written now.
+        self.flush();
+
+        if let Some(summary) = &self.summary {
+            write_or_exit(&mut self.stderr, summary.report(self.pretty));
+        }
+
+        match self.first_error {
+            Some(kind) => process::exit(kind as i32),
+            None => process::exit(0),
+        }
+    }
+
+    fn print(&mut self, ctx: ErrorContext, kind: ErrorKind, d: impl Display) {
+        self.first_error.get_or_insert(kind);
+
+        if let Some(summary) = &mut self.summary {
+            summary.failed(ctx, &d);
+        }
+
         match self.print_style {
             ErrorStyle::Silent => {}
-            ErrorStyle::Stderr => write_or_exit(&mut self.stderr, &format!("{}\n", d)),
+            ErrorStyle::Stderr => {
+                // Keep the error after the output which came before it.
+                self.flush();
+                write_or_exit(&mut self.stderr, format!("{}: {}\n", ctx, d))
+            }
             ErrorStyle::Json => {
-                let s = if self.pretty {
-                    format!("{{\n  \"yaml-error\": \"{}\"\n}}\n", d)
-                } else {
-                    format!("{{\"yaml-error\":\"{}\"}}\n", d)
-                };
-                write_or_exit(&mut self.stdout, &s);
+                let error = ctx.to_json(kind, d.to_string());
+                // Safe to unwrap, a Value without floats always serializes.
+                let mut s = self.json.value_to_string(&error).unwrap();
+                s.push('\n');
+                write_or_exit(&mut self.stdout, s);
             }
         };
+
+        if self.fail_fast {
+            self.exit();
+        }
+    }
+}
+
+/// `SummaryStyle` is the format of the `--summary` report.
+#[derive(Clone, Copy, PartialEq)]
+enum SummaryStyle {
+    Text,
+    Json,
+}
+
+/// `Summary` is the tally of documents for `--summary`, reported to stderr on exit.
+struct Summary {
+    style: SummaryStyle,
+    start: Instant,
+    files: Vec<FileSummary>,
+}
+
+/// `FileSummary` is the `--summary` tally for one input.
+struct FileSummary {
+    file: String,
+    converted: usize,
+    failed: usize,
+    /// Why the input as a whole failed, e.g. because it doesn't exist.
+    error: Option<String>,
+    /// The last document which failed, so that a document with several errors counts once.
+    last_failed: Option<usize>,
+}
+
+impl FileSummary {
+    fn new(file: &str) -> Self {
+        FileSummary {
+            file: file.to_string(),
+            converted: 0,
+            failed: 0,
+            error: None,
+            last_failed: None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut file = json!({
+            "file": self.file,
+            "converted": self.converted,
+            "failed": self.failed,
+        });
+        if let Some(error) = &self.error {
+            file["error"] = json!(error);
+        }
+        file
+    }
+}
+
+impl Summary {
+    fn new(style: SummaryStyle) -> Self {
+        Summary {
+            style,
+            start: Instant::now(),
+            files: Vec::new(),
+        }
+    }
+
+    /// `file()` returns the tally for the input being converted, if `ctx` is for it rather
+    /// than e.g. the `--merge` output.
+    fn file(&mut self, ctx: ErrorContext) -> Option<&mut FileSummary> {
+        self.files
+            .last_mut()
+            .filter(|file| file.file == ctx.file_name())
+    }
+
+    /// `failed()` counts the error `d` against the document or input in `ctx`.
+    fn failed(&mut self, ctx: ErrorContext, d: &impl Display) {
+        let file = match self.file(ctx) {
+            Some(file) => file,
+            None => return,
+        };
+
+        match ctx.doc {
+            Some(doc) if file.last_failed != Some(doc) => {
+                file.failed += 1;
+                file.last_failed = Some(doc);
+            }
+            Some(_) => {}
+            None => {
+                file.error.get_or_insert_with(|| d.to_string());
+            }
+        }
+    }
+
+    /// `report()` formats the summary, with a line for each input followed by the totals, or
+    /// as a single JSON object.
+    fn report(&self, pretty: bool) -> String {
+        let converted: usize = self.files.iter().map(|file| file.converted).sum();
+        let failed: usize = self.files.iter().map(|file| file.failed).sum();
+        let elapsed = self.start.elapsed().as_secs_f64();
+
+        if self.style == SummaryStyle::Json {
+            let summary = json!({"summary": {
+                "files": self.files.iter().map(FileSummary::to_json).collect::<Vec<_>>(),
+                "converted": converted,
+                "failed": failed,
+                "elapsed": elapsed,
+            }});
+            // Safe to unwrap, a Value always serializes.
+            let s = if pretty {
+                serde_json::to_string_pretty(&summary).unwrap()
+            } else {
+                serde_json::to_string(&summary).unwrap()
+            };
+            return s + "\n";
+        }
+
+        let mut report = String::new();
+        for file in &self.files {
+            match &file.error {
+                Some(error) => report.push_str(&format!("{}: {}\n", file.file, error)),
+                None => report.push_str(&format!(
+                    "{}: {} converted, {} failed\n",
+                    file.file, file.converted, file.failed
+                )),
+            }
+        }
+        report.push_str(&format!(
+            "{} file{}, {} document{} converted, {} failed in {:.3}s\n",
+            self.files.len(),
+            if self.files.len() == 1 { "" } else { "s" },
+            converted,
+            if converted == 1 { "" } else { "s" },
+            failed,
+            elapsed
+        ));
+        report
     }
 }
 
@@ -83,56 +442,757 @@ impl ErrorPrinter {
 // as otherwise the program may panic.
 // As this program's entire purpose is to write data to stdout / stderr
 // any failure here means we should just exit cleanly with an error code.
-fn write_or_exit(io: &mut dyn io::Write, s: &str) {
-    let w = io.write(s.as_bytes());
-
-    if w.is_err() {
+fn write_or_exit(io: &mut impl io::Write, s: impl AsRef<[u8]>) {
+    if io.write_all(s.as_ref()).is_err() {
         process::exit(1);
     }
 }
 
-fn write(yaml2json: &Yaml2Json, ep: &mut ErrorPrinter, read: impl Read) {
-    let doc_iter = DocumentIterator::new(read);
-    let mut printed_last = false;
-    let mut stdout = io::stdout();
+/// `validate()` prints every way in which `value` doesn't match `schema`, returning whether it
+/// matched.
+fn validate(schema: &Validator, ep: &mut ErrorPrinter, ctx: ErrorContext, value: &Value) -> bool {
+    let mut valid = true;
+
+    for e in schema.iter_errors(value) {
+        valid = false;
+        ep.print(
+            ctx.pointer(e.instance_path().as_str()),
+            ErrorKind::Validation,
+            &e,
+        );
+    }
+
+    valid
+}
+
+/// `Output` describes how each converted document is output.
+struct Output<'a> {
+    yaml2json: Yaml2Json,
+    /// Written between documents, and after the last one.
+    separator: &'static str,
+    schema: Option<Validator>,
+    /// The `--split-output` pattern, if documents are written to their own files.
+    split_output: Option<&'a str>,
+    /// Whether each document is wrapped with the file and index it came from.
+    with_meta: bool,
+    /// The comments collected for `--comments-out`, if they are being collected.
+    comments: Option<RefCell<Vec<Value>>>,
+    /// Whether stdout is flushed after each document, rather than when its buffer is full.
+    unbuffered: bool,
+    /// How each input is decompressed before it is converted.
+    decompress: Decompress,
+    /// Whether documents are only checked, and the output discarded.
+    check: bool,
+    /// The `--in-place` options, if each input is converted into a file next to it.
+    in_place: Option<InPlace<'a>>,
+    /// Whether only the front matter of each input is converted.
+    front_matter: bool,
+    /// The key the rest of each input is added under, with `--front-matter`.
+    content_key: Option<&'a str>,
+    /// With `--merge`, how arrays are merged, and the documents merged so far.
+    merge: Option<(MergeArrays, RefCell<Option<Value>>)>,
+    /// Whether documents are converted straight to JSON text, as nothing needs them as values.
+    direct: bool,
+    /// The documents of each input which are output, or every document if this is empty.
+    docs: Vec<Docs>,
+    /// How many documents are converted at once, on their own threads, or 0 for one per CPU.
+    jobs: usize,
+    /// Whether each document is written as it is converted, rather than once it has been read.
+    stream: bool,
+}
+
+/// `Docs` is a document index or range given with `--doc`, starting from 0. Ranges don't include
+/// their end, and negative indices count back from the end of each input, e.g. `-1` is its last
+/// document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Docs {
+    Index(isize),
+    Range(Option<isize>, Option<isize>),
+}
+
+impl FromStr for Docs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let index = |s: &str| match s.parse() {
+            Ok(i) => Ok(i),
+            Err(_) => bail!("expected a document index or range, e.g. 0, 2..5 or -1"),
+        };
+        let bound = |s: &str| match s {
+            "" => Ok(None),
+            _ => index(s).map(Some),
+        };
+
+        match s.split_once("..") {
+            Some((start, end)) => Ok(Docs::Range(bound(start)?, bound(end)?)),
+            None => index(s).map(Docs::Index),
+        }
+    }
+}
+
+impl Docs {
+    /// `contains()` returns whether document `doc` is selected, given the number of documents in
+    /// the input, if it is known yet. Until then, `doc` must be followed by at least `lookahead()`
+    /// more documents, so it is before anything negative indices select.
+    fn contains(self, doc: usize, len: Option<usize>) -> bool {
+        let resolve = |i: isize| match len {
+            _ if i >= 0 => i as usize,
+            Some(len) => len.saturating_sub(i.unsigned_abs()),
+            None => usize::MAX,
+        };
 
-    for res in doc_iter {
-        // print a newline between regular output lines
-        if printed_last {
-            write_or_exit(&mut stdout, "\n");
+        match self {
+            Docs::Index(i) if i < 0 => {
+                len.and_then(|len| len.checked_sub(i.unsigned_abs())) == Some(doc)
+            }
+            Docs::Index(i) => doc == i as usize,
+            Docs::Range(start, end) => {
+                start.map_or(0, resolve) <= doc && doc < end.map_or(usize::MAX, resolve)
+            }
         }
+    }
+
+    /// `lookahead()` returns how many documents must be read after one before it is known whether
+    /// it is selected, i.e. the furthest this counts back from the end.
+    fn lookahead(self) -> usize {
+        let bounds = match self {
+            Docs::Index(i) => [Some(i), None],
+            Docs::Range(start, end) => [start, end],
+        };
 
-        printed_last = false;
+        bounds
+            .iter()
+            .flatten()
+            .filter(|i| **i < 0)
+            .map(|i| i.unsigned_abs())
+            .max()
+            .unwrap_or(0)
+    }
 
-        match res {
-            Ok(doc) => match yaml2json.document_to_writer(&doc, &mut stdout) {
-                Ok(_) => printed_last = true,
-                Err(e) => ep.print(e),
-            },
-            Err(e) => match e {
-                // If there is an IOError, we should just exit.
-                YamlSplitError::IOError(_) => process::exit(1),
+    /// `end()` returns the index of the first document after those selected, if it doesn't depend
+    /// on the number of documents.
+    fn end(self) -> Option<usize> {
+        match self {
+            _ if self.lookahead() > 0 => None,
+            Docs::Index(i) => Some(i as usize + 1),
+            Docs::Range(_, end) => end.map(|end| end as usize),
+        }
+    }
+}
+
+/// `select()` filters the documents of an input by `--doc`, along with the index of each. Reading
+/// stops after the last selected document, unless some are counted back from the end. I/O errors
+/// are never filtered out.
+fn select<'a>(
+    documents: impl Iterator<Item = Result<Converted, Yaml2JsonError>> + 'a,
+    docs: &'a [Docs],
+) -> Box<dyn Iterator<Item = (usize, Result<Converted, Yaml2JsonError>)> + 'a> {
+    if docs.is_empty() {
+        return Box::new(documents.enumerate());
+    }
+
+    let lookahead = docs.iter().map(|d| d.lookahead()).max().unwrap_or(0);
+    let end = docs
+        .iter()
+        .map(|d| d.end())
+        .collect::<Option<Vec<_>>>()
+        .and_then(|ends| ends.into_iter().max());
+    let mut documents = documents.enumerate().take(end.unwrap_or(usize::MAX));
+    let mut pending = VecDeque::new();
+    let mut len = None;
+    let mut seen = 0;
+
+    Box::new(std::iter::from_fn(move || loop {
+        if len.is_none() && pending.len() <= lookahead {
+            match documents.next() {
+                Some((doc, res)) => {
+                    if !matches!(res, Err(Yaml2JsonError::IOError(_))) {
+                        seen = doc + 1;
+                    }
+                    pending.push_back((doc, res));
+                }
+                None => len = Some(seen),
+            }
+            continue;
+        }
+
+        let (doc, res) = pending.pop_front()?;
+        if matches!(res, Err(Yaml2JsonError::IOError(_)))
+            || docs.iter().any(|d| d.contains(doc, len))
+        {
+            return Some((doc, res));
+        }
+    }))
+}
+
+/// `split_path()` expands a `--split-output` pattern for document `doc` of `file`.
+fn split_path(pattern: &str, file: Option<&str>, doc: usize) -> String {
+    let mut path = String::new();
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('d') => path.push_str(&doc.to_string()),
+            Some('f') => match file {
+                Some(f) => {
+                    let stem = Path::new(f).file_stem().unwrap_or_default();
+                    path.push_str(&stem.to_string_lossy());
+                }
+                None => path.push_str("stdin"),
             },
+            Some('%') => path.push('%'),
+            // Anything else is left as it is.
+            Some(c) => {
+                path.push('%');
+                path.push(c);
+            }
+            None => path.push('%'),
         }
     }
 
-    if printed_last {
-        // Add final newline
-        write_or_exit(&mut stdout, "\n");
+    path
+}
+
+/// `Decompress` is the `--decompress` mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Decompress {
+    /// Decompress gzip and zstd input, recognised by its first bytes.
+    Auto,
+    Gzip,
+    Zstd,
+    None,
+}
+
+/// `ReadSend` is an input which can be read from another thread, for `--jobs`.
+trait ReadSend: Read + Send {}
+
+impl<R: Read + Send> ReadSend for R {}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// `decompress()` wraps `read` to decompress it as `mode` says. Neither magic number is valid
+/// UTF-8, so `Decompress::Auto` never mistakes YAML or JSON for compressed input.
+fn decompress(read: impl Read + Send + 'static, mode: Decompress) -> io::Result<Box<dyn ReadSend>> {
+    let mut read = BufReader::new(read);
+
+    let mode = match mode {
+        Decompress::Auto => {
+            let start = read.fill_buf()?;
+
+            if start.starts_with(GZIP_MAGIC) {
+                Decompress::Gzip
+            } else if start.starts_with(ZSTD_MAGIC) {
+                Decompress::Zstd
+            } else {
+                Decompress::None
+            }
+        }
+        mode => mode,
+    };
+
+    match mode {
+        Decompress::Gzip => gzip(read),
+        Decompress::Zstd => zstd(read),
+        _ => Ok(Box::new(read)),
     }
 }
 
-fn main() {
+#[cfg(feature = "gzip")]
+fn gzip(read: impl BufRead + Send + 'static) -> io::Result<Box<dyn ReadSend>> {
+    // Concatenated gzip files are read as one, as with zcat.
+    Ok(Box::new(flate2::bufread::MultiGzDecoder::new(read)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip(_read: impl BufRead) -> io::Result<Box<dyn ReadSend>> {
+    Err(unsupported("gzip"))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd(read: impl BufRead + Send + 'static) -> io::Result<Box<dyn ReadSend>> {
+    Ok(Box::new(zstd::stream::read::Decoder::with_buffer(read)?))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd(_read: impl BufRead) -> io::Result<Box<dyn ReadSend>> {
+    Err(unsupported("zstd"))
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd")))]
+fn unsupported(format: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} input needs yaml2json to be built with the {} feature",
+            format, format
+        ),
+    )
+}
+
+/// `is_url()` returns whether an input is an HTTP(S) URL, rather than a path.
+fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// `fetch()` requests `url`, returning the body of the response to read from.
+#[cfg(feature = "http")]
+fn fetch(url: &str) -> Result<impl Read, ureq::Error> {
+    Ok(ureq::get(url).call()?.into_body().into_reader())
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(_url: &str) -> Result<io::Empty, &'static str> {
+    Err("URL input needs yaml2json to be built with the http feature")
+}
+
+#[cfg(feature = "arbitrary-precision")]
+fn preserve_numbers(options: Yaml2JsonOptions) -> Result<Yaml2JsonOptions, &'static str> {
+    Ok(options.preserve_numbers(true))
+}
+
+#[cfg(not(feature = "arbitrary-precision"))]
+fn preserve_numbers(_options: Yaml2JsonOptions) -> Result<Yaml2JsonOptions, &'static str> {
+    Err("--preserve-numbers needs yaml2json to be built with the arbitrary-precision feature")
+}
+
+/// `write()` converts every document in `read`, writing them to stdout or to their own files.
+/// The input is read as a whole first when collecting comments, so it can be read twice.
+fn write(
+    output: &Output,
+    ep: &mut ErrorPrinter,
+    ctx: ErrorContext,
+    read: impl Read + Send + 'static,
+) {
+    let mut read = match decompress(read, output.decompress) {
+        Ok(read) => read,
+        Err(e) => return ep.print(ctx, ErrorKind::Input, e),
+    };
+
+    let comments = match &output.comments {
+        Some(comments) => comments,
+        None => return write_values(output, ep, ctx, read),
+    };
+
+    let mut input = Vec::new();
+    if let Err(e) = read.read_to_end(&mut input) {
+        ep.print(ctx, ErrorKind::IO, e);
+        ep.exit();
+    }
+
+    // Errors are reported by the conversion instead.
+    for (doc, res) in output.yaml2json.stream_comments(&input[..]).enumerate() {
+        for comment in res.into_iter().flatten() {
+            comments.borrow_mut().push(json!({
+                "file": ctx.file_name(),
+                "doc": doc,
+                "path": comment.path,
+                "line": comment.line,
+                "comment": comment.text,
+            }));
+        }
+    }
+
+    write_values(output, ep, ctx, io::Cursor::new(input));
+}
+
+/// `write_values()` does the conversion for `write()`.
+fn write_values(
+    output: &Output,
+    ep: &mut ErrorPrinter,
+    ctx: ErrorContext,
+    read: impl Read + Send + 'static,
+) {
+    if output.stream {
+        return stream(output, ep, ctx, read);
+    }
+
+    let documents: Box<dyn Iterator<Item = Result<Converted, Yaml2JsonError>>> =
+        if output.front_matter {
+            Box::new(std::iter::once(
+                front_matter(output, read).map(Converted::Value),
+            ))
+        } else if output.jobs != 1 && output.direct {
+            Box::new(
+                output
+                    .yaml2json
+                    .stream_parallel(read, output.jobs)
+                    .map(|r| r.map(Converted::Json)),
+            )
+        } else if output.jobs != 1 {
+            Box::new(
+                output
+                    .yaml2json
+                    .stream_values_parallel(read, output.jobs)
+                    .map(|r| r.map(Converted::Value)),
+            )
+        } else if output.direct {
+            Box::new(
+                output
+                    .yaml2json
+                    .stream(read)
+                    .map(|r| r.map(Converted::Json)),
+            )
+        } else {
+            Box::new(
+                output
+                    .yaml2json
+                    .stream_values(read)
+                    .map(|r| r.map(Converted::Value)),
+            )
+        };
+
+    // With --in-place, the output for the whole input, until a document fails.
+    let mut in_place = Some(Vec::new());
+
+    for (doc, res) in select(documents, &output.docs) {
+        match res {
+            Ok(Converted::Value(value)) if output.merge.is_some() => {
+                if let Some((arrays, merged)) = &output.merge {
+                    match &mut *merged.borrow_mut() {
+                        // Empty documents, e.g. an empty overlay, don't replace anything.
+                        _ if value.is_null() => {}
+                        Some(merged) => merge(merged, value, *arrays),
+                        merged => *merged = Some(value),
+                    }
+                }
+                ep.converted(ctx);
+            }
+            Ok(converted) => {
+                let mut buf = match converted {
+                    Converted::Json(json) => json.into_bytes(),
+                    Converted::Value(value) => match value_bytes(output, ep, ctx, doc, value) {
+                        Some(buf) => buf,
+                        None => {
+                            in_place = None;
+                            continue;
+                        }
+                    },
+                };
+                buf.extend_from_slice(output.separator.as_bytes());
+
+                match output.split_output {
+                    _ if output.check => {}
+                    _ if output.in_place.is_some() => {
+                        if let Some(in_place) = &mut in_place {
+                            in_place.extend_from_slice(&buf);
+                        }
+                    }
+                    Some(pattern) => {
+                        let path = split_path(pattern, ctx.file, doc);
+
+                        if let Err(e) = std::fs::write(&path, buf) {
+                            ep.print(ctx.doc(doc), ErrorKind::IO, format!("{}: {}", path, e));
+                            continue;
+                        }
+                    }
+                    None => {
+                        write_or_exit(ep.stdout(), buf);
+
+                        if output.unbuffered {
+                            ep.flush();
+                        }
+                    }
+                }
+                ep.converted(ctx);
+            }
+            // If there is an IOError, we should just exit.
+            Err(e @ Yaml2JsonError::IOError(_)) => {
+                ep.print(ctx.doc(doc).error(&e), ErrorKind::IO, &e);
+                ep.exit();
+            }
+            Err(e) => {
+                in_place = None;
+                ep.print(ctx.doc(doc).error(&e), ErrorKind::Conversion, &e)
+            }
+        }
+    }
+
+    if let (Some(options), Some(buf), Some(file)) = (&output.in_place, in_place, ctx.file) {
+        options.write(ep, ctx, file, buf);
+    }
+}
+
+/// `stream()` does the conversion for `write()` with `--stream`, writing each document as it is
+/// converted. An error ends the input, as the documents after it can't be found without parsing
+/// it.
+fn stream(output: &Output, ep: &mut ErrorPrinter, ctx: ErrorContext, read: impl Read) {
+    let mut docs = 0;
+    let res = if output.check {
+        output
+            .yaml2json
+            .reader_to_writer_with(read, &mut io::sink(), |_, doc| docs = doc + 1)
+    } else {
+        let unbuffered = output.unbuffered;
+
+        output
+            .yaml2json
+            .reader_to_writer_with(read, ep.stdout(), |stdout, doc| {
+                docs = doc + 1;
+
+                if unbuffered && stdout.flush().is_err() {
+                    process::exit(1);
+                }
+            })
+    };
+
+    for _ in 0..docs {
+        ep.converted(ctx);
+    }
+
+    match res {
+        Ok(()) => {}
+        Err(e @ Yaml2JsonError::IOError(_)) => {
+            ep.print(ctx.doc(docs).error(&e), ErrorKind::IO, &e);
+            ep.exit();
+        }
+        Err(e) => ep.print(ctx.doc(docs).error(&e), ErrorKind::Conversion, &e),
+    }
+}
+
+/// `convert_path()` converts the input `f` named on the command line or in a `--files-from` list,
+/// which is a file, a URL, or `-` for stdin.
+fn convert_path(output: &Output, ep: &mut ErrorPrinter, f: &str) {
+    let path = Path::new(f);
+    let ctx = ErrorContext::file((f != "-").then_some(f));
+    ep.start(ctx);
+
+    if output.in_place.is_some() && (f == "-" || is_url(f)) {
+        ep.print(ctx, ErrorKind::Input, "--in-place needs a file");
+    } else if f == "-" {
+        // Unlike its lock, stdin can be sent to the thread which splits the input for --jobs.
+        write(output, ep, ctx, io::stdin());
+    } else if is_url(f) {
+        match fetch(f) {
+            Ok(body) => write(output, ep, ctx, body),
+            Err(e) => ep.print(ctx, ErrorKind::Input, e),
+        }
+    } else if !path.exists() {
+        ep.print(ctx, ErrorKind::Input, "file does not exist");
+    } else if path.is_dir() {
+        ep.print(ctx, ErrorKind::Input, "file is a directory")
+    } else {
+        let file = File::open(f);
+
+        match file {
+            Ok(file) => write(output, ep, ctx, file),
+            Err(e) => ep.print(ctx, ErrorKind::Input, e),
+        }
+    }
+}
+
+/// `stdin_is_list()` reports `-` as an input when stdin is the `--files-from` list.
+fn stdin_is_list(ep: &mut ErrorPrinter) {
+    let ctx = ErrorContext::file(None);
+    ep.start(ctx);
+    ep.print(
+        ctx,
+        ErrorKind::Input,
+        "stdin is already read for --files-from",
+    );
+}
+
+/// `convert_list()` converts each of the inputs listed in the file `list`, or stdin for `-`, for
+/// `--files-from`. Entries are separated by line breaks, or by NUL characters with `null`, and
+/// are converted as soon as they are read, so a command writing the list may still be running.
+fn convert_list(output: &Output, ep: &mut ErrorPrinter, list: &str, null: bool) {
+    let ctx = ErrorContext::file((list != "-").then_some(list));
+    let mut read: Box<dyn BufRead> = if list == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match File::open(list) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => return ep.print(ctx, ErrorKind::Input, e),
+        }
+    };
+    let delimiter = if null { b'\0' } else { b'\n' };
+    let mut entry = Vec::new();
+
+    loop {
+        entry.clear();
+        match read.read_until(delimiter, &mut entry) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(e) => {
+                ep.print(ctx, ErrorKind::IO, e);
+                ep.exit();
+            }
+        }
+
+        if entry.last() == Some(&delimiter) {
+            entry.pop();
+        }
+        if !null && entry.last() == Some(&b'\r') {
+            entry.pop();
+        }
+
+        match std::str::from_utf8(&entry) {
+            Ok("") => {}
+            // stdin is locked for reading the list.
+            Ok("-") if list == "-" => stdin_is_list(ep),
+            Ok(f) => convert_path(output, ep, f),
+            Err(_) => {
+                let f = String::from_utf8_lossy(&entry).into_owned();
+                let ctx = ErrorContext::file(Some(&f));
+                ep.start(ctx);
+                ep.print(ctx, ErrorKind::Input, "file name is not valid UTF-8");
+            }
+        }
+    }
+}
+
+/// `InPlace` is the `--in-place` options.
+struct InPlace<'a> {
+    /// Replaces the extension of each input for its output file.
+    suffix: &'a str,
+    /// Whether each input is deleted once its output has been written.
+    delete_source: bool,
+}
+
+impl InPlace<'_> {
+    /// `write()` writes the output `buf` for `file` next to it, through a temporary file which is
+    /// then renamed, so that the output file is never left partly written.
+    fn write(&self, ep: &mut ErrorPrinter, ctx: ErrorContext, file: &str, buf: Vec<u8>) {
+        let source = Path::new(file);
+        let stem = source.file_stem().unwrap_or_default().to_string_lossy();
+        let dest = source.with_file_name(format!("{}{}", stem, self.suffix));
+        let temp = source.with_file_name(format!(".{}{}.{}.tmp", stem, self.suffix, process::id()));
+
+        let res = std::fs::write(&temp, buf).and_then(|_| std::fs::rename(&temp, &dest));
+        if let Err(e) = res {
+            let _ = std::fs::remove_file(&temp);
+            return ep.print(ctx, ErrorKind::IO, format!("{}: {}", dest.display(), e));
+        }
+
+        // The source may have been converted onto itself, e.g. when reformatting JSON.
+        if self.delete_source && dest != source {
+            if let Err(e) = std::fs::remove_file(source) {
+                ep.print(ctx, ErrorKind::IO, e);
+            }
+        }
+    }
+}
+
+/// `front_matter()` reads the whole of `read`, and converts its front matter.
+fn front_matter(output: &Output, mut read: impl Read) -> Result<Value, Yaml2JsonError> {
+    let mut input = String::new();
+    read.read_to_string(&mut input)?;
+
+    output
+        .yaml2json
+        .front_matter_to_value(&input, output.content_key)
+}
+
+/// `Converted` is a document converted by `write_values()`, as JSON text when nothing needs it as
+/// a value, see `Output::direct`.
+enum Converted {
+    Json(String),
+    Value(Value),
+}
+
+/// `value_bytes()` validates and outputs document `doc`, returning `None` if it was rejected.
+fn value_bytes(
+    output: &Output,
+    ep: &mut ErrorPrinter,
+    ctx: ErrorContext,
+    doc: usize,
+    value: Value,
+) -> Option<Vec<u8>> {
+    if let Some(schema) = &output.schema {
+        if !validate(schema, ep, ctx.doc(doc), &value) {
+            return None;
+        }
+    }
+
+    let value = if output.with_meta {
+        json!({"file": ctx.file_name(), "doc": doc, "data": value})
+    } else {
+        value
+    };
+
+    let mut buf = Vec::new();
+
+    match output.yaml2json.value_to_writer(&value, &mut buf) {
+        Ok(_) => Some(buf),
+        Err(e) => {
+            ep.print(ctx.doc(doc).error(&e), ErrorKind::Conversion, &e);
+            None
+        }
+    }
+}
+
+/// `load_schema()` reads and compiles the JSON Schema in `file`, exiting if that fails.
+fn load_schema(ep: &mut ErrorPrinter, file: &str) -> Validator {
+    let ctx = ErrorContext::file(Some(file));
+    let res = std::fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|s| {
+            Yaml2Json::new(Style::COMPACT)
+                .document_to_value(&s)
+                .map_err(|e| e.to_string())
+        })
+        .and_then(|schema| jsonschema::validator_for(&schema).map_err(|e| e.to_string()));
+
+    match res {
+        Ok(validator) => validator,
+        Err(e) => {
+            ep.print(ctx, ErrorKind::Input, format!("invalid schema: {}", e));
+            ep.exit();
+        }
+    }
+}
+
+fn parse_indent(s: &str) -> Result<Indent, String> {
+    match s {
+        "tab" => Ok(Indent::Tab),
+        _ => s
+            .parse()
+            .map(Indent::Spaces)
+            .map_err(|_| r#"expected a number of spaces or "tab""#.to_string()),
+    }
+}
+
+fn parse_limit(s: &str) -> Result<Option<usize>, String> {
+    match s {
+        "none" => Ok(None),
+        _ => s
+            .parse()
+            .map(Some)
+            .map_err(|_| r#"expected a number or "none""#.to_string()),
+    }
+}
+
+/// `app()` describes the command line, for parsing it and for generating shell completions.
+fn app() -> App<'static> {
     let usage = r#"./yaml2json file1.yaml file2.yaml
 
     cat file1.yaml | ./yaml2json
 
+    cat file2.yaml | ./yaml2json file1.yaml - file3.yaml
+
     ./yaml2json --error=json file1.yaml | jq"#;
-    let matches = App::new(crate_name!())
+    let exit_codes = r#"EXIT CODES:
+    0    All documents were converted
+    1    Reading input or writing output failed
+    2    Invalid arguments
+    3    A document could not be converted
+    4    An input file does not exist or could not be opened
+    5    A document does not match the schema
+
+If there are multiple errors, the exit code is decided by the first one."#;
+    App::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!())
         .about(crate_description!())
         .usage(usage)
+        .after_help(exit_codes)
+        // A file named help can be converted as before.
+        .disable_help_subcommand(true)
         .arg(
             Arg::with_name("pretty")
                 .takes_value(false)
@@ -144,54 +1204,644 @@ fn main() {
                 .takes_value(true)
                 .short('e')
                 .long("error")
+                .possible_values(["silent", "stderr", "json"])
+                .default_value("json")
+        )
+        .arg(
+            Arg::with_name("input")
+                .help("Parse the input as YAML or JSON, or as JSON if it starts with { or [, falling back to YAML from the first line which isn't valid JSON. JSON input may contain multiple values, e.g. newline delimited JSON.")
+                .takes_value(true)
+                .long("input")
+                .possible_values(["auto", "yaml", "json"])
+                .default_value("auto")
+        )
+        .arg(
+            Arg::with_name("to")
+                .help("Output JSON, binary MessagePack or CBOR, or TOML. Options which format JSON, e.g. --pretty, don't apply to the other formats.")
+                .takes_value(true)
+                .long("to")
+                .possible_values(["json", "msgpack", "cbor", "toml"])
                 .default_value("json")
         )
+        .arg(
+            Arg::with_name("split-output")
+                .help("Write each document to its own file instead of stdout. In the path, %d is replaced by the index of the document within its input, %f by the input's file name without its extension, and %% by %.")
+                .takes_value(true)
+                .value_name("PATH")
+                .long("split-output")
+        )
+        .arg(
+            Arg::with_name("check")
+                .help("Convert every document, but only report errors, without any other output. The exit code is non-zero if any document fails.")
+                .takes_value(false)
+                .long("check")
+                .conflicts_with_all(&["split-output", "in-place"])
+        )
+        .arg(
+            Arg::with_name("in-place")
+                .help("Write the output for each input file to a file next to it, with its extension replaced by --suffix, e.g. config.yaml to config.json. If any document in a file fails, nothing is written for it.")
+                .takes_value(false)
+                .long("in-place")
+                .requires("file")
+                .conflicts_with_all(&["split-output", "merge"])
+        )
+        .arg(
+            Arg::with_name("suffix")
+                .help("With --in-place, the suffix which replaces the extension of each input. Defaults to the extension of the --to format, e.g. .json.")
+                .takes_value(true)
+                .value_name("SUFFIX")
+                .long("suffix")
+                .requires("in-place")
+        )
+        .arg(
+            Arg::with_name("delete-source")
+                .help("With --in-place, delete each input file once its output has been written.")
+                .takes_value(false)
+                .long("delete-source")
+                .requires("in-place")
+        )
+        .arg(
+            Arg::with_name("with-meta")
+                .help("Wrap each document as {\"file\": ..., \"doc\": N, \"data\": ...}, with the file and the index of the document within it.")
+                .takes_value(false)
+                .long("with-meta")
+        )
+        .arg(
+            Arg::with_name("comments-key")
+                .help("Keep the comments in each document, under KEY in its root mapping, as {\"/path/to/node\": \"comment\"}.")
+                .takes_value(true)
+                .value_name("KEY")
+                .long("comments-key")
+        )
+        .arg(
+            Arg::with_name("comments-out")
+                .help("Write the comments in every document to FILE, as a JSON array of {\"file\", \"doc\", \"path\", \"line\", \"comment\"}.")
+                .takes_value(true)
+                .value_name("FILE")
+                .long("comments-out")
+        )
+        .arg(
+            Arg::with_name("decompress")
+                .help("Decompress gzip or zstd input, or with \"auto\", any input which starts like a gzip or zstd file.")
+                .takes_value(true)
+                .long("decompress")
+                .possible_values(["auto", "gzip", "zstd", "none"])
+                .default_value("auto")
+        )
+        .arg(
+            Arg::with_name("indent")
+                .help("Indent pretty output by N spaces, or by a tab with \"tab\". Implies --pretty.")
+                .takes_value(true)
+                .value_name("N|tab")
+                .long("indent")
+                .validator(parse_indent)
+        )
+        .arg(
+            Arg::with_name("color")
+                .help("Highlight the JSON output. \"auto\" does so when stdout is a terminal and NO_COLOR isn't set.")
+                .takes_value(true)
+                .long("color")
+                .possible_values(["auto", "always", "never"])
+                .default_value("auto")
+        )
+        .arg(
+            Arg::with_name("doc")
+                .help("Only output these documents of each input, by their index from 0, or a range which doesn't include its end, e.g. 0, 2..5, or 3.. for every document from 3. Negative indices count back from the end, e.g. -1 is the last document. Can be given more than once.")
+                .takes_value(true)
+                .value_name("INDEX")
+                .multiple_occurrences(true)
+                .use_value_delimiter(true)
+                .allow_hyphen_values(true)
+                .long("doc")
+                .validator(|s| s.parse::<Docs>())
+        )
+        .arg(
+            Arg::with_name("query")
+                .help("Output only the value at a JSON Pointer, e.g. /spec/containers/0, or a dot path, e.g. spec.containers.0, from each document.")
+                .takes_value(true)
+                .value_name("PATH")
+                .short('q')
+                .long("query")
+                .validator(|s| s.parse::<Query>())
+        )
+        .arg(
+            Arg::with_name("redact")
+                .help("Replace the values at PATTERN with \"***\" in every document, e.g. to scrub credentials. PATTERN is a dot path or JSON Pointer as for --query, where * matches any characters in a key and ** any number of keys, e.g. **.password or env.*_TOKEN. Mappings and sequences are replaced as a whole. Repeat this to redact more paths.")
+                .takes_value(true)
+                .value_name("PATTERN")
+                .multiple_occurrences(true)
+                .number_of_values(1)
+                .allow_hyphen_values(true)
+                .long("redact")
+                .validator(|s| s.parse::<PathPattern>())
+        )
+        .arg(
+            Arg::with_name("schema")
+                .help("Validate each document against a JSON Schema, in JSON or YAML. Documents which don't match are reported as errors instead of being output.")
+                .takes_value(true)
+                .value_name("FILE")
+                .long("schema")
+        )
+        .arg(
+            Arg::with_name("sort-keys")
+                .help("Sort the keys of every JSON object, instead of keeping the order of the YAML input.")
+                .takes_value(false)
+                .long("sort-keys")
+        )
+        .arg(
+            Arg::with_name("ascii")
+                .help("Escape every non-ASCII character in strings as \\uXXXX, for consumers which can't handle UTF-8. Characters beyond U+FFFF are escaped as UTF-16 surrogate pairs.")
+                .takes_value(false)
+                .long("ascii")
+        )
+        .arg(
+            Arg::with_name("canonical")
+                .help("Output canonical JSON (RFC 8785), to hash or compare documents byte for byte: keys sorted by UTF-16 code units, no whitespace, and numbers formatted as in JavaScript. Overrides --pretty, --indent and --color.")
+                .takes_value(false)
+                .long("canonical")
+                .conflicts_with("ascii")
+        )
+        .arg(
+            Arg::with_name("preserve-numbers")
+                .help("Output integers beyond 64 bits and decimals with more than 15 significant digits exactly as written, e.g. for IDs and amounts of money. Without it, such integers fail to convert and such decimals become the nearest float. Needs yaml2json to be built with the arbitrary-precision feature.")
+                .takes_value(false)
+                .long("preserve-numbers")
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .help("Convert up to N documents at once, each on its own thread, or one per CPU with 0. This speeds up large inputs with many documents, including from stdin, and the output is in the same order. Each input is still converted one after another. Doesn't apply to --front-matter, or to input detected as JSON.")
+                .takes_value(true)
+                .value_name("N")
+                .short('j')
+                .long("jobs")
+                .default_value("1")
+                .validator(|s| s.parse::<usize>())
+        )
+        .arg(
+            Arg::with_name("stream")
+                .help("Write each document as it is converted, without holding it in memory, for very large documents. Input is read as YAML unless --input json is given. Keeping the last value of duplicate keys, the default, holds each mapping in memory until its end, but not the sequences around it. --sort-keys, --canonical, --query, --redact and --to still hold each document in memory. The first error ends each input, and may leave the output of its document unfinished.")
+                .takes_value(false)
+                .long("stream")
+                .conflicts_with_all(&["jobs", "doc", "split-output", "in-place", "merge", "with-meta", "schema", "front-matter", "comments-key", "comments-out", "max-doc-size"])
+        )
+        .arg(
+            Arg::with_name("fail-fast")
+                .help("Stop at the first error, instead of continuing with the next document.")
+                .takes_value(false)
+                .long("fail-fast")
+        )
+        .arg(
+            Arg::with_name("summary")
+                .help("Print a report to stderr once done, with the number of documents of each input which were converted or failed, and the time taken. --summary=json prints it as JSON.")
+                .takes_value(true)
+                .value_name("FORMAT")
+                .min_values(0)
+                .require_equals(true)
+                .default_missing_value("text")
+                .possible_values(["text", "json"])
+                .long("summary")
+        )
+        .arg(
+            Arg::with_name("unbuffered")
+                .help("Flush the output after each document, e.g. when following a stream. This is done anyway when stdout is a terminal. Each document is output as soon as the --- or ... after it is read, and each value of input detected as JSON at the end of its line.")
+                .takes_value(false)
+                .long("unbuffered")
+        )
+        .arg(
+            Arg::with_name("front-matter")
+                .help("Convert only the YAML front matter of each input, e.g. a Markdown file, between --- lines at its start. An input without front matter is converted as an empty document.")
+                .takes_value(false)
+                .long("front-matter")
+                .conflicts_with("comments-out")
+        )
+        .arg(
+            Arg::with_name("content-key")
+                .help("With --front-matter, add the rest of each input to its front matter under KEY, e.g. --content-key content.")
+                .takes_value(true)
+                .value_name("KEY")
+                .long("content-key")
+                .requires("front-matter")
+        )
+        .arg(
+            Arg::with_name("merge")
+                .help("Deep-merge every document, from every input, into one document, which is output at the end. Later documents take precedence over earlier ones, and --query is applied to each document before it is merged. Empty and null documents are skipped.")
+                .takes_value(false)
+                .long("merge")
+                .conflicts_with_all(&["split-output", "with-meta"])
+        )
+        .arg(
+            Arg::with_name("merge-arrays")
+                .help("With --merge, replace arrays with the array from the later document, or append its items.")
+                .takes_value(true)
+                .long("merge-arrays")
+                .possible_values(["replace", "append"])
+                .default_value("replace")
+        )
+        .arg(
+            Arg::with_name("env-subst")
+                .help("Replace ${NAME} and ${NAME:-default} in scalars with the value of the environment variable NAME, after the YAML is parsed. $${ is a literal ${.")
+                .takes_value(false)
+                .long("env-subst")
+        )
+        .arg(
+            Arg::with_name("env-allow")
+                .help("Only replace references to these variables, leaving any others as they are.")
+                .takes_value(true)
+                .value_name("NAMES")
+                .multiple_occurrences(true)
+                .use_value_delimiter(true)
+                .requires("env-subst")
+                .long("env-allow")
+        )
+        .arg(
+            Arg::with_name("env-undefined")
+                .help("Replace references to variables which aren't set, and have no default, with nothing, or fail the document with \"error\".")
+                .takes_value(true)
+                .possible_values(["empty", "error"])
+                .default_value("empty")
+                .requires("env-subst")
+                .long("env-undefined")
+        )
+        .arg(
+            Arg::with_name("alias-refs")
+                .help("Output aliases as references to their anchored node, {\"$ref\": \"#/path/to/anchor\"}, instead of copies of it. --alias-refs=KEY uses KEY instead of $ref.")
+                .takes_value(true)
+                .value_name("KEY")
+                .min_values(0)
+                .require_equals(true)
+                .default_missing_value("$ref")
+                .long("alias-refs")
+        )
+        .arg(
+            Arg::with_name("merge-keys")
+                .help("Resolve merge keys (<<) into the mapping containing them, keep them as regular keys, or fail on them.")
+                .takes_value(true)
+                .long("merge-keys")
+                .possible_values(["resolve", "keep", "error"])
+                .default_value("keep")
+        )
+        .arg(
+            Arg::with_name("duplicate-keys")
+                .help("Keep the last value of mapping keys which appear more than once, or the first, or fail on them. Keeping the last value holds each mapping in memory until its end, with --stream.")
+                .takes_value(true)
+                .long("duplicate-keys")
+                .possible_values(["error", "first", "last"])
+                .default_value("last")
+        )
+        .arg(
+            Arg::with_name("key-mode")
+                .help("Convert mapping keys which aren't strings, e.g. 1 or true, to strings, or fail on them.")
+                .takes_value(true)
+                .long("key-mode")
+                .possible_values(["stringify", "error"])
+                .default_value("stringify")
+        )
+        .arg(
+            Arg::with_name("yaml-version")
+                .help("Resolve unquoted values such as yes, off and 0755 using the rules of YAML 1.1 or 1.2.")
+                .takes_value(true)
+                .long("yaml-version")
+                .possible_values(["1.1", "1.2"])
+                .default_value("1.2")
+        )
+        .arg(
+            Arg::with_name("binary")
+                .help("Output !!binary data as a base64 string or an array of byte values, or fail on it.")
+                .takes_value(true)
+                .long("binary")
+                .possible_values(["base64", "bytes", "error"])
+                .default_value("base64")
+        )
+        .arg(
+            Arg::with_name("special-floats")
+                .help("Output .nan and .inf as null, as \"NaN\" and \"Infinity\" strings, or fail on them.")
+                .takes_value(true)
+                .long("special-floats")
+                .possible_values(["null", "string", "error"])
+                .default_value("null")
+        )
+        .arg(
+            Arg::with_name("unknown-tags")
+                .help("Fail on application-defined tags, e.g. !vault, or ignore them and convert their nodes as if they were untagged. Tags of the YAML schemas, e.g. !!set, are always ignored, unless --strict is given.")
+                .takes_value(true)
+                .long("unknown-tags")
+                .possible_values(["error", "ignore"])
+                .default_value("error")
+        )
+        .arg(
+            Arg::with_name("strict")
+                .help("Fail on anything which can't be converted faithfully: unknown tags, non-string keys, duplicate keys, NaN and infinity, binary data, and numbers which would lose precision, unless --preserve-numbers keeps them. Overrides --key-mode, --duplicate-keys, --special-floats, --binary and --unknown-tags.")
+                .takes_value(false)
+                .long("strict")
+        )
+        .arg(
+            Arg::with_name("normalize-timestamps")
+                .help("Convert timestamps, e.g. 2001-12-14 21:59:43 -5, to RFC 3339 strings.")
+                .takes_value(false)
+                .long("normalize-timestamps")
+        )
+        .arg(
+            Arg::with_name("max-depth")
+                .help("Limit how deeply sequences and mappings may be nested.")
+                .takes_value(true)
+                .value_name("N")
+                .long("max-depth")
+                .default_value("128")
+                .validator(|s| s.parse::<usize>().map_err(|_| "expected a number"))
+        )
+        .arg(
+            Arg::with_name("max-alias-expansions")
+                .help("Limit how many aliases may be expanded per document.")
+                .takes_value(true)
+                .value_name("N|none")
+                .long("max-alias-expansions")
+                .default_value("10000")
+                .validator(parse_limit)
+        )
+        .arg(
+            Arg::with_name("max-output-size")
+                .help("Limit the approximate JSON size per document, in bytes.")
+                .takes_value(true)
+                .value_name("N|none")
+                .long("max-output-size")
+                .default_value("none")
+                .validator(parse_limit)
+        )
+        .arg(
+            Arg::with_name("max-doc-size")
+                .help("Limit the size of each YAML document, in bytes. Larger documents are skipped with an error, without being read into memory.")
+                .takes_value(true)
+                .value_name("N|none")
+                .long("max-doc-size")
+                .default_value("none")
+                .validator(parse_limit)
+        )
+        .arg(
+            Arg::with_name("files-from")
+                .help("Also convert each of the files listed in FILE, one per line, after any given as arguments. With - the list is read from stdin, e.g. find . -name '*.yaml' | yaml2json --files-from -. Files are converted as they are listed.")
+                .takes_value(true)
+                .value_name("FILE")
+                .long("files-from")
+        )
+        .arg(
+            Arg::with_name("null")
+                .help("Separate the entries of the --files-from list with NUL characters instead of line breaks, e.g. from find -print0.")
+                .takes_value(false)
+                .short('0')
+                .long("null")
+                .requires("files-from")
+        )
         .arg(
             Arg::with_name("file")
-                .help("Specify the path to files you want to convert. You can also pass files via stdin instead.")
+                .help("Specify the path to files you want to convert. You can also pass files via stdin instead, or with - among the paths. HTTP(S) URLs are fetched, if built with the http feature.")
                 .multiple(true)
         )
-        .get_matches();
+        .subcommand(
+            App::new("completions")
+                .about("Print a completion script for the shell, e.g. yaml2json completions bash > /etc/bash_completion.d/yaml2json")
+                .arg(
+                    Arg::with_name("shell")
+                        .required(true)
+                        .possible_values(["bash", "zsh", "fish", "powershell"])
+                )
+        )
+}
+
+fn main() {
+    let matches = app().get_matches();
+
+    if let Some(completions) = matches.subcommand_matches("completions") {
+        // Safe to unwrap, the shell is required and one of the possible values.
+        let shell: Shell = completions.value_of("shell").unwrap().parse().unwrap();
+        let stdout = io::stdout();
+
+        generate(shell, &mut app(), "yaml2json", &mut stdout.lock());
+        return;
+    }
 
     let fileopt = matches.values_of("file");
-    let pretty = matches.is_present("pretty");
+    let indent = matches.value_of("indent").map(|i| parse_indent(i).unwrap());
+    let pretty = matches.is_present("pretty") || indent.is_some();
+    let fail_fast = matches.is_present("fail-fast");
+    let sort_keys = matches.is_present("sort-keys");
+    let input = match matches.value_of("input").unwrap() {
+        "yaml" => Input::Yaml,
+        "json" => Input::Json,
+        _ if matches.is_present("stream") => Input::Yaml,
+        _ => Input::Auto,
+    };
+    let format = match matches.value_of("to").unwrap() {
+        "msgpack" => Format::MessagePack,
+        "cbor" => Format::Cbor,
+        "toml" => Format::Toml,
+        _ => Format::Json,
+    };
+    let color = match matches.value_of("color").unwrap() {
+        "always" => true,
+        "never" => false,
+        _ => io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    };
+    let merge_keys = match matches.value_of("merge-keys").unwrap() {
+        "resolve" => MergeKeys::Resolve,
+        "error" => MergeKeys::Error,
+        _ => MergeKeys::Keep,
+    };
+    let duplicate_keys = match matches.value_of("duplicate-keys").unwrap() {
+        "error" => DuplicateKeys::Error,
+        "first" => DuplicateKeys::First,
+        _ => DuplicateKeys::Last,
+    };
+    let key_mode = match matches.value_of("key-mode").unwrap() {
+        "error" => KeyMode::Error,
+        _ => KeyMode::Stringify,
+    };
+    let yaml_version = match matches.value_of("yaml-version").unwrap() {
+        "1.1" => YamlVersion::V1_1,
+        _ => YamlVersion::V1_2,
+    };
+    let binary = match matches.value_of("binary").unwrap() {
+        "bytes" => Binary::Bytes,
+        "error" => Binary::Error,
+        _ => Binary::Base64,
+    };
+    let special_floats = match matches.value_of("special-floats").unwrap() {
+        "string" => SpecialFloats::String,
+        "error" => SpecialFloats::Error,
+        _ => SpecialFloats::Null,
+    };
+    let unknown_tags = match matches.value_of("unknown-tags").unwrap() {
+        "ignore" => UnknownTags::Ignore,
+        _ => UnknownTags::Error,
+    };
+    let decompress = match matches.value_of("decompress").unwrap() {
+        "gzip" => Decompress::Gzip,
+        "zstd" => Decompress::Zstd,
+        "none" => Decompress::None,
+        _ => Decompress::Auto,
+    };
+    let limits = Limits {
+        max_depth: matches.value_of("max-depth").unwrap().parse().unwrap(),
+        max_alias_expansions: parse_limit(matches.value_of("max-alias-expansions").unwrap())
+            .unwrap(),
+        max_output_size: parse_limit(matches.value_of("max-output-size").unwrap()).unwrap(),
+        max_document_size: parse_limit(matches.value_of("max-doc-size").unwrap()).unwrap(),
+    };
     let error: ErrorStyle = matches
         .value_of("error")
         .unwrap()
         .parse()
         .expect(r#"invalid error value, expected one of "silent", "stderr" or "json""#);
 
-    let mut ep = ErrorPrinter::new(error, pretty);
-    let yaml2json_style = if pretty {
-        Style::PRETTY
-    } else {
-        Style::COMPACT
-    };
-    let yaml2json = Yaml2Json::new(yaml2json_style);
+    let summary = matches.value_of("summary").map(|style| match style {
+        "json" => SummaryStyle::Json,
+        _ => SummaryStyle::Text,
+    });
 
-    // if: files are provided as arguments, read those instead of stdin
-    if let Some(files) = fileopt {
-        for f in files {
-            let path = Path::new(f);
+    let ascii = matches.is_present("ascii");
+    let mut ep = ErrorPrinter::new(error, pretty, ascii, fail_fast, summary);
+    let mut options = Yaml2JsonOptions::new()
+        .pretty(pretty)
+        .input(input)
+        .format(format)
+        .sort_keys(sort_keys)
+        .color(color)
+        .merge_keys(merge_keys)
+        .duplicate_keys(duplicate_keys)
+        .key_mode(key_mode)
+        .yaml_version(yaml_version)
+        .binary(binary)
+        .special_floats(special_floats)
+        .unknown_tags(unknown_tags)
+        .normalize_timestamps(matches.is_present("normalize-timestamps"))
+        .ascii(ascii)
+        .canonical(matches.is_present("canonical"))
+        .limits(limits);
 
-            if !path.exists() {
-                ep.print(format!("file {} does not exist", path.display()));
-            } else if path.is_dir() {
-                ep.print(format!("{} is a directory", path.display()))
-            } else {
-                let file = File::open(f);
+    if let Some(indent) = indent {
+        options = options.indent(indent);
+    }
 
-                match file {
-                    Ok(f) => write(&yaml2json, &mut ep, f),
-                    Err(e) => ep.print(e),
-                }
+    if matches.is_present("strict") {
+        options = options.strict(true);
+    }
+
+    if matches.is_present("preserve-numbers") {
+        options = match preserve_numbers(options) {
+            Ok(options) => options,
+            Err(e) => app().error(clap::ErrorKind::ArgumentConflict, e).exit(),
+        };
+    }
+
+    if let Some(query) = matches.value_of("query") {
+        options = options.query(query.parse().unwrap());
+    }
+
+    for pattern in matches.values_of("redact").into_iter().flatten() {
+        options = options.redact(pattern.parse().unwrap());
+    }
+
+    if let Some(key) = matches.value_of("comments-key") {
+        options = options.comments_key(key);
+    }
+
+    if matches.is_present("env-subst") {
+        options = options.env_subst(EnvSubst {
+            allow: matches
+                .values_of("env-allow")
+                .map(|names| names.map(String::from).collect()),
+            undefined: match matches.value_of("env-undefined").unwrap() {
+                "error" => UndefinedVars::Error,
+                _ => UndefinedVars::Empty,
+            },
+            ..EnvSubst::default()
+        });
+    }
+
+    if let Some(key) = matches.value_of("alias-refs") {
+        options = options.alias_refs(key);
+    }
+
+    // Binary formats don't need anything between documents.
+    let separator = if format.is_binary() { "" } else { "\n" };
+    let output = Output {
+        yaml2json: Yaml2Json::with_options(options),
+        separator,
+        schema: matches.value_of("schema").map(|f| load_schema(&mut ep, f)),
+        split_output: matches.value_of("split-output"),
+        with_meta: matches.is_present("with-meta"),
+        comments: matches
+            .value_of("comments-out")
+            .map(|_| RefCell::new(Vec::new())),
+        unbuffered: matches.is_present("unbuffered") || io::stdout().is_terminal(),
+        decompress,
+        check: matches.is_present("check"),
+        in_place: matches.is_present("in-place").then(|| InPlace {
+            suffix: matches
+                .value_of("suffix")
+                .unwrap_or_else(|| format.extension()),
+            delete_source: matches.is_present("delete-source"),
+        }),
+        front_matter: matches.is_present("front-matter"),
+        content_key: matches.value_of("content-key"),
+        merge: matches.is_present("merge").then(|| {
+            let arrays = match matches.value_of("merge-arrays").unwrap() {
+                "append" => MergeArrays::Append,
+                _ => MergeArrays::Replace,
+            };
+            (arrays, RefCell::new(None))
+        }),
+        direct: !format.is_binary()
+            && !matches.is_present("schema")
+            && !matches.is_present("with-meta")
+            && !matches.is_present("merge"),
+        docs: matches
+            .values_of("doc")
+            .map(|docs| docs.map(|d| d.parse().unwrap()).collect())
+            .unwrap_or_default(),
+        jobs: matches.value_of("jobs").unwrap().parse().unwrap(),
+        stream: matches.is_present("stream"),
+    };
+
+    // if: files are provided as arguments or listed in a file, read those instead of stdin
+    let files_from = matches.value_of("files-from");
+    if fileopt.is_some() || files_from.is_some() {
+        for f in fileopt.into_iter().flatten() {
+            if f == "-" && files_from == Some("-") {
+                stdin_is_list(&mut ep);
+            } else {
+                convert_path(&output, &mut ep, f);
             }
         }
+
+        if let Some(list) = files_from {
+            convert_list(&output, &mut ep, list, matches.is_present("null"));
+        }
     // else: No files provided as args, use stdin for input
     } else {
-        let stdin = io::stdin();
-        let stdin_lock = stdin.lock();
+        let ctx = ErrorContext::file(None);
 
-        write(&yaml2json, &mut ep, stdin_lock);
+        ep.start(ctx);
+        write(&output, &mut ep, ctx, io::stdin());
     }
+
+    if let Some(merged) = output.merge.as_ref().and_then(|(_, merged)| merged.take()) {
+        let ctx = ErrorContext::file(Some("<merged>"));
+
+        if let Some(mut buf) =
+            value_bytes(&output, &mut ep, ctx, 0, merged).filter(|_| !output.check)
+        {
+            buf.extend_from_slice(output.separator.as_bytes());
+            write_or_exit(ep.stdout(), buf);
+        }
+    }
+
+    if let (Some(file), Some(comments)) = (matches.value_of("comments-out"), output.comments) {
+        let json = serde_json::to_string_pretty(&comments.into_inner()).unwrap();
+
+        if let Err(e) = std::fs::write(file, json + "\n") {
+            ep.print(ErrorContext::file(Some(file)), ErrorKind::IO, e);
+        }
+    }
+
+    ep.exit();
 }
@@ -0,0 +1,851 @@
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::{env, fs};
+
+/// `Run` is the outcome of running yaml2json.
+struct Run {
+    code: Option<i32>,
+    stdout: String,
+    stderr: String,
+}
+
+/// `yaml2json()` runs yaml2json with `args` in the current directory, writing `stdin` to it.
+fn yaml2json(args: &[&str], stdin: &str) -> Run {
+    yaml2json_in(Path::new("."), args, stdin)
+}
+
+fn yaml2json_in(dir: &Path, args: &[&str], stdin: &str) -> Run {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_yaml2json"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // stdin may be closed without being read, e.g. when the arguments are invalid.
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = child.wait_with_output().unwrap();
+
+    Run {
+        code: status.code(),
+        stdout: String::from_utf8(stdout).unwrap(),
+        stderr: String::from_utf8(stderr).unwrap(),
+    }
+}
+
+/// `TempDir` is a directory of input and output files for one test, removed once it's done.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(test: &str) -> TempDir {
+        let dir = env::temp_dir().join(format!("yaml2json-{}-{}", test, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        TempDir(dir)
+    }
+
+    /// `write()` creates the file `name` in the directory.
+    fn write(&self, name: &str, contents: impl AsRef<[u8]>) {
+        fs::write(self.0.join(name), contents).unwrap();
+    }
+
+    fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.0.join(name)).unwrap()
+    }
+
+    /// `files()` returns the names of the files in the directory, sorted.
+    fn files(&self) -> Vec<String> {
+        let mut files: Vec<String> = fs::read_dir(&self.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+
+        files
+    }
+
+    /// `yaml2json()` runs yaml2json within the directory, so that file names are relative to it.
+    fn yaml2json(&self, args: &[&str], stdin: &str) -> Run {
+        yaml2json_in(&self.0, args, stdin)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn stream() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_yaml2json"))
+        .arg("--stream")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+
+    let (started, output_started) = mpsc::channel();
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        let mut buf = [0; 4096];
+
+        loop {
+            let n = stdout.read(&mut buf).unwrap();
+            if n == 0 {
+                return output;
+            }
+            if output.is_empty() {
+                started.send(()).unwrap();
+            }
+            output.extend_from_slice(&buf[..n]);
+        }
+    });
+
+    // One document, far larger than the output buffer, which is output before stdin is closed.
+    // Only its mappings are held to replace duplicate keys.
+    let items = 128 * 1024;
+    stdin
+        .write_all("- {a: 1, a: item}\n".repeat(items).as_bytes())
+        .unwrap();
+    output_started
+        .recv_timeout(Duration::from_secs(10))
+        .expect("no output before the end of the input");
+    drop(stdin);
+
+    let output = reader.join().unwrap();
+    assert!(child.wait().unwrap().success());
+    assert_eq!(output.len(), r#"{"a":"item"},"#.len() * items + 2);
+}
+
+#[test]
+fn ascii() {
+    let run = yaml2json(&["--ascii", "-", "missing-é.yaml"], "- é\n- 🦀\n");
+
+    assert_eq!(run.code, Some(4));
+    assert_eq!(
+        run.stdout,
+        concat!(
+            r#"["\u00e9","\ud83e\udd80"]"#,
+            "\n",
+            r#"{"yaml-error":{"message":"file does not exist","kind":"input","file":"missing-\u00e9.yaml"}}"#,
+            "\n",
+        )
+    );
+
+    let run = yaml2json(&["--ascii", "--pretty", "--error", "json"], "é: [");
+    assert_eq!(run.code, Some(3));
+    assert!(run.stdout.starts_with("{\n  \"yaml-error\": {\n"));
+
+    // Canonical JSON is never escaped.
+    let run = yaml2json(&["--ascii", "--canonical"], "- é\n");
+    assert_eq!(run.code, Some(2));
+    assert_eq!(run.stdout, "");
+}
+
+#[test]
+fn error_context() {
+    let dir = TempDir::new("error_context");
+    dir.write("good.yaml", "a: 1\n");
+    dir.write("bad.yaml", "b: 1\n---\nc: [1\n---\nd: 3\n");
+
+    let run = dir.yaml2json(&["-e", "stderr", "good.yaml", "bad.yaml", "-"], "e: [1\n");
+    assert_eq!(run.stdout, "{\"a\":1}\n{\"b\":1}\n{\"d\":3}\n");
+    assert_eq!(
+        run.stderr,
+        concat!(
+            "bad.yaml (document 1): while parsing a flow sequence, expected ',' or ']' at line 4 column 1\n",
+            "<stdin> (document 0): while parsing a flow sequence, expected ',' or ']' at line 2 column 1\n",
+        )
+    );
+
+    let run = dir.yaml2json(&["-e", "stderr", "missing.yaml"], "");
+    assert_eq!(run.stderr, "missing.yaml: file does not exist\n");
+}
+
+#[test]
+fn exit_codes() {
+    let dir = TempDir::new("exit_codes");
+    dir.write("good.yaml", "a: 1\n");
+    dir.write("bad.yaml", "b: [1\n");
+
+    assert_eq!(dir.yaml2json(&["good.yaml", "-"], "c: 2\n").code, Some(0));
+    assert_eq!(dir.yaml2json(&["bad.yaml", "good.yaml"], "").code, Some(3));
+    assert_eq!(
+        dir.yaml2json(&["missing.yaml", "good.yaml"], "").code,
+        Some(4)
+    );
+    assert_eq!(dir.yaml2json(&["--no-such-flag"], "").code, Some(2));
+
+    // The first error decides the exit code.
+    assert_eq!(
+        dir.yaml2json(&["bad.yaml", "missing.yaml"], "").code,
+        Some(3)
+    );
+    assert_eq!(
+        dir.yaml2json(&["missing.yaml", "bad.yaml"], "").code,
+        Some(4)
+    );
+
+    // Errors are still reported with the exit code when they aren't printed.
+    let run = dir.yaml2json(&["-e", "silent", "bad.yaml", "good.yaml"], "");
+    assert_eq!(
+        (run.code, run.stdout, run.stderr),
+        (Some(3), "{\"a\":1}\n".to_string(), String::new())
+    );
+}
+
+#[test]
+fn fail_fast() {
+    let dir = TempDir::new("fail_fast");
+    dir.write("good.yaml", "a: 1\n");
+    dir.write("bad.yaml", "b: 1\n---\nc: [1\n---\nd: 3\n");
+
+    let run = dir.yaml2json(&["-e", "stderr", "bad.yaml", "good.yaml"], "");
+    assert_eq!(
+        (run.code, run.stdout.as_str()),
+        (Some(3), "{\"b\":1}\n{\"d\":3}\n{\"a\":1}\n")
+    );
+
+    // Neither the rest of the file nor the files after it are converted.
+    let run = dir.yaml2json(
+        &["-e", "stderr", "--fail-fast", "bad.yaml", "good.yaml"],
+        "",
+    );
+    assert_eq!((run.code, run.stdout.as_str()), (Some(3), "{\"b\":1}\n"));
+    assert_eq!(run.stderr.lines().count(), 1);
+
+    let run = dir.yaml2json(&["--fail-fast", "missing.yaml", "good.yaml"], "");
+    assert_eq!(run.code, Some(4));
+    assert!(!run.stdout.contains("{\"a\":1}"));
+}
+
+#[test]
+fn schema() {
+    let dir = TempDir::new("schema");
+    dir.write(
+        "schema.yaml",
+        "type: object\nrequired: [name]\nproperties: {name: {type: string}}\n",
+    );
+    dir.write("schema.json", r#"{"type": "array"}"#);
+    dir.write("invalid.yaml", "type: [\n");
+
+    let run = dir.yaml2json(
+        &["--schema", "schema.yaml"],
+        "name: a\n---\nname: 1\n---\nother: 2\n",
+    );
+    assert_eq!(run.code, Some(5));
+    assert_eq!(
+        run.stdout,
+        concat!(
+            "{\"name\":\"a\"}\n",
+            r#"{"yaml-error":{"message":"1 is not of type \"string\"","kind":"validation","file":"<stdin>","doc":1,"pointer":"/name"}}"#,
+            "\n",
+            r#"{"yaml-error":{"message":"\"name\" is a required property","kind":"validation","file":"<stdin>","doc":2,"pointer":""}}"#,
+            "\n",
+        )
+    );
+
+    let run = dir.yaml2json(&["--schema", "schema.json"], "- 1\n");
+    assert_eq!((run.code, run.stdout.as_str()), (Some(0), "[1]\n"));
+
+    // Nothing is converted without a usable schema.
+    for schema in ["invalid.yaml", "missing.yaml"] {
+        let run = dir.yaml2json(&["--schema", schema], "- 1\n");
+        assert_eq!(run.code, Some(4));
+        assert!(run.stdout.contains("invalid schema"), "{}", run.stdout);
+        assert!(!run.stdout.contains("[1]"));
+    }
+}
+
+#[test]
+fn stdin_among_files() {
+    let dir = TempDir::new("stdin_among_files");
+    dir.write("a.yaml", "a: 1\n");
+    dir.write("-", "dash: 1\n");
+
+    let run = dir.yaml2json(&["a.yaml", "-", "a.yaml"], "s: 1\n");
+    assert_eq!(
+        (run.code, run.stdout.as_str()),
+        (Some(0), "{\"a\":1}\n{\"s\":1}\n{\"a\":1}\n")
+    );
+
+    let run = dir.yaml2json(&[], "s: 1\n");
+    assert_eq!(run.stdout, "{\"s\":1}\n");
+
+    // A file named - can still be given as a path.
+    let run = dir.yaml2json(&["./-"], "s: 1\n");
+    assert_eq!(run.stdout, "{\"dash\":1}\n");
+}
+
+#[test]
+fn split_output() {
+    let dir = TempDir::new("split_output");
+    dir.write("app.config.yaml", "a: 1\n---\nb: 2\n");
+
+    let run = dir.yaml2json(
+        &["--split-output", "%f-%d-100%%.json", "app.config.yaml", "-"],
+        "c: 3\n---\nd: [\n---\ne: 5\n",
+    );
+    assert_eq!(run.code, Some(3));
+    // Only the error is output to stdout, and the failed document has no file.
+    assert_eq!(run.stdout.lines().count(), 1);
+    assert_eq!(
+        dir.files(),
+        [
+            "app.config-0-100%.json",
+            "app.config-1-100%.json",
+            "app.config.yaml",
+            "stdin-0-100%.json",
+            "stdin-2-100%.json",
+        ]
+    );
+    assert_eq!(dir.read("app.config-1-100%.json"), "{\"b\":2}\n");
+    assert_eq!(dir.read("stdin-2-100%.json"), "{\"e\":5}\n");
+}
+
+#[test]
+fn with_meta() {
+    let dir = TempDir::new("with_meta");
+    dir.write("a.yaml", "a: {b: 1}\n---\nc: [\n---\nd: 2\n");
+
+    let run = dir.yaml2json(&["--with-meta", "-e", "silent", "a.yaml", "-"], "- e\n");
+    assert_eq!(
+        run.stdout,
+        concat!(
+            r#"{"file":"a.yaml","doc":0,"data":{"a":{"b":1}}}"#,
+            "\n",
+            r#"{"file":"a.yaml","doc":2,"data":{"d":2}}"#,
+            "\n",
+            r#"{"file":"<stdin>","doc":0,"data":["e"]}"#,
+            "\n",
+        )
+    );
+
+    // Only the data is queried.
+    let run = dir.yaml2json(&["--with-meta", "--query", "a", "--doc", "0", "a.yaml"], "");
+    assert_eq!(
+        run.stdout,
+        "{\"file\":\"a.yaml\",\"doc\":0,\"data\":{\"b\":1}}\n"
+    );
+}
+
+#[test]
+fn buffered_output() {
+    let dir = TempDir::new("buffered_output");
+    let docs = 10_000;
+    let yaml: Vec<String> = (0..docs).map(|i| format!("i: {}\n", i)).collect();
+    dir.write("a.yaml", yaml.join("---\n"));
+    dir.write("bad.yaml", "b: [\n");
+
+    // JSON errors share the buffer, so they are output between the documents around them.
+    let mut expected: Vec<String> = (0..docs).map(|i| format!("{{\"i\":{}}}\n", i)).collect();
+    expected.push(dir.yaml2json(&["bad.yaml"], "").stdout);
+    expected.extend((0..docs).map(|i| format!("{{\"i\":{}}}\n", i)));
+
+    for unbuffered in [&[][..], &["--unbuffered"]] {
+        let args = [unbuffered, &["a.yaml", "bad.yaml", "a.yaml"]].concat();
+        let run = dir.yaml2json(&args, "");
+        assert_eq!(run.code, Some(3));
+        assert_eq!(run.stdout, expected.concat());
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn write_errors() {
+    // Writing to /dev/full always fails, so the buffered output can't be flushed.
+    let status = Command::new(env!("CARGO_BIN_EXE_yaml2json"))
+        .stdin(Stdio::piped())
+        .stdout(fs::File::create("/dev/full").unwrap())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().unwrap().write_all(b"a: 1\n")?;
+            child.wait()
+        })
+        .unwrap();
+
+    assert_eq!(status.code(), Some(1));
+}
+
+#[test]
+fn json_errors() {
+    let dir = TempDir::new("json_errors");
+    dir.write(
+        "a.yaml",
+        "a: 1\n---\nb: [1\n---\nc: 1\nc: 2\n---\nd: ${YAML2JSON_TEST_UNSET}\n",
+    );
+
+    let run = dir.yaml2json(
+        &[
+            "--error=json",
+            "--duplicate-keys=error",
+            "--env-subst",
+            "--env-undefined=error",
+            "a.yaml",
+            "missing.yaml",
+        ],
+        "",
+    );
+    let lines: Vec<Value> = run
+        .stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    assert_eq!(run.code, Some(3));
+    assert_eq!(
+        lines,
+        [
+            json!({"a": 1}),
+            json!({"yaml-error": {
+                "message": "while parsing a flow sequence, expected ',' or ']' at line 4 column 1",
+                "kind": "conversion",
+                "code": "parse",
+                "file": "a.yaml",
+                "doc": 1,
+                "line": 4,
+                "column": 1,
+            }}),
+            json!({"yaml-error": {
+                "message": "duplicate mapping key `c` at line 6 column 1",
+                "kind": "conversion",
+                "code": "duplicate-key",
+                "key": "c",
+                "file": "a.yaml",
+                "doc": 2,
+                "line": 6,
+                "column": 1,
+            }}),
+            json!({"yaml-error": {
+                "message": "undefined environment variable `YAML2JSON_TEST_UNSET` at line 8 column 4",
+                "kind": "conversion",
+                "code": "undefined-variable",
+                "variable": "YAML2JSON_TEST_UNSET",
+                "file": "a.yaml",
+                "doc": 3,
+                "line": 8,
+                "column": 4,
+            }}),
+            json!({"yaml-error": {
+                "message": "file does not exist",
+                "kind": "input",
+                "file": "missing.yaml",
+            }}),
+        ]
+    );
+}
+
+#[test]
+fn completions() {
+    for (shell, start) in [
+        ("bash", "_yaml2json() {"),
+        ("zsh", "#compdef yaml2json"),
+        ("fish", "complete -c yaml2json"),
+        (
+            "powershell",
+            "\nusing namespace System.Management.Automation",
+        ),
+    ] {
+        let run = yaml2json(&["completions", shell], "");
+        assert_eq!(run.code, Some(0), "{}", shell);
+        assert!(run.stdout.starts_with(start), "{}", shell);
+        assert!(run.stdout.contains("fail-fast"), "{}", shell);
+    }
+
+    assert_eq!(yaml2json(&["completions"], "").code, Some(2));
+    assert_eq!(yaml2json(&["completions", "tcsh"], "").code, Some(2));
+}
+
+#[test]
+#[cfg(all(feature = "gzip", feature = "zstd"))]
+fn decompress() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let gzip = |yaml: &str| {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(yaml.as_bytes()).unwrap();
+        gz.finish().unwrap()
+    };
+
+    let dir = TempDir::new("decompress");
+    // Concatenated gzip files are read as one.
+    dir.write("a.yaml.gz", [gzip("a: 1\n---\n"), gzip("b: 2\n")].concat());
+    dir.write("c.yaml.zst", zstd::encode_all(&b"c: 3\n"[..], 0).unwrap());
+    dir.write("d.yaml", "d: 4\n");
+
+    let run = dir.yaml2json(&["a.yaml.gz", "c.yaml.zst", "d.yaml"], "");
+    assert_eq!(
+        (run.code, run.stdout.as_str()),
+        (Some(0), "{\"a\":1}\n{\"b\":2}\n{\"c\":3}\n{\"d\":4}\n")
+    );
+
+    let run = dir.yaml2json(&["--decompress", "gzip", "a.yaml.gz"], "");
+    assert_eq!(run.stdout, "{\"a\":1}\n{\"b\":2}\n");
+
+    // Compressed input isn't valid UTF-8, so it can't be read as YAML.
+    let run = dir.yaml2json(&["--decompress", "none", "c.yaml.zst"], "");
+    assert_eq!(run.code, Some(1));
+    assert!(run.stdout.contains("stream did not contain valid UTF-8"));
+
+    let run = dir.yaml2json(&["--decompress", "zstd", "d.yaml"], "");
+    assert_eq!(run.code, Some(1));
+    assert!(!run.stdout.contains("{\"d\":4}"));
+}
+
+#[test]
+#[cfg(not(feature = "gzip"))]
+fn decompress_unsupported() {
+    let dir = TempDir::new("decompress_unsupported");
+    dir.write("a.yaml.gz", [0x1f, 0x8b, 0x08, 0x00]);
+
+    let run = dir.yaml2json(&["a.yaml.gz"], "");
+    assert!(run
+        .stdout
+        .contains("gzip input needs yaml2json to be built with the gzip feature"));
+}
+
+#[test]
+#[cfg(feature = "http")]
+fn http() {
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    // Serves a.yaml, and 404s for anything else, for the two requests the test makes.
+    let server = thread::spawn(move || {
+        for stream in listener.incoming().take(2) {
+            let mut stream = stream.unwrap();
+            let mut request = BufReader::new(&stream);
+            let mut line = String::new();
+            request.read_line(&mut line).unwrap();
+            // Skip the headers, up to the blank line after them.
+            let mut header = String::new();
+            while request.read_line(&mut header).unwrap_or(0) > 2 {
+                header.clear();
+            }
+
+            let (status, body) = if line.starts_with("GET /a.yaml ") {
+                ("200 OK", "a: 1\n---\nb: 2\n")
+            } else {
+                ("404 Not Found", "")
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    let a = format!("{}/a.yaml", url);
+    let missing = format!("{}/missing.yaml", url);
+    let run = yaml2json(&[&a, &missing], "");
+    server.join().unwrap();
+
+    assert_eq!(run.code, Some(4));
+    assert_eq!(
+        run.stdout,
+        format!(
+            "{{\"a\":1}}\n{{\"b\":2}}\n{{\"yaml-error\":{{\"message\":\"http status: 404\",\"kind\":\"input\",\"file\":\"{}\"}}}}\n",
+            missing
+        )
+    );
+}
+
+#[test]
+#[cfg(not(feature = "http"))]
+fn http_unsupported() {
+    let run = yaml2json(&["http://127.0.0.1:1/a.yaml"], "");
+
+    assert_eq!(run.code, Some(4));
+    assert!(run
+        .stdout
+        .contains("URL input needs yaml2json to be built with the http feature"));
+}
+
+#[test]
+fn in_place() {
+    let dir = TempDir::new("in_place");
+    dir.write("a.yaml", "a: 1\n---\nb: 2\n");
+    dir.write("c.yml", "c: 3\n");
+    dir.write("d.yaml", "d: 1\n---\ne: [\n");
+    dir.write("d.json", "old\n");
+
+    let run = dir.yaml2json(&["--in-place", "a.yaml", "c.yml", "d.yaml"], "");
+    assert_eq!(run.code, Some(3));
+    // Only the error is output to stdout.
+    assert_eq!(run.stdout.lines().count(), 1);
+    assert_eq!(dir.read("a.json"), "{\"a\":1}\n{\"b\":2}\n");
+    assert_eq!(dir.read("c.json"), "{\"c\":3}\n");
+    // A file with a failed document is left as it was, without any temporary file.
+    assert_eq!(dir.read("d.json"), "old\n");
+    assert_eq!(
+        dir.files(),
+        ["a.json", "a.yaml", "c.json", "c.yml", "d.json", "d.yaml"]
+    );
+
+    let run = dir.yaml2json(
+        &["--in-place", "--to", "toml", "--delete-source", "c.yml"],
+        "",
+    );
+    assert_eq!(run.code, Some(0));
+    assert_eq!(dir.read("c.toml"), "c = 3\n\n");
+
+    let run = dir.yaml2json(&["--in-place", "--suffix", ".out.json", "-"], "a: 1\n");
+    assert_eq!(run.code, Some(4));
+
+    // The output can't be renamed over a directory, and nothing is deleted when it fails.
+    fs::create_dir(dir.0.join("a.out.json")).unwrap();
+    let run = dir.yaml2json(
+        &[
+            "--in-place",
+            "--suffix",
+            ".out.json",
+            "--delete-source",
+            "a.yaml",
+        ],
+        "",
+    );
+    assert_eq!(run.code, Some(1));
+    assert_eq!(
+        dir.files(),
+        [
+            "a.json",
+            "a.out.json",
+            "a.yaml",
+            "c.json",
+            "c.toml",
+            "d.json",
+            "d.yaml"
+        ]
+    );
+}
+
+#[test]
+fn check() {
+    let dir = TempDir::new("check");
+    dir.write("good.yaml", "a: 1\n---\nb: 2\n");
+    dir.write("bad.yaml", "c: 1\n---\nd: [\n");
+    dir.write("schema.json", r#"{"type": "object", "required": ["a"]}"#);
+
+    let run = dir.yaml2json(&["--check", "good.yaml"], "");
+    assert_eq!((run.code, run.stdout.as_str()), (Some(0), ""));
+
+    // Only the errors are output.
+    let run = dir.yaml2json(&["--check", "good.yaml", "bad.yaml"], "");
+    assert_eq!(run.code, Some(3));
+    assert_eq!(run.stdout.lines().count(), 1);
+    assert!(run.stdout.contains("\"file\":\"bad.yaml\",\"doc\":1"));
+
+    let run = dir.yaml2json(
+        &[
+            "--check",
+            "--schema",
+            "schema.json",
+            "-e",
+            "stderr",
+            "good.yaml",
+        ],
+        "",
+    );
+    assert_eq!((run.code, run.stdout.as_str()), (Some(5), ""));
+    assert!(run.stderr.starts_with("good.yaml (document 1): "));
+
+    assert_eq!(
+        dir.yaml2json(&["--check", "--in-place", "good.yaml"], "")
+            .code,
+        Some(2)
+    );
+    assert_eq!(dir.files(), ["bad.yaml", "good.yaml", "schema.json"]);
+}
+
+#[test]
+fn doc_ranges() {
+    let dir = TempDir::new("doc_ranges");
+    dir.write("a.yaml", "0\n---\n1\n---\n[\n---\n3\n---\n4\n---\n5\n");
+    dir.write("b.yaml", "b0\n---\nb1\n");
+
+    let docs = |ranges: &[&str]| {
+        let mut args: Vec<&str> = ranges.iter().flat_map(|range| ["--doc", range]).collect();
+        args.push("a.yaml");
+        let run = dir.yaml2json(&args, "");
+        (run.code, run.stdout.lines().collect::<Vec<_>>().join(" "))
+    };
+
+    assert_eq!(docs(&["0"]), (Some(0), "0".to_string()));
+    assert_eq!(docs(&["3..5"]), (Some(0), "3 4".to_string()));
+    assert_eq!(docs(&["4.."]), (Some(0), "4 5".to_string()));
+    assert_eq!(docs(&["..2"]), (Some(0), "0 1".to_string()));
+    assert_eq!(docs(&["-1"]), (Some(0), "5".to_string()));
+    assert_eq!(docs(&["-3..-1"]), (Some(0), "3 4".to_string()));
+    // Each document is output once, in the order of the input.
+    assert_eq!(docs(&["5", "0..2", "1"]), (Some(0), "0 1 5".to_string()));
+    assert_eq!(docs(&["9", "-9", "3..1"]), (Some(0), String::new()));
+    // Only the selected documents can fail.
+    assert_eq!(docs(&["2"]).0, Some(3));
+
+    // Indices are counted within each input.
+    let run = dir.yaml2json(&["--doc", "-1", "a.yaml", "b.yaml", "-"], "s0\n---\ns1\n");
+    assert_eq!(run.stdout, "5\n\"b1\"\n\"s1\"\n");
+
+    for invalid in ["x", "1..x", "1...2", ""] {
+        assert_eq!(
+            dir.yaml2json(&["--doc", invalid], "").code,
+            Some(2),
+            "{}",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn summary() {
+    let dir = TempDir::new("summary");
+    dir.write("a.yaml", "a: 1\n---\nb: 2\n");
+    dir.write("bad.yaml", "c: 1\n---\nd: [\n---\ne: 3\n");
+
+    let run = dir.yaml2json(
+        &[
+            "--summary",
+            "-e",
+            "stderr",
+            "a.yaml",
+            "bad.yaml",
+            "missing.yaml",
+        ],
+        "",
+    );
+    assert_eq!(run.code, Some(3));
+    let (report, elapsed) = run.stderr.rsplit_once(" in ").unwrap();
+    assert_eq!(
+        report,
+        concat!(
+            "bad.yaml (document 1): while parsing a node, did not find expected node content at line 4 column 1\n",
+            "missing.yaml: file does not exist\n",
+            "a.yaml: 2 converted, 0 failed\n",
+            "bad.yaml: 2 converted, 1 failed\n",
+            "missing.yaml: file does not exist\n",
+            "3 files, 4 documents converted, 1 failed",
+        )
+    );
+    assert!(elapsed.ends_with("s\n"), "{}", elapsed);
+
+    // With --fail-fast, the report stops at the first error.
+    let run = dir.yaml2json(
+        &[
+            "--summary=json",
+            "--fail-fast",
+            "-e",
+            "silent",
+            "a.yaml",
+            "bad.yaml",
+            "-",
+        ],
+        "f: 1\n",
+    );
+    let mut report: Value = serde_json::from_str(&run.stderr).unwrap();
+    assert!(report["summary"]["elapsed"].as_f64().is_some());
+    report["summary"].as_object_mut().unwrap().remove("elapsed");
+    assert_eq!(
+        report,
+        json!({"summary": {
+            "files": [
+                {"file": "a.yaml", "converted": 2, "failed": 0},
+                {"file": "bad.yaml", "converted": 1, "failed": 1},
+            ],
+            "converted": 3,
+            "failed": 1,
+        }})
+    );
+    assert_eq!(run.stdout, "{\"a\":1}\n{\"b\":2}\n{\"c\":1}\n");
+}
+
+#[test]
+fn files_from() {
+    let dir = TempDir::new("files_from");
+    dir.write("a.yaml", "a: 1\n");
+    dir.write("b c.yaml", "b: 2\n");
+    dir.write("list", "b c.yaml\r\n\nmissing.yaml\na.yaml");
+
+    // Arguments come first, then the list in order, skipping empty lines.
+    let run = dir.yaml2json(&["--files-from", "list", "a.yaml"], "");
+    assert_eq!(run.code, Some(4));
+    let files: Vec<Value> = run
+        .stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(
+        files,
+        [
+            json!({"a": 1}),
+            json!({"b": 2}),
+            json!({"yaml-error": {"message": "file does not exist", "kind": "input", "file": "missing.yaml"}}),
+            json!({"a": 1}),
+        ]
+    );
+
+    let run = dir.yaml2json(&["-0", "--files-from", "-"], "a.yaml\0b c.yaml\0");
+    assert_eq!(
+        (run.code, run.stdout.as_str()),
+        (Some(0), "{\"a\":1}\n{\"b\":2}\n")
+    );
+
+    // stdin can't be both the list and an input.
+    for (args, stdin) in [
+        (&["--files-from", "-", "-"][..], "a.yaml\n"),
+        (&["--files-from", "-"], "-\na.yaml\n"),
+    ] {
+        let run = dir.yaml2json(args, stdin);
+        assert_eq!(run.code, Some(4));
+        assert_eq!(
+            run.stdout,
+            concat!(
+                r#"{"yaml-error":{"message":"stdin is already read for --files-from","kind":"input","file":"<stdin>"}}"#,
+                "\n",
+                "{\"a\":1}\n",
+            )
+        );
+    }
+
+    assert_eq!(
+        dir.yaml2json(&["--files-from", "missing"], "").code,
+        Some(4)
+    );
+    assert_eq!(dir.yaml2json(&["-0", "a.yaml"], "").code, Some(2));
+}
+
+#[test]
+fn unknown_tags() {
+    let run = yaml2json(&[], "a: !vault x\n");
+    assert_eq!(run.code, Some(3));
+    assert!(run.stdout.contains("unknown tag `!vault`"));
+
+    let run = yaml2json(&["--unknown-tags", "ignore"], "a: !vault x\nb: !!set {c}\n");
+    assert_eq!(run.code, Some(0));
+    assert_eq!(run.stdout, "{\"a\":\"x\",\"b\":{\"c\":null}}\n");
+
+    // --strict overrides it.
+    let run = yaml2json(&["--unknown-tags", "ignore", "--strict"], "a: !vault x\n");
+    assert_eq!(run.code, Some(3));
+}
@@ -0,0 +1,122 @@
+//! `convert` benchmarks converting a corpus of generated Kubernetes-style manifests, which are
+//! mostly short string scalars. Run it with `cargo bench -p yaml2json-rs`.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::io;
+use yaml2json_rs::{Style, Yaml2Json};
+
+/// `manifest()` returns a deployment with a service in front of it, as separate documents.
+fn manifest(i: usize) -> String {
+    format!(
+        r#"---
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: app-{i}
+  namespace: team-{team}
+  labels:
+    app.kubernetes.io/name: app-{i}
+    app.kubernetes.io/part-of: platform
+    app.kubernetes.io/managed-by: yaml2json
+  annotations:
+    description: "Deployment number {i}, generated for benchmarking."
+spec:
+  replicas: {replicas}
+  selector:
+    matchLabels:
+      app.kubernetes.io/name: app-{i}
+  template:
+    metadata:
+      labels:
+        app.kubernetes.io/name: app-{i}
+    spec:
+      containers:
+        - name: app
+          image: registry.example.com/team-{team}/app-{i}:1.{i}.0
+          args: ["--port", "8080", "--log-level", info]
+          ports:
+            - containerPort: 8080
+              protocol: TCP
+          env:
+            - name: RUST_LOG
+              value: info
+            - name: DATABASE_URL
+              value: postgres://db.team-{team}.svc:5432/app
+          resources:
+            limits: {{cpu: 500m, memory: 256Mi}}
+            requests: {{cpu: 100m, memory: 128Mi}}
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: app-{i}
+  namespace: team-{team}
+spec:
+  selector:
+    app.kubernetes.io/name: app-{i}
+  ports:
+    - port: 80
+      targetPort: 8080
+"#,
+        i = i,
+        team = i % 7,
+        replicas = i % 5 + 1,
+    )
+}
+
+fn bench(c: &mut Criterion) {
+    let corpus: String = (0..500).map(manifest).collect();
+    let document = manifest(0);
+    let document = document.split("---").nth(1).unwrap();
+    let y2j = Yaml2Json::new(Style::COMPACT);
+    let sorted = Yaml2Json::new(Style::COMPACT).sort_keys(true);
+
+    let mut group = c.benchmark_group("document");
+    group.throughput(Throughput::Bytes(document.len() as u64));
+    group.bench_function("document_to_string", |b| {
+        b.iter(|| y2j.document_to_string(document).unwrap())
+    });
+    group.bench_function("document_to_value", |b| {
+        b.iter(|| y2j.document_to_value(document).unwrap())
+    });
+    group.bench_function("document_to_string sorted", |b| {
+        b.iter(|| sorted.document_to_string(document).unwrap())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("corpus");
+    group.throughput(Throughput::Bytes(corpus.len() as u64));
+    group.sample_size(20);
+    group.bench_function("stream", |b| {
+        b.iter(|| {
+            y2j.stream(corpus.as_bytes())
+                .map(Result::unwrap)
+                .map(|s| s.len())
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("stream_values", |b| {
+        b.iter(|| y2j.stream_values(corpus.as_bytes()).count())
+    });
+    for jobs in [4, 0] {
+        group.bench_function(format!("stream_parallel {} jobs", jobs), |b| {
+            b.iter(|| {
+                y2j.stream_parallel(io::Cursor::new(corpus.clone()), jobs)
+                    .map(Result::unwrap)
+                    .map(|s| s.len())
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.bench_function("reader_to_writer", |b| {
+        b.iter(|| {
+            let mut buf = Vec::new();
+            y2j.reader_to_writer(corpus.as_bytes(), &mut buf).unwrap();
+            buf
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
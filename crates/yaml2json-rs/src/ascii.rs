@@ -0,0 +1,147 @@
+use serde_json::ser::{CharEscape, Formatter};
+use std::io::{self, Write};
+
+/// `AsciiFormatter` wraps another formatter, escaping every non-ASCII character in strings as
+/// `\uXXXX`.
+pub(crate) struct AsciiFormatter<F> {
+    inner: F,
+}
+
+impl<F: Formatter> AsciiFormatter<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        AsciiFormatter { inner }
+    }
+}
+
+impl<F: Formatter> Formatter for AsciiFormatter<F> {
+    fn write_string_fragment<W: ?Sized + Write>(
+        &mut self,
+        w: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        let mut rest = fragment;
+
+        while let Some(i) = rest.find(|c: char| !c.is_ascii()) {
+            self.inner.write_string_fragment(w, &rest[..i])?;
+
+            // Safe to unwrap, `find()` only returns the index of a char.
+            let c = rest[i..].chars().next().unwrap();
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                write!(w, "\\u{:04x}", unit)?;
+            }
+
+            rest = &rest[i + c.len_utf8()..];
+        }
+
+        self.inner.write_string_fragment(w, rest)
+    }
+
+    fn write_null<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.write_null(w)
+    }
+
+    fn write_bool<W: ?Sized + Write>(&mut self, w: &mut W, value: bool) -> io::Result<()> {
+        self.inner.write_bool(w, value)
+    }
+
+    fn write_i64<W: ?Sized + Write>(&mut self, w: &mut W, value: i64) -> io::Result<()> {
+        self.inner.write_i64(w, value)
+    }
+
+    fn write_u64<W: ?Sized + Write>(&mut self, w: &mut W, value: u64) -> io::Result<()> {
+        self.inner.write_u64(w, value)
+    }
+
+    fn write_f64<W: ?Sized + Write>(&mut self, w: &mut W, value: f64) -> io::Result<()> {
+        self.inner.write_f64(w, value)
+    }
+
+    fn write_number_str<W: ?Sized + Write>(&mut self, w: &mut W, value: &str) -> io::Result<()> {
+        self.inner.write_number_str(w, value)
+    }
+
+    fn begin_string<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.begin_string(w)
+    }
+
+    fn end_string<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_string(w)
+    }
+
+    fn write_char_escape<W: ?Sized + Write>(
+        &mut self,
+        w: &mut W,
+        char_escape: CharEscape,
+    ) -> io::Result<()> {
+        self.inner.write_char_escape(w, char_escape)
+    }
+
+    fn begin_array<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.begin_array(w)
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_array(w)
+    }
+
+    fn begin_array_value<W: ?Sized + Write>(&mut self, w: &mut W, first: bool) -> io::Result<()> {
+        self.inner.begin_array_value(w, first)
+    }
+
+    fn end_array_value<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_array_value(w)
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.begin_object(w)
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_object(w)
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(&mut self, w: &mut W, first: bool) -> io::Result<()> {
+        self.inner.begin_object_key(w, first)
+    }
+
+    fn end_object_key<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_object_key(w)
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.begin_object_value(w)
+    }
+
+    fn end_object_value<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_object_value(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ascii::AsciiFormatter;
+    use serde::Serialize;
+    use serde_json::json;
+    use serde_json::ser::CompactFormatter;
+
+    #[test]
+    fn escapes() {
+        let value = json!({"ключ": ["plain", "é\n\"ü\"", "a🦀b"]});
+        let mut buf = Vec::new();
+        let formatter = AsciiFormatter::new(CompactFormatter);
+        value
+            .serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buf, formatter,
+            ))
+            .unwrap();
+
+        let res = String::from_utf8(buf).unwrap();
+        let expected =
+            r#"{"\u043a\u043b\u044e\u0447":["plain","\u00e9\n\"\u00fc\"","a\ud83e\udd80b"]}"#;
+        assert_eq!(expected, res);
+        assert_eq!(
+            value,
+            serde_json::from_str::<serde_json::Value>(&res).unwrap()
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use serde_json::ser::Formatter;
+use serde_json::Value;
+use std::io::{self, Write};
+
+/// `CanonicalFormatter` writes JSON in the canonical form of RFC 8785: compact, with numbers
+/// formatted as ECMAScript does. String escaping is already the same as serde_json's, and keys
+/// are sorted beforehand by `sort_keys()`.
+pub(crate) struct CanonicalFormatter;
+
+impl Formatter for CanonicalFormatter {
+    fn write_i64<W: ?Sized + Write>(&mut self, w: &mut W, value: i64) -> io::Result<()> {
+        self.write_f64(w, value as f64)
+    }
+
+    fn write_u64<W: ?Sized + Write>(&mut self, w: &mut W, value: u64) -> io::Result<()> {
+        self.write_f64(w, value as f64)
+    }
+
+    fn write_f64<W: ?Sized + Write>(&mut self, w: &mut W, value: f64) -> io::Result<()> {
+        w.write_all(number(value).as_bytes())
+    }
+
+    /// Numbers kept exactly by `Yaml2JsonOptions::preserve_numbers()` are still doubles here.
+    fn write_number_str<W: ?Sized + Write>(&mut self, w: &mut W, value: &str) -> io::Result<()> {
+        match value.parse::<f64>() {
+            Ok(f) if f.is_finite() => self.write_f64(w, f),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is out of range for canonical JSON", value),
+            )),
+        }
+    }
+}
+
+/// `number()` formats `value` as ECMAScript's `Number.prototype.toString()`, which RFC 8785 uses
+/// for every number. JSON values are always finite.
+fn number(value: f64) -> String {
+    if value == 0.0 {
+        // Including -0.
+        return "0".to_string();
+    }
+
+    // The shortest digits which round trip, as in ECMAScript, e.g. "1.25e-7".
+    let exp_str = format!("{:e}", value.abs());
+    let (mantissa, exponent) = exp_str.split_once('e').unwrap_or((&exp_str, "0"));
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    // Safe to unwrap, the exponent is always an integer.
+    let point = exponent.parse::<i32>().unwrap() + 1;
+    let len = digits.len() as i32;
+
+    let mut out = String::new();
+    if value < 0.0 {
+        out.push('-');
+    }
+
+    if len <= point && point <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (point - len) as usize));
+    } else if 0 < point && point <= 21 {
+        out.push_str(&digits[..point as usize]);
+        out.push('.');
+        out.push_str(&digits[point as usize..]);
+    } else if -6 < point && point <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -point as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if len > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push_str(&format!(
+            "e{}{}",
+            if point > 0 { "+" } else { "-" },
+            (point - 1).abs()
+        ));
+    }
+
+    out
+}
+
+/// `sort_keys()` sorts the keys of every object by their UTF-16 code units, as RFC 8785 requires.
+pub(crate) fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, sort_keys(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::canonical::{number, sort_keys, CanonicalFormatter};
+    use serde::Serialize;
+    use serde_json::json;
+
+    #[test]
+    fn numbers() {
+        // From RFC 8785, appendix B.
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (5e-324, "5e-324"),
+            (-5e-324, "-5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+            (-1.7976931348623157e308, "-1.7976931348623157e+308"),
+            (9007199254740992.0, "9007199254740992"),
+            (-9007199254740992.0, "-9007199254740992"),
+            (295147905179352830000.0, "295147905179352830000"),
+            (9.999999999999997e22, "9.999999999999997e+22"),
+            (1e23, "1e+23"),
+            (1.0000000000000001e21, "1.0000000000000001e+21"),
+            (999999999999999700000.0, "999999999999999700000"),
+            (999999999999999900000.0, "999999999999999900000"),
+            (1e21, "1e+21"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (0.000004999999999999999, "0.000004999999999999999"),
+            (333333333.3333332, "333333333.3333332"),
+            (1.5, "1.5"),
+            (100.0, "100"),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(expected, number(value), "{:e}", value);
+        }
+    }
+
+    #[test]
+    fn canonical() {
+        // The sorting example from RFC 8785, section 3.2.3.
+        let value = json!({
+            "\u{20ac}": "Euro Sign",
+            "\r": "Carriage Return",
+            "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\u{1f600}": "Emoji: Grinning Face",
+            "\u{80}": "Control",
+            "\u{f6}": "Latin Small Letter O With Diaeresis",
+            "numbers": [1e2, -0.5, 10, true, null],
+            "escapes": "\u{7f}\u{1f}/\"",
+        });
+        let mut buf = Vec::new();
+        sort_keys(value)
+            .serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buf,
+                CanonicalFormatter,
+            ))
+            .unwrap();
+
+        // U+1F600 is a surrogate pair in UTF-16, so it sorts before U+FB33, unlike in UTF-8.
+        let expected = concat!(
+            r#"{"\r":"Carriage Return","1":"One","escapes":""#,
+            "\u{7f}",
+            r#"\u001f/\"","#,
+            r#""numbers":[100,-0.5,10,true,null],"#,
+            "\"\u{80}\":\"Control\",\"\u{f6}\":\"Latin Small Letter O With Diaeresis\",",
+            "\"\u{20ac}\":\"Euro Sign\",\"\u{1f600}\":\"Emoji: Grinning Face\",",
+            "\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}",
+        );
+        assert_eq!(expected, String::from_utf8(buf).unwrap());
+    }
+}
@@ -0,0 +1,330 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read};
+use std::rc::Rc;
+use std::str;
+
+/// A shared slot for the first I/O error hit by a `ReadChars`. The YAML parser only sees an
+/// iterator of chars, so an error ends the iterator early and is reported through this slot.
+pub(crate) type IOErrorSlot = Rc<RefCell<Option<io::Error>>>;
+
+/// `ReadChars` incrementally decodes a UTF-8 reader into chars for the YAML parser, holding at
+/// most one buffer's worth of input in memory at a time.
+pub(crate) struct ReadChars<R: Read> {
+    reader: BufReader<R>,
+    chunk: String,
+    pos: usize,
+    // Bytes of a multi-byte char which was split across two reads.
+    carry: Vec<u8>,
+    error: IOErrorSlot,
+    line: LastLineSlot,
+}
+
+impl<R: Read> ReadChars<R> {
+    pub(crate) fn new(reader: R) -> (Self, IOErrorSlot) {
+        let error = IOErrorSlot::default();
+        let chars = ReadChars {
+            reader: BufReader::new(reader),
+            chunk: String::new(),
+            pos: 0,
+            carry: Vec::new(),
+            error: error.clone(),
+            line: LastLineSlot::default(),
+        };
+
+        (chars, error)
+    }
+
+    /// `last_line()` returns the `LastLine` of the input, which is updated as it is read.
+    pub(crate) fn last_line(&self) -> LastLineSlot {
+        self.line.clone()
+    }
+
+    /// `fill()` replaces the current chunk with the next decoded chunk of input. It returns false
+    /// once the reader is exhausted.
+    fn fill(&mut self) -> io::Result<bool> {
+        loop {
+            let buf = self.reader.fill_buf()?;
+            if buf.is_empty() {
+                if self.carry.is_empty() {
+                    return Ok(false);
+                }
+
+                return Err(invalid_utf8());
+            }
+
+            let len = buf.len();
+            self.carry.extend_from_slice(buf);
+            self.reader.consume(len);
+
+            let valid = match str::from_utf8(&self.carry) {
+                Ok(_) => self.carry.len(),
+                // An incomplete char at the end of the buffer is fine, the rest will follow. Any
+                // invalid bytes are reported once everything before them has been read.
+                Err(e) if e.error_len().is_none() || e.valid_up_to() > 0 => e.valid_up_to(),
+                Err(_) => return Err(invalid_utf8()),
+            };
+
+            if valid > 0 {
+                // Safe to unwrap, the first `valid` bytes were just checked.
+                self.chunk = str::from_utf8(&self.carry[..valid]).unwrap().to_owned();
+                self.line.borrow_mut().read(&self.chunk);
+                self.pos = 0;
+                self.carry.drain(..valid);
+                return Ok(true);
+            }
+        }
+    }
+}
+
+/// The most of the last line of the input which a `LastLine` keeps, in bytes.
+const MAX_TAIL: usize = 4096;
+
+/// A shared `LastLine`, which is updated as the YAML parser reads the input.
+pub(crate) type LastLineSlot = Rc<RefCell<LastLine>>;
+
+/// `LastLine` is the end of the last line of the input read so far, and whether the YAML parser
+/// has read all of it. yaml-rust2 adds a line break to a block scalar whose last line ends the
+/// input without one, which YAML's chomping doesn't, and `unterminated()` tells when it has.
+#[derive(Default)]
+pub(crate) struct LastLine {
+    /// The end of the line, up to twice `MAX_TAIL` bytes.
+    text: String,
+    /// Whether the start of the line was dropped from `text`.
+    truncated: bool,
+    /// Whether the parser has read the whole input.
+    ended: bool,
+}
+
+impl LastLine {
+    /// `of()` returns the `LastLine` of `document`, which is read as a whole.
+    pub(crate) fn of(document: &str) -> LastLineSlot {
+        let mut line = LastLine::default();
+        line.read(document);
+
+        Rc::new(RefCell::new(line))
+    }
+
+    /// `read()` follows the input through `s`, the next part of it.
+    fn read(&mut self, s: &str) {
+        let s = match s.rfind(['\n', '\r']) {
+            Some(i) => {
+                self.text.clear();
+                self.truncated = false;
+                &s[i + 1..]
+            }
+            None => s,
+        };
+
+        let mut start = s.len().saturating_sub(MAX_TAIL);
+        while !s.is_char_boundary(start) {
+            start += 1;
+        }
+        if start > 0 {
+            self.text.clear();
+            self.truncated = true;
+        }
+        self.text.push_str(&s[start..]);
+
+        if self.text.len() > 2 * MAX_TAIL {
+            let mut start = self.text.len() - MAX_TAIL;
+            while !self.text.is_char_boundary(start) {
+                start += 1;
+            }
+            self.text.drain(..start);
+            self.truncated = true;
+        }
+    }
+
+    /// `unterminated()` returns whether `value`, a block scalar which was just read with its
+    /// content indented by `indent`, ends the input on a line without a line break, so that its
+    /// last line break was added by the parser.
+    pub(crate) fn unterminated(&self, value: &str, indent: usize) -> bool {
+        let value = match value.strip_suffix('\n') {
+            Some(value) if self.ended => value,
+            _ => return false,
+        };
+        let text = if self.truncated {
+            &self.text[..]
+        } else {
+            self.text.trim_start()
+        };
+
+        if text.is_empty() {
+            // A line of spaces is only part of the scalar if it is indented as far as its content.
+            !self.truncated
+                && self.text.len() >= indent.max(1)
+                && self.text.bytes().all(|b| b == b' ')
+        } else {
+            value.ends_with(text)
+        }
+    }
+}
+
+/// The positions of the first chars of lines indented with tabs which `Tail` has passed to the YAML
+/// parser, as a line from 1 and a column from 0 like a `Marker`.
+pub(crate) type TabIndentsSlot = Rc<RefCell<VecDeque<(usize, usize)>>>;
+
+/// `Tail` passes chars through to the YAML parser, noting in their `LastLine` when they run out.
+///
+/// It also notes which lines are indented with tabs, which YAML forbids. yaml-rust2 only checks
+/// this within block collections, so the reader checks a document's top level node itself. The
+/// reader drops the positions it has passed, so only those the parser has read ahead are kept.
+pub(crate) struct Tail<T> {
+    chars: T,
+    line: LastLineSlot,
+    tab_indents: TabIndentsSlot,
+    /// The position of the next char.
+    row: usize,
+    col: usize,
+    /// Whether the indentation of the current line includes a tab, until its first other char.
+    indent: Option<bool>,
+    after_cr: bool,
+}
+
+impl<T: Iterator<Item = char>> Tail<T> {
+    pub(crate) fn new(chars: T, line: LastLineSlot) -> (Self, TabIndentsSlot) {
+        let tab_indents = TabIndentsSlot::default();
+        let tail = Tail {
+            chars,
+            line,
+            tab_indents: tab_indents.clone(),
+            row: 1,
+            col: 0,
+            indent: Some(false),
+            after_cr: false,
+        };
+
+        (tail, tab_indents)
+    }
+}
+
+impl<T: Iterator<Item = char>> Iterator for Tail<T> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        match c {
+            None => self.line.borrow_mut().ended = true,
+            // yaml-rust2 counts `\r\n` as a single line break.
+            Some('\n') if self.after_cr => {}
+            Some('\n' | '\r') => {
+                self.row += 1;
+                self.col = 0;
+                self.indent = Some(false);
+            }
+            Some(c) => {
+                match (self.indent, c) {
+                    (Some(_), ' ') => {}
+                    (Some(_), '\t') => self.indent = Some(true),
+                    (Some(tabs), _) => {
+                        if tabs {
+                            self.tab_indents
+                                .borrow_mut()
+                                .push_back((self.row, self.col));
+                        }
+                        self.indent = None;
+                    }
+                    (None, _) => {}
+                }
+                self.col += 1;
+            }
+        }
+        self.after_cr = c == Some('\r');
+
+        c
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "stream did not contain valid UTF-8",
+    )
+}
+
+impl<R: Read> Iterator for ReadChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.pos >= self.chunk.len() {
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => {
+                    self.error.borrow_mut().get_or_insert(e);
+                    return None;
+                }
+            }
+        }
+
+        let c = self.chunk[self.pos..].chars().next()?;
+        self.pos += c.len_utf8();
+
+        Some(c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chars::{LastLine, ReadChars, Tail, MAX_TAIL};
+    use std::io::Read;
+
+    /// A reader which returns a single byte per read, splitting up multi-byte chars.
+    struct ByteReader<'a>(&'a [u8]);
+
+    impl Read for ByteReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.split_first() {
+                Some((b, rest)) if !buf.is_empty() => {
+                    buf[0] = *b;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn decodes_split_chars() {
+        let input = "héllo: wörld ✓";
+        let (chars, error) = ReadChars::new(ByteReader(input.as_bytes()));
+
+        assert_eq!(input, chars.collect::<String>());
+        assert!(error.borrow().is_none());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let input: &[u8] = b"abc: \xff";
+        let (chars, error) = ReadChars::new(input);
+
+        assert_eq!("abc: ", chars.collect::<String>());
+        assert!(error.borrow().is_some());
+    }
+
+    #[test]
+    fn last_line() {
+        let line = LastLine::of("a: |\n  text");
+        assert!(!line.borrow().unterminated("text\n", 2));
+        line.borrow_mut().ended = true;
+        assert!(line.borrow().unterminated("text\n", 2));
+        assert!(!line.borrow().unterminated("text", 2));
+        assert!(!line.borrow().unterminated("other\n", 2));
+
+        let line = LastLine::of("a: |\n  text\n");
+        line.borrow_mut().ended = true;
+        assert!(!line.borrow().unterminated("text\n", 2));
+
+        // The line is followed across reads, keeping only the end of a long line.
+        let long = "é".repeat(3 * MAX_TAIL);
+        let input = format!("a: |\n  {}", long);
+        let (chars, _) = ReadChars::new(ByteReader(input.as_bytes()));
+        let line = chars.last_line();
+        Tail::new(chars, line.clone()).0.for_each(drop);
+
+        assert!(line.borrow().text.len() <= 2 * MAX_TAIL);
+        assert!(line.borrow().unterminated(&(long + "\n"), 2));
+    }
+}
@@ -0,0 +1,168 @@
+use serde_json::ser::Formatter;
+use std::io::{self, Write};
+
+const KEY: &[u8] = b"\x1b[1;34m";
+const STRING: &[u8] = b"\x1b[32m";
+const NUMBER: &[u8] = b"\x1b[36m";
+const BOOL: &[u8] = b"\x1b[33m";
+const NULL: &[u8] = b"\x1b[90m";
+const RESET: &[u8] = b"\x1b[0m";
+
+/// `ColorFormatter` wraps another formatter, highlighting keys, strings, numbers, booleans and
+/// null with ANSI escape codes.
+pub(crate) struct ColorFormatter<F> {
+    inner: F,
+    in_key: bool,
+}
+
+impl<F: Formatter> ColorFormatter<F> {
+    pub(crate) fn new(inner: F) -> Self {
+        ColorFormatter {
+            inner,
+            in_key: false,
+        }
+    }
+}
+
+/// `colored()` writes `color`, then whatever `f` writes, then resets the color.
+fn colored<W: ?Sized + Write>(
+    w: &mut W,
+    color: &[u8],
+    f: impl FnOnce(&mut W) -> io::Result<()>,
+) -> io::Result<()> {
+    w.write_all(color)?;
+    f(w)?;
+    w.write_all(RESET)
+}
+
+impl<F: Formatter> Formatter for ColorFormatter<F> {
+    fn write_null<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        colored(w, NULL, |w| self.inner.write_null(w))
+    }
+
+    fn write_bool<W: ?Sized + Write>(&mut self, w: &mut W, value: bool) -> io::Result<()> {
+        colored(w, BOOL, |w| self.inner.write_bool(w, value))
+    }
+
+    fn write_i64<W: ?Sized + Write>(&mut self, w: &mut W, value: i64) -> io::Result<()> {
+        colored(w, NUMBER, |w| self.inner.write_i64(w, value))
+    }
+
+    fn write_u64<W: ?Sized + Write>(&mut self, w: &mut W, value: u64) -> io::Result<()> {
+        colored(w, NUMBER, |w| self.inner.write_u64(w, value))
+    }
+
+    fn write_f64<W: ?Sized + Write>(&mut self, w: &mut W, value: f64) -> io::Result<()> {
+        colored(w, NUMBER, |w| self.inner.write_f64(w, value))
+    }
+
+    fn write_number_str<W: ?Sized + Write>(&mut self, w: &mut W, value: &str) -> io::Result<()> {
+        colored(w, NUMBER, |w| self.inner.write_number_str(w, value))
+    }
+
+    fn begin_string<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        w.write_all(if self.in_key { KEY } else { STRING })?;
+        self.inner.begin_string(w)
+    }
+
+    fn end_string<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_string(w)?;
+        w.write_all(RESET)
+    }
+
+    fn write_string_fragment<W: ?Sized + Write>(
+        &mut self,
+        w: &mut W,
+        fragment: &str,
+    ) -> io::Result<()> {
+        self.inner.write_string_fragment(w, fragment)
+    }
+
+    fn write_char_escape<W: ?Sized + Write>(
+        &mut self,
+        w: &mut W,
+        char_escape: serde_json::ser::CharEscape,
+    ) -> io::Result<()> {
+        self.inner.write_char_escape(w, char_escape)
+    }
+
+    fn begin_array<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.begin_array(w)
+    }
+
+    fn end_array<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_array(w)
+    }
+
+    fn begin_array_value<W: ?Sized + Write>(&mut self, w: &mut W, first: bool) -> io::Result<()> {
+        self.inner.begin_array_value(w, first)
+    }
+
+    fn end_array_value<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_array_value(w)
+    }
+
+    fn begin_object<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.begin_object(w)
+    }
+
+    fn end_object<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_object(w)
+    }
+
+    fn begin_object_key<W: ?Sized + Write>(&mut self, w: &mut W, first: bool) -> io::Result<()> {
+        self.in_key = true;
+        self.inner.begin_object_key(w, first)
+    }
+
+    fn end_object_key<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.in_key = false;
+        self.inner.end_object_key(w)
+    }
+
+    fn begin_object_value<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.begin_object_value(w)
+    }
+
+    fn end_object_value<W: ?Sized + Write>(&mut self, w: &mut W) -> io::Result<()> {
+        self.inner.end_object_value(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color::ColorFormatter;
+    use serde::Serialize;
+    use serde_json::json;
+    use serde_json::ser::{CompactFormatter, PrettyFormatter};
+
+    #[test]
+    fn compact() {
+        let value = json!({"a": ["b", 1, true, null]});
+        let mut buf = Vec::new();
+        let formatter = ColorFormatter::new(CompactFormatter);
+        value
+            .serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buf, formatter,
+            ))
+            .unwrap();
+
+        let expected = "{\x1b[1;34m\"a\"\x1b[0m:[\x1b[32m\"b\"\x1b[0m,\x1b[36m1\x1b[0m,\x1b[33mtrue\x1b[0m,\x1b[90mnull\x1b[0m]}";
+        assert_eq!(expected, String::from_utf8(buf).unwrap());
+    }
+
+    #[test]
+    fn pretty() {
+        let value = json!({"a": {"b": "c"}});
+        let mut buf = Vec::new();
+        let formatter = ColorFormatter::new(PrettyFormatter::new());
+        value
+            .serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buf, formatter,
+            ))
+            .unwrap();
+
+        let expected = "{\n  \x1b[1;34m\"a\"\x1b[0m: {\n    \x1b[1;34m\"b\"\x1b[0m: \x1b[32m\"c\"\x1b[0m\n  }\n}";
+        assert_eq!(expected, String::from_utf8(buf).unwrap());
+    }
+}
@@ -0,0 +1,361 @@
+use crate::query;
+use crate::Yaml2JsonError;
+use serde_json::{Map, Value};
+use yaml_rust2::parser::{Event, Parser};
+use yaml_rust2::scanner::Marker;
+
+/// `Comment` is a YAML comment, and the node it describes. See `Yaml2Json::document_comments()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comment {
+    /// The JSON Pointer (RFC 6901) of the mapping entry or sequence item the comment belongs to,
+    /// or `""` for the document as a whole.
+    pub path: String,
+    /// The line of the comment, starting from 1.
+    pub line: usize,
+    /// The text of the comment, without the `#` and the space after it.
+    pub text: String,
+}
+
+/// `collect()` finds the comments in `document` and the node each of them belongs to.
+///
+/// A comment after a node, on the same line, belongs to the last mapping entry which starts on
+/// that line, or else the last sequence item, or else the entry or item before it. A comment on a
+/// line of its own belongs to the next entry or item. Comments which aren't followed by anything
+/// belong to the document.
+pub(crate) fn collect(document: &str) -> Result<Vec<Comment>, Yaml2JsonError> {
+    let comments = scan(document);
+    if comments.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let nodes = nodes(document)?;
+
+    Ok(comments
+        .into_iter()
+        .map(|(line, trailing, text)| {
+            let node = if trailing {
+                let mut on_line = nodes.iter().rev().filter(|n| n.line == line);
+                on_line
+                    .clone()
+                    .find(|n| n.key)
+                    .or_else(|| on_line.next())
+                    .or_else(|| nodes.iter().rev().find(|n| n.line < line))
+            } else {
+                nodes.iter().find(|n| n.line > line)
+            };
+
+            Comment {
+                path: node.map(|n| n.path.clone()).unwrap_or_default(),
+                line,
+                text,
+            }
+        })
+        .collect())
+}
+
+/// `embed()` adds `comments` to the root mapping of `value`, under `key`, as an object from each
+/// path to its comments. A path with more than one comment has them joined by newlines. Nothing
+/// is added when there are no comments, or the root isn't a mapping.
+pub(crate) fn embed(value: &mut Value, key: &str, comments: Vec<Comment>) {
+    let root = match value {
+        Value::Object(root) if !comments.is_empty() => root,
+        _ => return,
+    };
+    let mut embedded = Map::new();
+
+    for comment in comments {
+        match embedded.get_mut(&comment.path) {
+            Some(Value::String(text)) => {
+                text.push('\n');
+                text.push_str(&comment.text);
+            }
+            _ => {
+                embedded.insert(comment.path, Value::String(comment.text));
+            }
+        }
+    }
+
+    root.insert(key.to_string(), Value::Object(embedded));
+}
+
+/// `Node` is the start of a mapping entry or sequence item.
+struct Node {
+    line: usize,
+    path: String,
+    /// Whether this is a mapping entry, rather than a sequence item.
+    key: bool,
+}
+
+enum Frame {
+    Seq(usize),
+    /// The key of the current entry, once it has been seen.
+    Map(Option<String>),
+}
+
+/// `nodes()` lists the mapping entries and sequence items in `document`, in order.
+fn nodes(document: &str) -> Result<Vec<Node>, Yaml2JsonError> {
+    let mut parser = Parser::new_from_str(document);
+    let mut nodes = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut path: Vec<String> = Vec::new();
+
+    loop {
+        let (event, marker): (Event, Marker) = parser.next_token()?;
+        let line = marker.line();
+
+        let start = match &event {
+            Event::StreamEnd => break,
+            Event::SequenceEnd | Event::MappingEnd => {
+                frames.pop();
+                // The root has no segment.
+                if !frames.is_empty() {
+                    path.pop();
+                }
+                continue;
+            }
+            Event::Scalar(..) | Event::Alias(..) => false,
+            Event::SequenceStart(..) | Event::MappingStart(..) => true,
+            _ => continue,
+        };
+
+        let segment = match frames.last_mut() {
+            None => None,
+            Some(Frame::Seq(i)) => {
+                let segment = i.to_string();
+                *i += 1;
+                nodes.push(node(&path, &segment, line, false));
+                Some(segment)
+            }
+            Some(Frame::Map(key @ None)) => {
+                // Complex keys aren't followed, only their values.
+                let segment = match &event {
+                    Event::Scalar(value, ..) => value.clone(),
+                    _ => String::new(),
+                };
+                nodes.push(node(&path, &segment, line, true));
+                *key = Some(segment);
+
+                if start {
+                    skip(&mut parser)?;
+                }
+                continue;
+            }
+            Some(Frame::Map(key)) => key.take(),
+        };
+
+        if start {
+            frames.push(match event {
+                Event::SequenceStart(..) => Frame::Seq(0),
+                _ => Frame::Map(None),
+            });
+            if let Some(segment) = segment {
+                path.push(segment);
+            }
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn node(path: &[String], segment: &str, line: usize, key: bool) -> Node {
+    Node {
+        line,
+        path: query::pointer(path.iter().map(String::as_str).chain([segment])),
+        key,
+    }
+}
+
+/// `skip()` skips the rest of a sequence or mapping, after its start.
+fn skip<T: Iterator<Item = char>>(parser: &mut Parser<T>) -> Result<(), Yaml2JsonError> {
+    let mut open = 1;
+
+    while open > 0 {
+        match parser.next_token()?.0 {
+            Event::SequenceStart(..) | Event::MappingStart(..) => open += 1,
+            Event::SequenceEnd | Event::MappingEnd => open -= 1,
+            Event::StreamEnd => break,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// `scan()` finds the comments in `document`, returning the line of each, whether it follows
+/// something else on that line, and its text. The parser doesn't report comments, so this skips
+/// over quoted and block scalars itself.
+fn scan(document: &str) -> Vec<(usize, bool, String)> {
+    let mut comments = Vec::new();
+    let mut quote = None;
+    // The indentation of the line which started the current block scalar.
+    let mut block = None;
+
+    for (i, line) in document.lines().enumerate() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+
+        if let Some(block_indent) = block {
+            if line.trim().is_empty() || indent > block_indent {
+                continue;
+            }
+            block = None;
+        }
+
+        let mut chars = line.char_indices().peekable();
+        let mut prev = None;
+        let mut content = false;
+        let mut code = line;
+
+        while let Some((j, c)) = chars.next() {
+            match (quote, c) {
+                (Some('"'), '\\') => {
+                    chars.next();
+                }
+                (Some('"'), '"') => quote = None,
+                (Some('\''), '\'') => {
+                    if chars.peek().map(|&(_, c)| c) == Some('\'') {
+                        chars.next();
+                    } else {
+                        quote = None;
+                    }
+                }
+                (Some(_), _) => {}
+                (None, '#') if prev.is_none_or(char::is_whitespace) => {
+                    let text = line[j + 1..].strip_prefix(' ').unwrap_or(&line[j + 1..]);
+                    comments.push((i + 1, content, text.trim_end().to_string()));
+                    code = &line[..j];
+                    break;
+                }
+                (None, '"' | '\'')
+                    if prev.is_none_or(|p: char| p.is_whitespace() || "[{,".contains(p)) =>
+                {
+                    quote = Some(c);
+                }
+                (None, _) => {}
+            }
+
+            content |= !c.is_whitespace();
+            prev = Some(c);
+        }
+
+        if quote.is_none() && starts_block(code) {
+            block = Some(indent);
+        }
+    }
+
+    comments
+}
+
+/// `starts_block()` returns whether `line` ends with a block scalar indicator, e.g. `key: |` or
+/// `- >-`.
+fn starts_block(line: &str) -> bool {
+    let line = line.trim_end();
+    let token = line.rsplit(' ').next().unwrap_or(line);
+
+    let mut chars = token.chars();
+    matches!(chars.next(), Some('|' | '>'))
+        && chars.all(|c| c.is_ascii_digit() || c == '-' || c == '+')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comments::{collect, embed, Comment};
+    use serde_json::json;
+
+    fn comments(document: &str) -> Vec<(String, String)> {
+        collect(document)
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.path, c.text))
+            .collect()
+    }
+
+    fn pair(path: &str, text: &str) -> (String, String) {
+        (path.to_string(), text.to_string())
+    }
+
+    #[test]
+    fn paths() {
+        let input = r#"# The service.
+name: web # trailing
+spec:
+  # How many.
+  replicas: 2
+  ports: [80, 443] # flow
+  containers:
+    # The first.
+    - image: nginx # image
+      args:
+        - -v
+  "a/b~": 1 # escaped
+# The end.
+"#;
+        assert_eq!(
+            vec![
+                pair("/name", "The service."),
+                pair("/name", "trailing"),
+                pair("/spec/replicas", "How many."),
+                pair("/spec/ports", "flow"),
+                pair("/spec/containers/0", "The first."),
+                pair("/spec/containers/0/image", "image"),
+                pair("/spec/a~1b~0", "escaped"),
+                pair("", "The end."),
+            ],
+            comments(input)
+        );
+    }
+
+    #[test]
+    fn not_comments() {
+        let input = r##"a: b#c
+d: "e # f" # g
+h: 'i '' # j
+  k' # l
+m: |
+  # n
+  o
+# p
+q: >-
+  # r
+s: [t, "#u"] # v
+"##;
+        assert_eq!(
+            vec![
+                pair("/d", "g"),
+                pair("/h", "l"),
+                pair("/q", "p"),
+                pair("/s", "v"),
+            ],
+            comments(input)
+        );
+    }
+
+    #[test]
+    fn lines() {
+        let comments = collect("a: 1\n\n# b\nc: 2\n").unwrap();
+        assert_eq!(
+            vec![Comment {
+                path: "/c".to_string(),
+                line: 3,
+                text: "b".to_string()
+            }],
+            comments
+        );
+        assert!(collect("a: 1").unwrap().is_empty());
+        assert!(collect("# a\n[1").is_err());
+    }
+
+    #[test]
+    fn embedding() {
+        let input = "# a\n# b\nc: 1 # d\ne: 2\n";
+        let mut value = json!({"c": 1, "e": 2});
+        embed(&mut value, "$comments", collect(input).unwrap());
+        assert_eq!(
+            json!({"c": 1, "e": 2, "$comments": {"/c": "a\nb\nd"}}),
+            value
+        );
+
+        let mut value = json!([1]);
+        embed(&mut value, "$comments", collect("# a\n- 1").unwrap());
+        assert_eq!(json!([1]), value);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+/// `EnvSubst` configures the expansion of environment variables in scalars, see
+/// `Yaml2JsonOptions::env_subst()`.
+/// ```
+/// use yaml2json_rs::{EnvSubst, UndefinedVars};
+///
+/// let env = EnvSubst {
+///     allow: Some(vec!["HOME".to_string(), "USER".to_string()]),
+///     undefined: UndefinedVars::Error,
+///     ..EnvSubst::default()
+/// };
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EnvSubst {
+    /// The variables which may be referenced, or `None` for any variable. References to other
+    /// variables are left as they are written. This defaults to `None`.
+    pub allow: Option<Vec<String>>,
+    /// What references to variables which aren't set are replaced with, see `UndefinedVars`.
+    pub undefined: UndefinedVars,
+    /// The variables to use instead of the environment of the process, e.g. for tests or in an
+    /// environment without one. This defaults to `None`, reading the environment.
+    pub vars: Option<HashMap<String, String>>,
+}
+
+/// `UndefinedVars` defines how `EnvSubst` handles references to variables which aren't set, and
+/// have no default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum UndefinedVars {
+    /// `UndefinedVars::Empty` replaces them with nothing, as a shell would. This is the default.
+    #[default]
+    Empty,
+    /// `UndefinedVars::Error` fails the document with `Yaml2JsonError::UndefinedVariableError`.
+    Error,
+}
+
+impl EnvSubst {
+    fn allowed(&self, name: &str) -> bool {
+        self.allow
+            .as_ref()
+            .is_none_or(|allow| allow.iter().any(|a| a == name))
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        match &self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+}
+
+/// `substitute()` expands the references to variables in `value`. `${NAME}` is replaced with the
+/// value of `NAME`, and `${NAME:-default}` with `default` when `NAME` isn't set or is empty.
+/// `$${` is a literal `${`, and anything else is left as it is, e.g. `$NAME` or `${1}`. It fails
+/// with the name of a variable which isn't set, with `UndefinedVars::Error`.
+pub(crate) fn substitute(value: String, env: &EnvSubst) -> Result<String, String> {
+    if !value.contains("${") {
+        return Ok(value);
+    }
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value.as_str();
+
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if let Some(after) = rest.strip_prefix("$${") {
+            out.push_str("${");
+            rest = after;
+            continue;
+        }
+
+        match reference(rest) {
+            Some((name, default, len)) if env.allowed(name) => {
+                match (env.get(name), default) {
+                    (Some(value), Some(default)) if value.is_empty() => out.push_str(default),
+                    (Some(value), _) => out.push_str(&value),
+                    (None, Some(default)) => out.push_str(default),
+                    (None, None) if env.undefined == UndefinedVars::Error => {
+                        return Err(name.to_string())
+                    }
+                    (None, None) => {}
+                }
+                rest = &rest[len..];
+            }
+            _ => {
+                out.push('$');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// `reference()` parses a `${NAME}` or `${NAME:-default}` reference at the start of `s`,
+/// returning the name, the default and the length of the reference.
+fn reference(s: &str) -> Option<(&str, Option<&str>, usize)> {
+    let inner = s.strip_prefix("${")?;
+    let end = inner.find('}')?;
+    let (name, default) = match inner[..end].split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (&inner[..end], None),
+    };
+
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    valid.then_some((name, default, end + 3))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::env::{substitute, EnvSubst, UndefinedVars};
+
+    fn env() -> EnvSubst {
+        EnvSubst {
+            vars: Some(
+                [("HOST", "db"), ("PORT", "5432"), ("EMPTY", "")]
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            ..EnvSubst::default()
+        }
+    }
+
+    fn subst(value: &str, env: &EnvSubst) -> Result<String, String> {
+        substitute(value.to_string(), env)
+    }
+
+    #[test]
+    fn references() {
+        let env = env();
+        let ok = |value| subst(value, &env).unwrap();
+
+        assert_eq!("db:5432", ok("${HOST}:${PORT}"));
+        assert_eq!("db", ok("${HOST:-localhost}"));
+        assert_eq!("x", ok("${EMPTY:-x}"));
+        assert_eq!("localhost", ok("${UNSET:-localhost}"));
+        assert_eq!("a-", ok("a-${UNSET}"));
+        assert_eq!("", ok("${UNSET:-}"));
+        assert_eq!("${HOST} db", ok("$${HOST} ${HOST}"));
+        assert_eq!(
+            "$HOST ${1} ${HO-ST} ${HOST",
+            ok("$HOST ${1} ${HO-ST} ${HOST")
+        );
+        assert_eq!("🦀${HOST}$", ok("🦀$${HOST}$"));
+    }
+
+    #[test]
+    fn undefined() {
+        let env = EnvSubst {
+            undefined: UndefinedVars::Error,
+            ..env()
+        };
+
+        assert_eq!(Err("UNSET".to_string()), subst("${HOST}${UNSET}", &env));
+        assert_eq!(Ok("x".to_string()), subst("${UNSET:-x}", &env));
+        assert_eq!(Ok("".to_string()), subst("${EMPTY}", &env));
+    }
+
+    #[test]
+    fn allow() {
+        let env = EnvSubst {
+            allow: Some(vec!["HOST".to_string()]),
+            undefined: UndefinedVars::Error,
+            ..env()
+        };
+
+        assert_eq!(
+            Ok("db ${PORT} ${UNSET}".to_string()),
+            subst("${HOST} ${PORT} ${UNSET}", &env)
+        );
+    }
+}
@@ -0,0 +1,1395 @@
+use crate::chars::{IOErrorSlot, LastLine, LastLineSlot, ReadChars, TabIndentsSlot, Tail};
+use crate::env;
+use crate::query;
+use crate::scalar;
+use crate::{DuplicateKeys, KeyMode, Limit, MergeKeys, Yaml2Json, Yaml2JsonError};
+use serde::ser::{Error, SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::rc::Rc;
+use std::str::Chars;
+use yaml_rust2::parser::{Event, Parser, Tag};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+type Recorded = Rc<Vec<(Event, Marker)>>;
+
+/// An anchored node whose events are still being recorded.
+struct Recording {
+    anchor: usize,
+    open: usize,
+    events: Vec<(Event, Marker)>,
+}
+
+/// A mapping key, see `Events::key()`.
+enum Key {
+    Name(String),
+    /// The `<<` merge key, only used when merge keys are not kept as regular keys.
+    Merge,
+}
+
+/// An entry of a mapping whose merge keys are being resolved.
+enum Entry {
+    Value(String, Value),
+    Merge(Vec<Map<String, Value>>),
+}
+
+/// `Events` wraps the YAML parser, expanding aliases by replaying the events recorded for their
+/// anchor. Only anchored nodes are ever held in memory, everything else is passed through as soon
+/// as the parser produces it.
+///
+/// With `Yaml2JsonOptions::alias_refs()`, aliases of anchors which have already been output are
+/// passed through instead, and the JSON Pointer of each anchored node is kept to refer to it.
+pub(crate) struct Events<'a, T: Iterator<Item = char>> {
+    y2j: &'a Yaml2Json,
+    parser: Parser<Tail<T>>,
+    /// The end of the input read so far, see `Tail`.
+    last_line: LastLineSlot,
+    tab_indents: TabIndentsSlot,
+    /// Whether the next event is the top level node of a document.
+    at_root: bool,
+    /// An event read from the parser ahead of time, by `ends_input()`.
+    lookahead: Option<(Event, Marker)>,
+    peeked: Option<(Event, Marker)>,
+    anchors: HashMap<usize, Recorded>,
+    recordings: Vec<Recording>,
+    replays: Vec<(Recorded, usize)>,
+    /// The JSON Pointer of each anchored node which has been output, for `alias_refs()`.
+    anchor_paths: HashMap<usize, String>,
+    /// The segments of the JSON Pointer of the current node, only tracked for `alias_refs()`.
+    path: Vec<String>,
+    /// How many nodes are being converted for something other than output, i.e. keys and merged
+    /// mappings, within which aliases are always expanded.
+    expanding: usize,
+    depth: usize,
+    alias_expansions: usize,
+    output_size: usize,
+    io_error: Option<IOErrorSlot>,
+    /// Whether the output is written as it is converted, see `direct()`.
+    direct: bool,
+    /// Whether mappings are held until their end to replace duplicate keys, see `hold_mappings()`.
+    hold_mappings: bool,
+    // The error behind the most recent failure, as serde only lets us pass a message through.
+    error: Option<Yaml2JsonError>,
+}
+
+impl<'a, 'b> Events<'a, Chars<'b>> {
+    /// `new()` parses the YAML in `document`.
+    pub(crate) fn new(y2j: &'a Yaml2Json, document: &'b str) -> Self {
+        Events::with_chars(y2j, document.chars(), LastLine::of(document), None)
+    }
+}
+
+impl<'a, R: Read> Events<'a, ReadChars<R>> {
+    /// `from_reader()` parses the YAML read from `reader`, reporting I/O errors instead of the
+    /// parse errors they cause.
+    pub(crate) fn from_reader(y2j: &'a Yaml2Json, reader: R) -> Self {
+        let (chars, io_error) = ReadChars::new(reader);
+        let last_line = chars.last_line();
+
+        Events::with_chars(y2j, chars, last_line, Some(io_error))
+    }
+}
+
+impl<'a, T: Iterator<Item = char>> Events<'a, T> {
+    fn with_chars(
+        y2j: &'a Yaml2Json,
+        chars: T,
+        last_line: LastLineSlot,
+        io_error: Option<IOErrorSlot>,
+    ) -> Self {
+        let (chars, tab_indents) = Tail::new(chars, last_line.clone());
+
+        Events {
+            y2j,
+            parser: Parser::new(chars),
+            last_line,
+            tab_indents,
+            at_root: false,
+            lookahead: None,
+            peeked: None,
+            anchors: HashMap::new(),
+            recordings: Vec::new(),
+            replays: Vec::new(),
+            anchor_paths: HashMap::new(),
+            path: Vec::new(),
+            expanding: 0,
+            depth: 0,
+            alias_expansions: 0,
+            output_size: 0,
+            io_error,
+            direct: false,
+            hold_mappings: false,
+            error: None,
+        }
+    }
+
+    /// `direct()` is used when the output is written as it is converted, so earlier values can't
+    /// be overwritten. Duplicate keys fail with `Yaml2JsonError::DuplicateKeyError` even with
+    /// `DuplicateKeys::Last`, for the caller to convert the document again as a `Value`.
+    pub(crate) fn direct(mut self) -> Self {
+        self.direct = true;
+        self
+    }
+
+    /// `hold_mappings()` is used when the output is written as it is converted and the input can't
+    /// be converted again. With `DuplicateKeys::Last`, each mapping is then held as a `Value` until
+    /// its end, as a later key can replace an earlier value, while sequences are still written as
+    /// they are read.
+    pub(crate) fn hold_mappings(mut self) -> Self {
+        self.hold_mappings = true;
+        self
+    }
+
+    /// `next_document()` moves to the start of the next document, returning false at the end of
+    /// the stream.
+    pub(crate) fn next_document(&mut self) -> Result<bool, Yaml2JsonError> {
+        loop {
+            match self.next()?.0 {
+                Event::DocumentStart => {
+                    self.anchors.clear();
+                    self.anchor_paths.clear();
+                    self.path.clear();
+                    self.alias_expansions = 0;
+                    self.output_size = 0;
+                    return Ok(true);
+                }
+                Event::StreamEnd => return Ok(false),
+                _ => continue,
+            }
+        }
+    }
+
+    /// `end_document()` checks that nothing but the end of the stream follows the current
+    /// document.
+    pub(crate) fn end_document(&mut self) -> Result<(), Yaml2JsonError> {
+        if self.next_document()? {
+            return Err(Yaml2JsonError::UnsupportedError(
+                "converting YAML containing more than one document is not supported".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<(Event, Marker), Yaml2JsonError> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(peeked);
+        }
+
+        loop {
+            let (event, mark) = self.next_raw()?;
+
+            if let Event::Alias(anchor) = event {
+                if self.expanding == 0 && self.anchor_paths.contains_key(&anchor) {
+                    return Ok((event, mark));
+                }
+
+                self.expand(anchor)?;
+                continue;
+            }
+
+            return Ok((event, mark));
+        }
+    }
+
+    /// `expand()` replays the events recorded for `anchor`, in place of an alias.
+    fn expand(&mut self, anchor: usize) -> Result<(), Yaml2JsonError> {
+        let events = self.anchors.get(&anchor).cloned().ok_or_else(|| {
+            Yaml2JsonError::UnsupportedError("recursive aliases are not supported".into())
+        })?;
+
+        self.alias_expansions += 1;
+        check_limit(
+            Limit::AliasExpansions,
+            self.alias_expansions,
+            self.y2j.options.limits.max_alias_expansions,
+        )?;
+
+        self.replays.push((events, 0));
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Result<&Event, Yaml2JsonError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.next()?);
+        }
+
+        // Safe to unwrap, the value was just set.
+        Ok(&self.peeked.as_ref().unwrap().0)
+    }
+
+    /// `next_raw()` returns the next event from the current alias replay, or from the parser.
+    fn next_raw(&mut self) -> Result<(Event, Marker), Yaml2JsonError> {
+        while let Some((events, pos)) = self.replays.last_mut() {
+            if let Some(event) = events.get(*pos) {
+                *pos += 1;
+                return Ok(event.clone());
+            }
+
+            self.replays.pop();
+        }
+
+        let (event, mark) = match self.lookahead.take() {
+            Some(next) => next,
+            None => self.parse()?,
+        };
+        let event = match event {
+            Event::Scalar(mut value, style, anchor, tag)
+                if matches!(style, TScalarStyle::Literal | TScalarStyle::Folded)
+                    && self.last_line.borrow().unterminated(&value, mark.col())
+                    && self.ends_input()? =>
+            {
+                value.pop();
+                Event::Scalar(value, style, anchor, tag)
+            }
+            event => event,
+        };
+        self.record(&event, mark);
+
+        Ok((event, mark))
+    }
+
+    /// `parse()` returns the next event from the parser, with its tag normalized.
+    fn parse(&mut self) -> Result<(Event, Marker), Yaml2JsonError> {
+        match self.parser.next_token() {
+            Ok((event, mark)) => {
+                self.check_indent(&event, mark)?;
+                Ok((normalize(event), mark))
+            }
+            // The parser only sees the end of its input when reading fails, the I/O error is the
+            // real cause.
+            Err(e) => match self.io_error.as_ref().and_then(|s| s.borrow_mut().take()) {
+                Some(io_error) => Err(io_error.into()),
+                None => Err(e.into()),
+            },
+        }
+    }
+
+    /// `check_indent()` fails if `event` is the top level node of a document, on a line which is
+    /// indented with tabs. yaml-rust2 checks this for the nodes within it, see `Tail`.
+    fn check_indent(&mut self, event: &Event, mark: Marker) -> Result<(), Yaml2JsonError> {
+        let mut tab_indents = self.tab_indents.borrow_mut();
+        while tab_indents
+            .front()
+            .is_some_and(|&(line, _)| line < mark.line())
+        {
+            tab_indents.pop_front();
+        }
+
+        let root = self.at_root;
+        self.at_root = matches!(event, Event::DocumentStart);
+        let node = matches!(
+            event,
+            Event::Scalar(..)
+                | Event::SequenceStart(..)
+                | Event::MappingStart(..)
+                | Event::Alias(_)
+        );
+        // The marker of a block mapping is past the start of its first key.
+        let indented = tab_indents
+            .front()
+            .filter(|&&(line, column)| line == mark.line() && column <= mark.col());
+        match indented {
+            Some(&(line, column)) if root && node => Err(Yaml2JsonError::ParseError {
+                message: "tabs disallowed within this context (block indentation)".to_string(),
+                line,
+                column: column + 1,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// `ends_input()` returns whether no node follows the one just parsed, looking at the next
+    /// event. This is only used once the whole input has been read.
+    fn ends_input(&mut self) -> Result<bool, Yaml2JsonError> {
+        let next = self.parse()?;
+        let ends = !matches!(
+            next.0,
+            Event::Scalar(..)
+                | Event::SequenceStart(..)
+                | Event::MappingStart(..)
+                | Event::Alias(_)
+        );
+        self.lookahead = Some(next);
+
+        Ok(ends)
+    }
+
+    fn record(&mut self, event: &Event, mark: Marker) {
+        let anchor = match event {
+            Event::Scalar(_, _, anchor, _)
+            | Event::SequenceStart(anchor, _)
+            | Event::MappingStart(anchor, _) => *anchor,
+            _ => 0,
+        };
+
+        if anchor > 0 {
+            self.recordings.push(Recording {
+                anchor,
+                open: 0,
+                events: Vec::new(),
+            });
+        }
+
+        for recording in self.recordings.iter_mut() {
+            recording.events.push((event.clone(), mark));
+
+            match event {
+                Event::SequenceStart(..) | Event::MappingStart(..) => recording.open += 1,
+                Event::SequenceEnd | Event::MappingEnd => recording.open -= 1,
+                _ => {}
+            }
+        }
+
+        // Nested anchors always finish before the anchors around them.
+        while self.recordings.last().is_some_and(|r| r.open == 0) {
+            // Safe to unwrap, checked above.
+            let recording = self.recordings.pop().unwrap();
+            self.anchors
+                .insert(recording.anchor, Rc::new(recording.events));
+        }
+    }
+
+    /// `refs()` returns whether aliases are output as references, see `alias_refs()`.
+    fn refs(&self) -> bool {
+        self.y2j.options.alias_refs.is_some()
+    }
+
+    /// `output_anchor()` keeps the JSON Pointer of a node which is about to be output, if it is
+    /// anchored. An anchor which is output more than once, as part of a larger expanded alias,
+    /// keeps its first location.
+    fn output_anchor(&mut self, event: &Event) {
+        let anchor = match event {
+            Event::Scalar(_, _, anchor, _)
+            | Event::SequenceStart(anchor, _)
+            | Event::MappingStart(anchor, _) => *anchor,
+            _ => 0,
+        };
+
+        if anchor > 0 && self.expanding == 0 && self.refs() {
+            let path = &self.path;
+            self.anchor_paths
+                .entry(anchor)
+                .or_insert_with(|| query::pointer(path.iter().map(String::as_str)));
+        }
+    }
+
+    /// `push_path()` adds the next segment to the location of the current node, for
+    /// `alias_refs()`. Each call must be followed by a call to `pop_path()`.
+    fn push_path(&mut self, segment: impl FnOnce() -> String) {
+        if self.refs() {
+            self.path.push(segment());
+        }
+    }
+
+    fn pop_path(&mut self) {
+        self.path.pop();
+    }
+
+    fn enter(&mut self) -> Result<(), Yaml2JsonError> {
+        self.depth += 1;
+        check_limit(
+            Limit::Depth,
+            self.depth,
+            Some(self.y2j.options.limits.max_depth),
+        )?;
+        self.output(2)
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// `output()` accounts for `size` more bytes of output. This is an estimate of the JSON size,
+    /// counting the text of each scalar and key plus a little for punctuation, so that it can be
+    /// tracked in the same way whether or not JSON is written.
+    fn output(&mut self, size: usize) -> Result<(), Yaml2JsonError> {
+        self.output_size += size;
+        check_limit(
+            Limit::OutputSize,
+            self.output_size,
+            self.y2j.options.limits.max_output_size,
+        )
+    }
+
+    /// `fail()` stores `e` so it can be recovered after serialization has been aborted.
+    fn fail<E: Error>(&mut self, e: Yaml2JsonError) -> E {
+        let err = E::custom(&e);
+        self.error = Some(e);
+        err
+    }
+
+    /// `take_error()` returns the error stored by `fail()`, or `fallback` if the failure
+    /// came from the serializer itself.
+    pub(crate) fn take_error(&mut self, fallback: impl Into<Yaml2JsonError>) -> Yaml2JsonError {
+        self.error.take().unwrap_or_else(|| fallback.into())
+    }
+
+    /// `skip_duplicate()` applies the `DuplicateKeys` policy to `key`, which was found at `marker`.
+    /// It returns true if the value of `key` has been skipped, as an earlier value takes precedence.
+    /// With `DuplicateKeys::Last`, it is up to the serializer to overwrite earlier values, see
+    /// `direct()`, and the anchors within them are forgotten. Keys which aren't skipped must be
+    /// added to `seen` by the caller, see `track_duplicates()`.
+    fn skip_duplicate(
+        &mut self,
+        seen: &HashSet<String>,
+        key: &str,
+        marker: Marker,
+    ) -> Result<bool, Yaml2JsonError> {
+        match self.y2j.options.duplicate_keys {
+            DuplicateKeys::Last if !self.direct => {
+                if seen.contains(key) {
+                    self.forget_anchors(key);
+                }
+                Ok(false)
+            }
+            _ if !seen.contains(key) => Ok(false),
+            DuplicateKeys::Error | DuplicateKeys::Last => Err(Yaml2JsonError::DuplicateKeyError {
+                key: key.to_string(),
+                line: marker.line(),
+                column: marker.col() + 1,
+            }),
+            DuplicateKeys::First => self.skip().map(|_| true),
+        }
+    }
+
+    /// `track_duplicates()` returns whether keys must be added to the set given to
+    /// `skip_duplicate()`.
+    fn track_duplicates(&self) -> bool {
+        self.direct || self.y2j.options.duplicate_keys != DuplicateKeys::Last || self.refs()
+    }
+
+    /// `forget_anchors()` drops the JSON Pointers of the anchored nodes within the value of `key`
+    /// in the current mapping, as a later value replaces it, so that their aliases are expanded.
+    fn forget_anchors(&mut self, key: &str) {
+        let path = query::pointer(self.path.iter().map(String::as_str).chain([key]));
+        let within = format!("{}/", path);
+        self.anchor_paths
+            .retain(|_, pointer| *pointer != path && !pointer.starts_with(&within));
+    }
+
+    /// `skip()` consumes the next node without converting it.
+    fn skip(&mut self) -> Result<(), Yaml2JsonError> {
+        let mut open = 0;
+
+        loop {
+            match self.next()?.0 {
+                Event::SequenceStart(..) | Event::MappingStart(..) => open += 1,
+                Event::SequenceEnd | Event::MappingEnd => open -= 1,
+                _ => {}
+            }
+
+            if open == 0 {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn check_limit(limit: Limit, value: usize, max: Option<usize>) -> Result<(), Yaml2JsonError> {
+    match max {
+        Some(max) if value > max => Err(Yaml2JsonError::LimitExceededError { limit, max }),
+        _ => Ok(()),
+    }
+}
+
+/// `Node` serializes the next YAML node from `events`, pulling events from the parser as the
+/// serializer asks for more output.
+pub(crate) struct Node<'a, 'b, T: Iterator<Item = char>> {
+    pub(crate) events: &'a RefCell<Events<'b, T>>,
+}
+
+impl<T: Iterator<Item = char>> Node<'_, '_, T> {
+    /// `key()` converts the next mapping key. Keys which don't resolve to a string, e.g. `1`,
+    /// `true` or `[a, b]`, are kept as written if they are scalars, and converted to their JSON
+    /// text otherwise, unless they are rejected by `KeyMode::Error`.
+    fn key(&self) -> Result<(Key, Marker), Yaml2JsonError> {
+        self.events.borrow_mut().expanding += 1;
+        let res = self.key_value();
+        self.events.borrow_mut().expanding -= 1;
+
+        res
+    }
+
+    fn key_value(&self) -> Result<(Key, Marker), Yaml2JsonError> {
+        let y2j = self.events.borrow().y2j;
+
+        // The key may have been peeked at before its alias would have been expanded.
+        let peeked = self.events.borrow_mut().peeked.take();
+        match peeked {
+            Some((Event::Alias(anchor), _)) => self.events.borrow_mut().expand(anchor)?,
+            peeked => self.events.borrow_mut().peeked = peeked,
+        }
+        let (event, marker) = self.events.borrow_mut().next()?;
+
+        let (text, value) = match event {
+            Event::Scalar(value, style, _, tag) => {
+                let merge = match &tag {
+                    Some(tag) => tag.handle == scalar::CORE_TAG_HANDLE && tag.suffix == "merge",
+                    None => style == TScalarStyle::Plain && value == "<<",
+                };
+
+                match y2j.options.merge_keys {
+                    MergeKeys::Resolve if merge => return Ok((Key::Merge, marker)),
+                    MergeKeys::Error if merge => {
+                        return Err(Yaml2JsonError::MergeKeyError {
+                            line: marker.line(),
+                            column: marker.col() + 1,
+                        })
+                    }
+                    _ => {}
+                }
+
+                // Only plain and tagged scalars may resolve to something other than a string.
+                let value = substitute(value, marker, y2j)?;
+                let text = (style == TScalarStyle::Plain || tag.is_some()).then(|| value.clone());
+                let value = scalar::resolve(value, style, tag.as_ref(), y2j)
+                    .map_err(|e| locate(e, marker, y2j))?;
+
+                (text, value)
+            }
+            event @ (Event::SequenceStart(..) | Event::MappingStart(..)) => {
+                self.events.borrow_mut().peeked = Some((event, marker));
+                let value = serde_json::to_value(self)
+                    .map_err(|e| self.events.borrow_mut().take_error(e))?;
+
+                (None, value)
+            }
+            event => {
+                return Err(Yaml2JsonError::UnsupportedError(format!(
+                    "unexpected YAML event {:?}",
+                    event
+                )))
+            }
+        };
+
+        let key = match (value, text) {
+            (Value::String(key), _) => key,
+            (value, text) if y2j.options.key_mode == KeyMode::Error => {
+                return Err(Yaml2JsonError::NonStringKeyError {
+                    key: text.unwrap_or_else(|| value.to_string()),
+                    line: marker.line(),
+                    column: marker.col() + 1,
+                })
+            }
+            (_, Some(text)) => text,
+            (value, None) => value.to_string(),
+        };
+
+        self.events.borrow_mut().output(key.len() + 4)?;
+        Ok((Key::Name(key), marker))
+    }
+
+    fn serialize_collection<S: Serializer>(
+        &self,
+        seq: bool,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if seq {
+            self.serialize_seq(serializer)
+        } else {
+            self.serialize_map(serializer)
+        }
+    }
+
+    fn serialize_seq<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+
+        for i in 0.. {
+            let mut events = self.events.borrow_mut();
+            match events.peek() {
+                Ok(Event::SequenceEnd) => {
+                    events.peeked = None;
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => return Err(events.fail(e)),
+            }
+            events.push_path(|| i.to_string());
+            drop(events);
+
+            seq.serialize_element(self)?;
+            self.events.borrow_mut().pop_path();
+        }
+
+        seq.end()
+    }
+
+    fn serialize_map<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let events = self.events.borrow();
+        let merge_keys = events.y2j.options.merge_keys;
+        let hold = events.hold_mappings && events.y2j.options.duplicate_keys == DuplicateKeys::Last;
+        drop(events);
+
+        if merge_keys == MergeKeys::Resolve {
+            return self.serialize_merged_map(serializer);
+        }
+
+        // Nothing is written until the end of a held mapping, which a later key can change.
+        if hold {
+            let mut held = Map::new();
+            self.map_entries(|key| {
+                let value = serde_json::to_value(self).map_err(S::Error::custom)?;
+                held.insert(key.to_string(), value);
+                Ok(())
+            })?;
+            return held.serialize(serializer);
+        }
+
+        let mut map = serializer.serialize_map(None)?;
+        self.map_entries(|key| map.serialize_entry(key, self))?;
+        map.end()
+    }
+
+    /// `map_entries()` calls `entry` with each key of a mapping, for it to convert the value after
+    /// the key. Duplicate keys are handled first, see `skip_duplicate()`.
+    fn map_entries<E: Error>(&self, mut entry: impl FnMut(&str) -> Result<(), E>) -> Result<(), E> {
+        let mut seen = HashSet::new();
+
+        loop {
+            let mut events = self.events.borrow_mut();
+            match events.peek() {
+                Ok(Event::MappingEnd) => {
+                    events.peeked = None;
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => return Err(events.fail(e)),
+            }
+            drop(events);
+
+            let key = self.key();
+            let mut events = self.events.borrow_mut();
+            let (key, marker) = match key {
+                Ok((Key::Name(key), marker)) => (key, marker),
+                Ok((Key::Merge, _)) => {
+                    unreachable!("merge keys are only returned when resolving them")
+                }
+                Err(e) => return Err(events.fail(e)),
+            };
+
+            match events.skip_duplicate(&seen, &key, marker) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => return Err(events.fail(e)),
+            }
+            let track = events.track_duplicates();
+            events.push_path(|| key.clone());
+            drop(events);
+
+            entry(&key)?;
+            self.events.borrow_mut().pop_path();
+            if track {
+                seen.insert(key);
+            }
+        }
+    }
+
+    /// `serialize_merged_map()` serializes a mapping, merging in the mappings referred to by any
+    /// `<<` keys. The mapping's own keys take precedence over merged keys, and earlier merged
+    /// mappings take precedence over later ones. Merged keys are output in place of the `<<` key.
+    ///
+    /// Whether a key has been overridden is only known at the end of the mapping, so the mapping
+    /// is held in memory as a whole.
+    fn serialize_merged_map<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entries = Vec::new();
+        let mut seen = HashSet::new();
+
+        loop {
+            let mut events = self.events.borrow_mut();
+            match events.peek() {
+                Ok(Event::MappingEnd) => {
+                    events.peeked = None;
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => return Err(events.fail(e)),
+            }
+            drop(events);
+
+            let key = self.key();
+            let mut events = self.events.borrow_mut();
+            let (key, marker) = match key {
+                Ok(key) => key,
+                Err(e) => return Err(events.fail(e)),
+            };
+
+            if let Key::Name(key) = &key {
+                match events.skip_duplicate(&seen, key, marker) {
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(e) => return Err(events.fail(e)),
+                }
+                if events.track_duplicates() {
+                    seen.insert(key.clone());
+                }
+            }
+            match &key {
+                Key::Name(key) => events.push_path(|| key.clone()),
+                Key::Merge => events.expanding += 1,
+            }
+            drop(events);
+
+            let value = serde_json::to_value(self).map_err(S::Error::custom);
+            let mut events = self.events.borrow_mut();
+            match &key {
+                Key::Name(_) => events.pop_path(),
+                Key::Merge => events.expanding -= 1,
+            }
+            drop(events);
+
+            let value = value?;
+            entries.push(match key {
+                Key::Name(key) => Entry::Value(key, value),
+                Key::Merge => {
+                    match merged_mappings(value) {
+                        Some(mappings) => Entry::Merge(mappings),
+                        None => return Err(self
+                            .events
+                            .borrow_mut()
+                            .fail(Yaml2JsonError::UnsupportedError(
+                            "merge keys (`<<`) must refer to a mapping or a sequence of mappings"
+                                .to_string(),
+                        ))),
+                    }
+                }
+            });
+        }
+
+        let own_keys: HashSet<String> = entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Value(key, _) => Some(key.clone()),
+                Entry::Merge(_) => None,
+            })
+            .collect();
+        let mut map = Map::new();
+
+        for entry in entries {
+            match entry {
+                Entry::Value(key, value) => {
+                    map.insert(key, value);
+                }
+                Entry::Merge(mappings) => {
+                    for (key, value) in mappings.into_iter().flatten() {
+                        if !own_keys.contains(&key) {
+                            map.entry(key).or_insert(value);
+                        }
+                    }
+                }
+            }
+        }
+
+        map.serialize(serializer)
+    }
+}
+
+/// `merged_mappings()` returns the mappings referred to by a `<<` key, which may be a single
+/// mapping or a sequence of them.
+fn merged_mappings(value: Value) -> Option<Vec<Map<String, Value>>> {
+    match value {
+        Value::Object(map) => Some(vec![map]),
+        Value::Array(values) => values
+            .into_iter()
+            .map(|value| match value {
+                Value::Object(map) => Some(map),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+impl<T: Iterator<Item = char>> Serialize for Node<'_, '_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut events = self.events.borrow_mut();
+        let (event, marker) = match events.next() {
+            Ok(next) => next,
+            Err(e) => return Err(events.fail(e)),
+        };
+        events.output_anchor(&event);
+
+        match event {
+            Event::Scalar(value, style, _, tag) => {
+                if let Err(e) = events.output(value.len() + 1) {
+                    return Err(events.fail(e));
+                }
+
+                match resolve(value, style, tag.as_ref(), marker, events.y2j) {
+                    Ok(value) => {
+                        drop(events);
+                        value.serialize(serializer)
+                    }
+                    Err(e) => Err(events.fail(e)),
+                }
+            }
+            Event::SequenceStart(_, ref tag) | Event::MappingStart(_, ref tag) => {
+                if let Err(e) = events.enter() {
+                    return Err(events.fail(e));
+                }
+                let seq = matches!(event, Event::SequenceStart(..));
+                let handler = tag
+                    .as_ref()
+                    .and_then(|tag| events.y2j.options.tag_handlers.get(tag));
+
+                if handler.is_none() {
+                    let core = if seq { "seq" } else { "map" };
+                    if let Err(e) = scalar::check_tag(tag.as_ref(), &[core], events.y2j) {
+                        let e = locate(e, marker, events.y2j);
+                        return Err(events.fail(e));
+                    }
+                }
+                drop(events);
+
+                let res = match handler {
+                    Some((name, handler)) => self
+                        .serialize_collection(seq, serde_json::value::Serializer)
+                        .map_err(S::Error::custom)
+                        .and_then(|value| handler(&name, value).serialize(serializer)),
+                    None => self.serialize_collection(seq, serializer),
+                };
+                self.events.borrow_mut().leave();
+
+                res
+            }
+            Event::Alias(anchor) => {
+                // `next()` only returns aliases to be output as references.
+                let key = events.y2j.options.alias_refs.clone().unwrap_or_default();
+                let reference = format!("#{}", fragment(&events.anchor_paths[&anchor]));
+
+                if let Err(e) = events.output(key.len() + reference.len() + 6) {
+                    return Err(events.fail(e));
+                }
+                drop(events);
+
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&key, &reference)?;
+                map.end()
+            }
+            event => Err(events.fail(Yaml2JsonError::UnsupportedError(format!(
+                "unexpected YAML event {:?}",
+                event
+            )))),
+        }
+    }
+}
+
+/// `fragment()` percent-encodes a JSON Pointer for use as a URI fragment (RFC 6901, section 6).
+fn fragment(pointer: &str) -> String {
+    let mut out = String::with_capacity(pointer.len());
+
+    for b in pointer.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+
+    out
+}
+
+/// `normalize()` gives core schema tags the `tag:yaml.org,2002:` handle however they were written,
+/// e.g. `!<tag:yaml.org,2002:str>`, so that they only need to be matched one way. The
+/// non-specific `!` tag becomes `!!str`, `!!seq` or `!!map`, as it means the node isn't resolved.
+fn normalize(event: Event) -> Event {
+    match event {
+        Event::Scalar(value, style, anchor, Some(tag)) => {
+            Event::Scalar(value, style, anchor, Some(normalize_tag(tag, "str")))
+        }
+        Event::SequenceStart(anchor, Some(tag)) => {
+            Event::SequenceStart(anchor, Some(normalize_tag(tag, "seq")))
+        }
+        Event::MappingStart(anchor, Some(tag)) => {
+            Event::MappingStart(anchor, Some(normalize_tag(tag, "map")))
+        }
+        event => event,
+    }
+}
+
+fn normalize_tag(tag: Tag, non_specific: &str) -> Tag {
+    let core = |suffix: &str| Tag {
+        handle: scalar::CORE_TAG_HANDLE.to_string(),
+        suffix: suffix.to_string(),
+    };
+
+    if tag.handle == scalar::CORE_TAG_HANDLE {
+        return tag;
+    }
+    if tag.handle.is_empty() && tag.suffix == "!" {
+        return core(non_specific);
+    }
+
+    let name = format!("{}{}", tag.handle, tag.suffix);
+    match name.strip_prefix(scalar::CORE_TAG_HANDLE) {
+        Some(suffix) => core(suffix),
+        None => tag,
+    }
+}
+
+/// `resolve()` converts a scalar at `marker`, after expanding any environment variables in it with
+/// `Yaml2JsonOptions::env_subst()`.
+fn resolve(
+    value: String,
+    style: TScalarStyle,
+    tag: Option<&Tag>,
+    marker: Marker,
+    y2j: &Yaml2Json,
+) -> Result<Value, Yaml2JsonError> {
+    let value = substitute(value, marker, y2j)?;
+
+    scalar::resolve(value, style, tag, y2j).map_err(|e| locate(e, marker, y2j))
+}
+
+/// `substitute()` expands the environment variables in a scalar, with
+/// `Yaml2JsonOptions::env_subst()`.
+fn substitute(value: String, marker: Marker, y2j: &Yaml2Json) -> Result<String, Yaml2JsonError> {
+    match &y2j.options.env_subst {
+        Some(env_subst) => env::substitute(value, env_subst).map_err(|name| {
+            Yaml2JsonError::UndefinedVariableError {
+                name,
+                line: marker.line(),
+                column: marker.col() + 1,
+            }
+        }),
+        None => Ok(value),
+    }
+}
+
+/// `locate()` adds the location of the node at `marker` to an error about its value, with
+/// `Yaml2JsonOptions::strict()`.
+fn locate(e: Yaml2JsonError, marker: Marker, y2j: &Yaml2Json) -> Yaml2JsonError {
+    match e {
+        Yaml2JsonError::UnsupportedError(message) if y2j.options.strict => {
+            Yaml2JsonError::LossyError {
+                message,
+                line: marker.line(),
+                column: marker.col() + 1,
+            }
+        }
+        e => e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{Events, Node};
+    use crate::{
+        DuplicateKeys, EnvSubst, KeyMode, Limit, Limits, MergeKeys, Style, UndefinedVars,
+        UnknownTags, Yaml2Json, Yaml2JsonError,
+    };
+    use serde_json::{json, Value};
+    use std::cell::RefCell;
+
+    fn try_convert(y2j: &Yaml2Json, input: &str) -> Result<String, Yaml2JsonError> {
+        let events = RefCell::new(Events::new(y2j, input));
+        assert!(events.borrow_mut().next_document().unwrap());
+
+        serde_json::to_string(&Node { events: &events })
+            .map_err(|e| events.borrow_mut().take_error(e))
+    }
+
+    fn convert(input: &str) -> String {
+        try_convert(&Yaml2Json::new(Style::COMPACT), input).unwrap()
+    }
+
+    fn convert_err(input: &str) -> bool {
+        try_convert(&Yaml2Json::new(Style::COMPACT), input).is_err()
+    }
+
+    fn limit_exceeded(limits: Limits, input: &str) -> Option<Limit> {
+        let y2j = Yaml2Json::new(Style::COMPACT).limits(limits);
+
+        match try_convert(&y2j, input) {
+            Err(Yaml2JsonError::LimitExceededError { limit, .. }) => Some(limit),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let input = r#"
+a:
+  - 1
+  - b: [c, "d"]
+e: {}
+"#;
+        assert_eq!(r#"{"a":[1,{"b":["c","d"]}],"e":{}}"#, convert(input));
+    }
+
+    #[test]
+    fn aliases() {
+        let input = r#"
+base: &base
+  name: &name x
+  items: [1, 2]
+copy: *base
+name: *name
+nested: &outer
+  inner: *base
+again: *outer
+"#;
+        let expected = r#"{"base":{"name":"x","items":[1,2]},"copy":{"name":"x","items":[1,2]},"name":"x","nested":{"inner":{"name":"x","items":[1,2]}},"again":{"inner":{"name":"x","items":[1,2]}}}"#;
+        assert_eq!(expected, convert(input));
+    }
+
+    #[test]
+    fn alias_refs() {
+        let input = r#"
+base: &base
+  name: &name x
+  items: [1, &one 1]
+copy: *base
+name: *name
+list: [*one, &self [*self]]
+"a/b c": &spaced 2
+spaced: *spaced
+*name : key
+merged: {<<: *base}
+"#;
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .alias_refs("$ref")
+            .merge_keys(MergeKeys::Resolve);
+        let expected = r##"{"base":{"name":"x","items":[1,1]},"copy":{"$ref":"#/base"},"name":{"$ref":"#/base/name"},"list":[{"$ref":"#/base/items/1"},[{"$ref":"#/list/1"}]],"a/b c":2,"spaced":{"$ref":"#/a~1b%20c"},"x":"key","merged":{"name":"x","items":[1,1]}}"##;
+        assert_eq!(expected, try_convert(&y2j, input).unwrap());
+
+        // Replacing a value which doesn't hold the anchor keeps referring to it.
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .alias_refs("@ref")
+            .duplicate_keys(DuplicateKeys::Last);
+        let input = "a: &a [1]\nb: *a\nb: {c: *a}";
+        let expected = r##"{"a":[1],"b":{"c":{"@ref":"#/a"}}}"##;
+        assert_eq!(expected, y2j.document_to_string(input).unwrap());
+
+        // Once the value holding an anchor is replaced, its aliases are expanded.
+        let input = "a: &x 1\na: 2\nb: *x\nc: {d: &y [3]}\nc: {}\ne: *y";
+        let expected = r##"{"a":2,"b":1,"c":{},"e":[3]}"##;
+        assert_eq!(expected, y2j.document_to_string(input).unwrap());
+
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .alias_refs("$ref")
+            .duplicate_keys(DuplicateKeys::First);
+        let input = "a: 1\na: &a [2]\nb: *a";
+        assert_eq!(r#"{"a":1,"b":[2]}"#, try_convert(&y2j, input).unwrap());
+
+        assert!(convert_err("a: &a [*a]"));
+    }
+
+    #[test]
+    fn env_subst() {
+        let env = EnvSubst {
+            vars: Some([("A".to_string(), "1".to_string())].into()),
+            undefined: UndefinedVars::Error,
+            ..EnvSubst::default()
+        };
+        let y2j = Yaml2Json::new(Style::COMPACT).env_subst(env);
+
+        assert_eq!(
+            r#"{"1":1,"b":"${A}","c":"1 # 1"}"#,
+            try_convert(&y2j, "${A}: ${A}\nb: $${A}\nc: '${A} # ${A}'").unwrap()
+        );
+        assert!(matches!(
+            try_convert(&y2j, "a: 1\nb: [x, \"${B}\"]"),
+            Err(Yaml2JsonError::UndefinedVariableError { name, line: 2, column: 8 }) if name == "B"
+        ));
+    }
+
+    #[test]
+    fn non_string_keys() {
+        let input = r#"
+1: a
+true: b
+~: c
+0x1f: d
+.inf: e
+?
+: f
+[e, {f: g}]: h
+"i": j
+"#;
+        let expected = r#"{"1":"a","true":"b","~":"c","0x1f":"d",".inf":"e","":"f","[\"e\",{\"f\":\"g\"}]":"h","i":"j"}"#;
+        assert_eq!(expected, convert(input));
+
+        let y2j = Yaml2Json::new(Style::COMPACT).key_mode(KeyMode::Error);
+        assert!(matches!(
+            try_convert(&y2j, input),
+            Err(Yaml2JsonError::NonStringKeyError { key, line: 2, column: 1 }) if key == "1"
+        ));
+        assert!(matches!(
+            try_convert(&y2j, "a: b\n? [c]\n: d"),
+            Err(Yaml2JsonError::NonStringKeyError { key, line: 2, column: 3 }) if key == r#"["c"]"#
+        ));
+        assert!(try_convert(&y2j, r#"{"1": a, yes: b}"#).is_ok());
+    }
+
+    #[test]
+    fn block_scalars() {
+        // The last line break is only kept if the input has one.
+        assert_eq!(r#"{"a":"text"}"#, convert("a: |\n  text"));
+        assert_eq!(r#"{"a":"text\n"}"#, convert("a: |\n  text\n"));
+        assert_eq!(r#"{"a":"text"}"#, convert("a: |+\n  text"));
+        assert_eq!(r#"{"a":"x y"}"#, convert("a: >\n  x\n  y"));
+        assert_eq!(r#"["a\n","b"]"#, convert("- |\n  a\n- |\n  b"));
+        assert_eq!(r#"{"a":"x\n","b":"x"}"#, convert("a: |\n  x\nb: |\n  x"));
+        assert_eq!(r#"{"a":"text\n"}"#, convert("a: |\n  text\n  "));
+        assert_eq!(r#"{"a":"text\n"}"#, convert("a: |\n    text\n  "));
+        assert_eq!(r#"{"a":"text\n"}"#, convert("a: |\n  text\n# comment"));
+        assert_eq!(r#"{"a":"x\n# y"}"#, convert("a: |\n  x\n  # y"));
+        assert_eq!(r#"{"a":"t\n","b":"t\n"}"#, convert("a: &t |\n  t\nb: *t"));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let input = "[".repeat(200) + &"]".repeat(200);
+        let res = limit_exceeded(Limits::default(), &input);
+        assert_eq!(Some(Limit::Depth), res);
+
+        let limits = Limits {
+            max_depth: 2,
+            ..Limits::default()
+        };
+        assert_eq!(None, limit_exceeded(limits, "[[1]]"));
+        assert_eq!(Some(Limit::Depth), limit_exceeded(limits, "[[[1]]]"));
+    }
+
+    #[test]
+    fn alias_expansion_limit() {
+        let input = r#"
+a: &a ["lol", "lol", "lol", "lol", "lol", "lol", "lol", "lol"]
+b: &b [*a, *a, *a, *a, *a, *a, *a, *a]
+c: &c [*b, *b, *b, *b, *b, *b, *b, *b]
+d: &d [*c, *c, *c, *c, *c, *c, *c, *c]
+e: &e [*d, *d, *d, *d, *d, *d, *d, *d]
+f: &f [*e, *e, *e, *e, *e, *e, *e, *e]
+"#;
+        let res = limit_exceeded(Limits::default(), input);
+        assert_eq!(Some(Limit::AliasExpansions), res);
+
+        let limits = Limits {
+            max_alias_expansions: Some(1),
+            ..Limits::default()
+        };
+        assert_eq!(None, limit_exceeded(limits, "a: &a 1\nb: *a"));
+        assert_eq!(
+            Some(Limit::AliasExpansions),
+            limit_exceeded(limits, "a: &a 1\nb: *a\nc: *a")
+        );
+    }
+
+    #[test]
+    fn output_size_limit() {
+        let limits = Limits {
+            max_output_size: Some(64),
+            ..Limits::default()
+        };
+        assert_eq!(None, limit_exceeded(limits, "abc: def"));
+
+        let input = format!("abc: {}", "d".repeat(64));
+        assert_eq!(Some(Limit::OutputSize), limit_exceeded(limits, &input));
+    }
+
+    #[test]
+    fn merge_keys() {
+        let input = r#"
+base: &base {a: 1, b: 2}
+other: &other {b: 3, c: 4}
+single: {<<: *base, x: 0}
+multiple: {c: 5, <<: [*base, *other]}
+override: {b: 0, <<: *base}
+quoted: {"<<": *base}
+"#;
+        let y2j = Yaml2Json::new(Style::COMPACT).merge_keys(MergeKeys::Resolve);
+        let expected = r#"{"base":{"a":1,"b":2},"other":{"b":3,"c":4},"single":{"a":1,"b":2,"x":0},"multiple":{"c":5,"a":1,"b":2},"override":{"b":0,"a":1},"quoted":{"<<":{"a":1,"b":2}}}"#;
+        assert_eq!(expected, try_convert(&y2j, input).unwrap());
+
+        assert!(try_convert(&y2j, "a: {<<: 1}").is_err());
+
+        let expected = r#"{"a":{"<<":{"b":1}}}"#;
+        assert_eq!(expected, convert("a: {<<: {b: 1}}"));
+
+        let y2j = Yaml2Json::new(Style::COMPACT).merge_keys(MergeKeys::Error);
+        assert!(matches!(
+            try_convert(&y2j, "a: {<<: {b: 1}}"),
+            Err(Yaml2JsonError::MergeKeyError { line: 1, column: 5 })
+        ));
+        assert!(matches!(
+            try_convert(&y2j, "a:\n  b: 1\n  !!merge x: {c: 2}"),
+            Err(Yaml2JsonError::MergeKeyError {
+                line: 3,
+                column: 11
+            })
+        ));
+        assert!(try_convert(&y2j, r#"a: {"<<": {b: 1}}"#).is_ok());
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let input = r#"
+a: 1
+b: {c: [2], d: 3}
+a: {e: 4}
+b: 5
+"#;
+        let y2j = Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Error);
+        let res = try_convert(&y2j, input);
+        assert!(matches!(
+            res,
+            Err(Yaml2JsonError::DuplicateKeyError { key, line: 4, column: 1 }) if key == "a"
+        ));
+
+        let y2j = Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::First);
+        let expected = r#"{"a":1,"b":{"c":[2],"d":3}}"#;
+        assert_eq!(expected, try_convert(&y2j, input).unwrap());
+
+        let y2j = y2j.merge_keys(MergeKeys::Resolve);
+        assert_eq!(expected, try_convert(&y2j, input).unwrap());
+    }
+
+    #[test]
+    fn tag_handlers() {
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .tag_handler("!vault", |tag, value| json!({ tag: value }))
+            .tag_handler("!join", |_, value| match value {
+                Value::Array(seq) => {
+                    let parts: Vec<_> = seq.iter().filter_map(Value::as_str).collect();
+                    Value::String(parts.join("-"))
+                }
+                value => value,
+            })
+            .tag_handler("tag:yaml.org,2002:str", |_, value| json!([value]));
+
+        let input = r#"
+secret: !vault s3cr3t
+joined: !join [a, !vault b, c]
+map: !vault {a: 1}
+!join [x, y]: &anchor !vault z
+alias: *anchor
+core: !!str 2
+verbatim: !<tag:yaml.org,2002:str> 3
+"#;
+        let expected = r#"{"secret":{"!vault":"s3cr3t"},"joined":"a-c","map":{"!vault":{"a":1}},"x-y":{"!vault":"z"},"alias":{"!vault":"z"},"core":["2"],"verbatim":["3"]}"#;
+        assert_eq!(expected, try_convert(&y2j, input).unwrap());
+        assert!(try_convert(&y2j, "other: !other 1").is_err());
+    }
+
+    #[test]
+    fn core_tags() {
+        let input = r#"
+a: !<tag:yaml.org,2002:str> 1
+b: !<tag:yaml.org,2002:int> "2"
+c: ! 3
+d: ! [4]
+e: !<tag:yaml.org,2002:seq> [!<tag:yaml.org,2002:null> ""]
+"#;
+        let expected = r#"{"a":"1","b":2,"c":"3","d":[4],"e":[null]}"#;
+        assert_eq!(expected, convert(input));
+
+        let y2j = Yaml2Json::new(Style::COMPACT).strict(true);
+        assert_eq!(expected, try_convert(&y2j, input).unwrap());
+
+        let input = "%TAG !y! tag:yaml.org,2002:\n---\na: !y!str 1";
+        assert_eq!(r#"{"a":"1"}"#, try_convert(&y2j, input).unwrap());
+        assert!(try_convert(&y2j, "a: !<tag:yaml.org,2002:set> {b}").is_err());
+    }
+
+    #[test]
+    fn tab_indentation() {
+        for input in ["\t- a", "\ta: 1", " \ta: 1", "\t'a'", "---\n\tx"] {
+            assert!(
+                matches!(
+                    try_convert(&Yaml2Json::new(Style::COMPACT), input),
+                    Err(Yaml2JsonError::ParseError { .. })
+                ),
+                "{:?}",
+                input
+            );
+        }
+
+        assert_eq!(r#"{"a":[1,2]}"#, convert("a: [1,\n\t2]"));
+        assert_eq!(r#"["a","b"]"#, convert("\t# a\n- a\n-\tb"));
+    }
+
+    #[test]
+    fn strict() {
+        let y2j = Yaml2Json::new(Style::COMPACT).strict(true);
+        let lossy = |input: &str| match try_convert(&y2j, input) {
+            Err(Yaml2JsonError::LossyError {
+                message,
+                line,
+                column,
+            }) => Some((message, line, column)),
+            _ => None,
+        };
+
+        assert_eq!(
+            Some(("unknown tag `!vault`".to_string(), 2, 11)),
+            lossy("a: 1\nb: !vault c")
+        );
+        assert_eq!(
+            Some(("unsupported tag `!!set`".to_string(), 1, 10)),
+            lossy("a: !!set {b, c}")
+        );
+        assert_eq!(
+            Some(("unknown tag `!k`".to_string(), 1, 5)),
+            lossy("{!k a: 1}")
+        );
+        assert_eq!(
+            Some((
+                "integer `123456789012345678901234567890` is outside the 64 bit range".to_string(),
+                1,
+                4
+            )),
+            lossy("a: 123456789012345678901234567890")
+        );
+        assert_eq!(
+            Some((
+                "`0.1000000000000000055511151231257827` can't be represented exactly as a float"
+                    .to_string(),
+                2,
+                3
+            )),
+            lossy("- 0.5\n- 0.1000000000000000055511151231257827")
+        );
+        assert!(lossy("a: -0x10000000000000000").is_some());
+        assert!(lossy("a: !!float 9007199254740993").is_some());
+        assert!(lossy("a: !!float .inf").is_some());
+        assert!(lossy("a: !!binary aGk=").is_some());
+        assert!(matches!(
+            try_convert(&y2j, "a: 1\na: 2"),
+            Err(Yaml2JsonError::DuplicateKeyError { .. })
+        ));
+        assert!(matches!(
+            try_convert(&y2j, "true: 1"),
+            Err(Yaml2JsonError::NonStringKeyError { .. })
+        ));
+
+        let input = "a: [0.1, 1.5e3, -9223372036854775808, 18446744073709551615]";
+        assert_eq!(
+            r#"{"a":[0.1,1500.0,-9223372036854775808,18446744073709551615]}"#,
+            try_convert(&y2j, input).unwrap()
+        );
+
+        let input = "a: !!str 1\nb: !!seq [2]\nc: !!map {d: !!null ''}";
+        assert_eq!(
+            r#"{"a":"1","b":[2],"c":{"d":null}}"#,
+            try_convert(&y2j, input).unwrap()
+        );
+
+        let y2j = y2j.tag_handler("!vault", |_, _| Value::Null);
+        assert_eq!(r#"{"b":null}"#, try_convert(&y2j, "b: !vault c").unwrap());
+        let y2j = y2j.unknown_tags(UnknownTags::Ignore);
+        assert_eq!(r#"{"b":"c"}"#, try_convert(&y2j, "b: !other c").unwrap());
+        assert!(try_convert(&y2j, "b: !!set {c}").is_err());
+
+        // Outside of strict mode, only the tags of the YAML schemas are ignored.
+        assert_eq!(r#"{"b":{"c":null}}"#, convert("b: !!set {c}"));
+        assert!(matches!(
+            try_convert(&Yaml2Json::new(Style::COMPACT), "b: !vault c"),
+            Err(Yaml2JsonError::UnsupportedError(message)) if message == "unknown tag `!vault`"
+        ));
+        assert!(convert_err("b: !vault [c]"));
+    }
+}
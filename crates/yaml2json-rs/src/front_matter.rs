@@ -0,0 +1,54 @@
+/// `split()` splits `input` into its YAML front matter and the body after it, or returns `None`
+/// if it doesn't start with front matter. The front matter is everything between a `---` line at
+/// the very start of the input, after any byte order mark, and the next `---` or `...` line. It
+/// always starts on the second line of the input.
+pub(crate) fn split(input: &str) -> Option<(&str, &str)> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut lines = input.split_inclusive('\n');
+
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+    let start = input.find('\n')? + 1;
+    let mut end = start;
+
+    for line in lines {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some((&input[start..end], &input[end + line.len()..]));
+        }
+        end += line.len();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::front_matter::split;
+
+    #[test]
+    fn front_matter() {
+        assert_eq!(
+            Some(("title: Hello\ntags: [a]\n", "# Hello\n\nText.\n")),
+            split("---\ntitle: Hello\ntags: [a]\n---\n# Hello\n\nText.\n")
+        );
+        assert_eq!(
+            Some(("a: 1\r\n", "b")),
+            split("\u{feff}---\r\na: 1\r\n...\r\nb")
+        );
+        assert_eq!(Some(("", "")), split("---\n---"));
+        assert_eq!(
+            Some(("a: |\n  ---x\n", "")),
+            split("---\na: |\n  ---x\n---\n")
+        );
+    }
+
+    #[test]
+    fn no_front_matter() {
+        assert_eq!(None, split("# Hello\n---\na: 1\n---\n"));
+        assert_eq!(None, split("\n---\na: 1\n---\n"));
+        assert_eq!(None, split("---\na: 1\n"));
+        assert_eq!(None, split("--- a\nb\n---\n"));
+        assert_eq!(None, split(""));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::{Limit, Yaml2JsonError};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io;
+use std::mem;
+
+/// `looks_like_json()` returns whether `input` starts with a JSON object or array, ignoring any
+/// leading whitespace. YAML flow collections start the same way, so this is only a hint.
+pub(crate) fn looks_like_json(input: &[u8]) -> bool {
+    matches!(
+        input.iter().find(|b| !b.is_ascii_whitespace()),
+        Some(b'{' | b'[')
+    )
+}
+
+/// `values()` parses a stream of JSON values, e.g. newline delimited JSON, stopping after the first
+/// error.
+pub(crate) fn values<'a, R: io::Read + 'a>(
+    reader: R,
+) -> impl Iterator<Item = Result<Value, Yaml2JsonError>> + 'a {
+    let mut failed = false;
+
+    serde_json::Deserializer::from_reader(reader)
+        .into_iter()
+        .map_while(move |res| {
+            if failed {
+                return None;
+            }
+
+            failed = res.is_err();
+            Some(res.map_err(parse_error))
+        })
+}
+
+/// `Prefix` parses the JSON values at the start of input which may turn out to be YAML instead,
+/// for `Input::Auto`. The input is pushed to it as it is read, so each value is returned once it
+/// is complete, without reading the rest of the input first.
+///
+/// A JSON value can still start some YAML on the same line, e.g. `[a, b]: c`, so values are only
+/// returned at the end of their line. Once the input turns out not to be JSON, `rest()` is the
+/// input from the start of that line, to be parsed as YAML.
+#[derive(Default)]
+pub(crate) struct Prefix {
+    buf: Vec<u8>,
+    /// The start of the current line in `buf`.
+    start: usize,
+    /// How much of `buf` has been scanned.
+    scanned: usize,
+    /// The start of the value being scanned, and how far through it the scan is.
+    value: Option<(usize, Scan)>,
+    /// The values on the current line, which are returned at its end.
+    line: Vec<Value>,
+    ready: VecDeque<Value>,
+    /// The number of lines before the current line.
+    lines: usize,
+    invalid: bool,
+}
+
+/// How far `Prefix` is through a JSON value.
+#[derive(Clone, Copy)]
+enum Scan {
+    /// `depth` arrays and objects deep, possibly within a string.
+    Nested {
+        depth: usize,
+        string: bool,
+        escaped: bool,
+    },
+    String {
+        escaped: bool,
+    },
+    /// A number, `true`, `false` or `null`, which ends before the next byte that can't be part of
+    /// it.
+    Bare,
+}
+
+impl Prefix {
+    /// `push()` adds the next part of the input, returning false once the input isn't JSON.
+    pub(crate) fn push(&mut self, input: &[u8]) -> bool {
+        // Only the current line is kept, in case it has to be parsed as YAML.
+        self.buf.drain(..self.start);
+        self.scanned -= self.start;
+        if let Some((start, _)) = self.value.as_mut() {
+            *start -= self.start;
+        }
+        self.start = 0;
+
+        self.buf.extend_from_slice(input);
+        self.scan();
+        !self.invalid
+    }
+
+    /// `finish()` ends the input, returning false if it isn't JSON.
+    pub(crate) fn finish(&mut self) -> bool {
+        match self.value.take() {
+            Some((start, Scan::Bare)) if !self.invalid => self.parse(start, self.buf.len()),
+            Some(_) => self.invalid = true,
+            None => {}
+        }
+
+        if !self.invalid {
+            self.ready.extend(self.line.drain(..));
+        }
+        !self.invalid
+    }
+
+    /// `next()` returns the next complete value.
+    pub(crate) fn next(&mut self) -> Option<Value> {
+        self.ready.pop_front()
+    }
+
+    /// `rest()` takes the input from the start of the line which isn't JSON, along with the number
+    /// of lines before it. The values of earlier lines are still returned by `next()`.
+    pub(crate) fn rest(&mut self) -> (Vec<u8>, usize) {
+        let mut rest = mem::take(&mut self.buf);
+        rest.drain(..self.start);
+
+        (rest, self.lines)
+    }
+
+    fn scan(&mut self) {
+        while !self.invalid && self.scanned < self.buf.len() {
+            let i = self.scanned;
+            let b = self.buf[i];
+
+            match self.value {
+                None => match b {
+                    b'\n' => {
+                        self.ready.extend(self.line.drain(..));
+                        // The line may have been continued by the values on it.
+                        let line = &self.buf[self.start..=i];
+                        self.lines += line.iter().filter(|&&b| b == b'\n').count();
+                        self.start = i + 1;
+                    }
+                    b' ' | b'\t' | b'\r' => {}
+                    b'{' | b'[' => {
+                        let scan = Scan::Nested {
+                            depth: 1,
+                            string: false,
+                            escaped: false,
+                        };
+                        self.value = Some((i, scan));
+                    }
+                    b'"' => self.value = Some((i, Scan::String { escaped: false })),
+                    _ => self.value = Some((i, Scan::Bare)),
+                },
+                Some((start, Scan::Bare)) => {
+                    if b.is_ascii_whitespace() || b"{}[],:\"".contains(&b) {
+                        self.parse(start, i);
+                        // The byte after the value is scanned again on its own.
+                        continue;
+                    }
+                }
+                Some((start, Scan::String { escaped })) => match b {
+                    _ if escaped => self.value = Some((start, Scan::String { escaped: false })),
+                    b'\\' => self.value = Some((start, Scan::String { escaped: true })),
+                    b'"' => self.parse(start, i + 1),
+                    _ => {}
+                },
+                Some((
+                    start,
+                    Scan::Nested {
+                        depth,
+                        string,
+                        escaped,
+                    },
+                )) => {
+                    let (depth, string, escaped) = match b {
+                        _ if escaped => (depth, string, false),
+                        b'\\' if string => (depth, string, true),
+                        b'"' => (depth, !string, false),
+                        _ if string => (depth, string, false),
+                        b'{' | b'[' => (depth + 1, string, false),
+                        b'}' | b']' => (depth - 1, string, false),
+                        _ => (depth, string, false),
+                    };
+
+                    if depth == 0 {
+                        self.parse(start, i + 1);
+                    } else {
+                        let scan = Scan::Nested {
+                            depth,
+                            string,
+                            escaped,
+                        };
+                        self.value = Some((start, scan));
+                    }
+                }
+            }
+
+            self.scanned += 1;
+        }
+    }
+
+    /// `parse()` parses the value scanned from `start` to `end`.
+    fn parse(&mut self, start: usize, end: usize) {
+        self.value = None;
+        match serde_json::from_slice(&self.buf[start..end]) {
+            Ok(value) => self.line.push(value),
+            Err(_) => self.invalid = true,
+        }
+    }
+}
+
+/// `check_depth()` applies `Limits::max_depth` to a parsed JSON value, counting arrays and
+/// objects in the same way as YAML sequences and mappings. `serde_json` refuses to parse more than
+/// 128 levels itself, so this only matters for a lower limit.
+pub(crate) fn check_depth(value: &Value, max_depth: usize) -> Result<(), Yaml2JsonError> {
+    let mut stack = vec![(value, 0)];
+
+    while let Some((value, depth)) = stack.pop() {
+        let children: Box<dyn Iterator<Item = &Value>> = match value {
+            Value::Array(seq) => Box::new(seq.iter()),
+            Value::Object(map) => Box::new(map.values()),
+            _ => continue,
+        };
+
+        if depth + 1 > max_depth {
+            return Err(Yaml2JsonError::LimitExceededError {
+                limit: Limit::Depth,
+                max: max_depth,
+            });
+        }
+        stack.extend(children.map(|child| (child, depth + 1)));
+    }
+
+    Ok(())
+}
+
+/// `parse_error()` converts a `serde_json` parsing error into the same form as YAML parsing errors.
+pub(crate) fn parse_error(e: serde_json::Error) -> Yaml2JsonError {
+    if e.is_io() {
+        return Yaml2JsonError::IOError(e.into());
+    }
+
+    let mut message = e.to_string();
+    // The location is kept separately.
+    if let Some(i) = message.rfind(" at line ") {
+        message.truncate(i);
+    }
+
+    Yaml2JsonError::ParseError {
+        message,
+        line: e.line(),
+        column: e.column(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::{check_depth, looks_like_json, values, Prefix};
+    use crate::{Limit, Yaml2JsonError};
+    use serde_json::json;
+
+    #[test]
+    fn sniffing() {
+        assert!(looks_like_json(b"{\"a\": 1}"));
+        assert!(looks_like_json(b"\n  [1, 2]"));
+        assert!(!looks_like_json(b"a: 1"));
+        assert!(!looks_like_json(b"---\n{}"));
+        assert!(!looks_like_json(b"  "));
+    }
+
+    #[test]
+    fn stream() {
+        let input = "{\"a\": 1}\n[true]\n\"b\" {\"c\": }\n{}";
+        let mut values = values(input.as_bytes());
+
+        assert_eq!(json!({"a": 1}), values.next().unwrap().unwrap());
+        assert_eq!(json!([true]), values.next().unwrap().unwrap());
+        assert_eq!(json!("b"), values.next().unwrap().unwrap());
+        assert!(matches!(
+            values.next().unwrap(),
+            Err(Yaml2JsonError::ParseError { message, line: 3, column: 11 })
+                if message == "expected value"
+        ));
+        assert!(values.next().is_none());
+    }
+
+    #[test]
+    fn prefix() {
+        let mut prefix = Prefix::default();
+        assert!(prefix.push(b"{\"a\": \"}\\\"\"}\n[1, [2]] 3"));
+        assert_eq!(Some(json!({"a": "}\""})), prefix.next());
+        assert_eq!(None, prefix.next());
+
+        // Values on the same line are only returned once it ends.
+        assert!(prefix.push(b"4 \"b\" null\r\n{"));
+        for value in [json!([1, [2]]), json!(34), json!("b"), json!(null)] {
+            assert_eq!(Some(value), prefix.next());
+        }
+        assert!(prefix.push(b"}"));
+        assert!(prefix.finish());
+        assert_eq!(Some(json!({})), prefix.next());
+        assert_eq!(None, prefix.next());
+
+        // The line which isn't JSON is left to be parsed as YAML, along with the rest.
+        let mut prefix = Prefix::default();
+        assert!(!prefix.push(b"[1,\n1]\n\n[a, b]: {c: 1}\n[2]\n"));
+        assert_eq!((b"[a, b]: {c: 1}\n[2]\n".to_vec(), 3), prefix.rest());
+        assert_eq!(Some(json!([1, 1])), prefix.next());
+        assert_eq!(None, prefix.next());
+
+        let mut prefix = Prefix::default();
+        assert!(prefix.push(b"[1,\n2"));
+        assert!(!prefix.finish());
+        assert_eq!((b"[1,\n2".to_vec(), 0), prefix.rest());
+    }
+
+    #[test]
+    fn depth() {
+        let value = json!({"a": [1, {"b": []}], "c": {}});
+        assert!(check_depth(&value, 4).is_ok());
+        assert!(matches!(
+            check_depth(&value, 3),
+            Err(Yaml2JsonError::LimitExceededError {
+                limit: Limit::Depth,
+                max: 3
+            })
+        ));
+        assert!(check_depth(&json!(1), 0).is_ok());
+        assert!(check_depth(&json!([]), 0).is_err());
+    }
+}
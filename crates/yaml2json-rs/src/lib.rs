@@ -1,10 +1,69 @@
+use crate::ascii::AsciiFormatter;
+use crate::canonical::CanonicalFormatter;
+use crate::color::ColorFormatter;
+use crate::events::{Events, Node};
 use crate::Style::{COMPACT, PRETTY};
-use core::fmt::Debug;
-use std::io;
+use core::fmt::{self, Debug, Display, Formatter};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, Formatter as JsonFormatter, PrettyFormatter};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::io::{self, BufRead, Read};
+use std::sync::{Arc, OnceLock};
 use thiserror::Error;
+use yaml_rust2::ScanError;
+use yaml_split::{DocumentIterator, Span, SpannedDocumentIterator, YamlSplitError};
+
+mod ascii;
+mod canonical;
+mod chars;
+mod color;
+mod comments;
+mod env;
+mod events;
+mod front_matter;
+mod json;
+mod merge;
+#[cfg(feature = "arbitrary-precision")]
+mod numbers;
+mod options;
+mod parallel;
+mod query;
+mod scalar;
+#[cfg(feature = "async")]
+mod stream;
+mod timestamp;
+mod transform;
+
+pub use crate::comments::Comment;
+pub use crate::env::{EnvSubst, UndefinedVars};
+pub use crate::merge::merge;
+pub use crate::options::Yaml2JsonOptions;
+pub use crate::query::Query;
+pub use crate::transform::PathPattern;
 
 #[derive(Error, Debug)]
 pub enum Yaml2JsonError {
+    /// The YAML could not be parsed. `line` and `column` start from 1.
+    #[error("{message} at line {line} column {column}")]
+    ParseError {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+
+    /// A mapping key is not a string, with `KeyMode::Error`. `line` and `column` start from 1.
+    #[error("non-string mapping key `{key}` at line {line} column {column}")]
+    NonStringKeyError {
+        key: String,
+        line: usize,
+        column: usize,
+    },
+
+    /// serde_yaml is no longer used to parse YAML, so this is never returned. Parse errors are
+    /// `Yaml2JsonError::ParseError` instead.
+    #[deprecated(note = "YAML is parsed with yaml-rust2, parse errors are `ParseError`")]
     #[error(transparent)]
     SerdeYamlError(#[from] serde_yaml::Error),
 
@@ -13,6 +72,120 @@ pub enum Yaml2JsonError {
 
     #[error(transparent)]
     IOError(#[from] io::Error),
+
+    #[error("{0}")]
+    UnsupportedError(String),
+
+    /// A mapping contains the same key twice, with `DuplicateKeys::Error`. `line` and `column`
+    /// start from 1, and point at the second occurrence of `key`.
+    #[error("duplicate mapping key `{key}` at line {line} column {column}")]
+    DuplicateKeyError {
+        key: String,
+        line: usize,
+        column: usize,
+    },
+
+    /// A mapping contains a merge key, with `MergeKeys::Error`. `line` and `column` start from 1,
+    /// and point at the key, e.g. `<<`.
+    #[error("merge keys (`<<`) are not allowed at line {line} column {column}")]
+    MergeKeyError { line: usize, column: usize },
+
+    /// One of the `Limits` was exceeded, the document is not converted any further.
+    #[error("{limit} limit of {max} exceeded")]
+    LimitExceededError { limit: Limit, max: usize },
+
+    /// A `Query` could not be parsed.
+    #[error("invalid query `{0}`")]
+    InvalidQueryError(String),
+
+    /// A `PathPattern` could not be parsed.
+    #[error("invalid path pattern `{0}`")]
+    InvalidPatternError(String),
+
+    /// A document could not be encoded in the output `Format`, e.g. because TOML has no null.
+    #[error("can't encode as {format}: {message}")]
+    EncodeError { format: Format, message: String },
+
+    /// A document doesn't contain a value at the `Query` given to `Yaml2Json::query()`.
+    #[error("no value found at `{query}`")]
+    QueryNotFoundError { query: String },
+
+    /// A node can't be converted without losing information, with `Yaml2JsonOptions::strict()`,
+    /// e.g. it has an unknown tag. `line` and `column` start from 1, and point at the node.
+    #[error("{message} at line {line} column {column}")]
+    LossyError {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+
+    /// A scalar refers to an environment variable which isn't set, with `UndefinedVars::Error`.
+    /// `line` and `column` start from 1, and point at the scalar.
+    #[error("undefined environment variable `{name}` at line {line} column {column}")]
+    UndefinedVariableError {
+        name: String,
+        line: usize,
+        column: usize,
+    },
+}
+
+impl Yaml2JsonError {
+    /// `code()` returns a short name for the kind of error, e.g. `parse` or `limit-exceeded`, for
+    /// reporting errors to other programs. Unlike the messages, these don't change between
+    /// releases.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let e = y2j.document_to_string("a: [b").unwrap_err();
+    ///
+    /// assert_eq!(e.code(), "parse");
+    /// assert_eq!(e.location(), Some((2, 1)));
+    /// ```
+    pub fn code(&self) -> &'static str {
+        #[allow(deprecated)]
+        match self {
+            Yaml2JsonError::ParseError { .. } => "parse",
+            Yaml2JsonError::SerdeYamlError(_) => "parse",
+            Yaml2JsonError::NonStringKeyError { .. } => "non-string-key",
+            Yaml2JsonError::SerdeJsonError(_) => "json",
+            Yaml2JsonError::IOError(_) => "io",
+            Yaml2JsonError::UnsupportedError(_) => "unsupported",
+            Yaml2JsonError::DuplicateKeyError { .. } => "duplicate-key",
+            Yaml2JsonError::MergeKeyError { .. } => "merge-key",
+            Yaml2JsonError::LimitExceededError { .. } => "limit-exceeded",
+            Yaml2JsonError::InvalidQueryError(_) => "invalid-query",
+            Yaml2JsonError::InvalidPatternError(_) => "invalid-pattern",
+            Yaml2JsonError::EncodeError { .. } => "encode",
+            Yaml2JsonError::QueryNotFoundError { .. } => "query-not-found",
+            Yaml2JsonError::LossyError { .. } => "lossy",
+            Yaml2JsonError::UndefinedVariableError { .. } => "undefined-variable",
+        }
+    }
+
+    /// `location()` returns the line and column the error was found at, starting from 1, for the
+    /// errors which have one.
+    pub fn location(&self) -> Option<(usize, usize)> {
+        match self {
+            Yaml2JsonError::ParseError { line, column, .. }
+            | Yaml2JsonError::DuplicateKeyError { line, column, .. }
+            | Yaml2JsonError::NonStringKeyError { line, column, .. }
+            | Yaml2JsonError::MergeKeyError { line, column }
+            | Yaml2JsonError::LossyError { line, column, .. }
+            | Yaml2JsonError::UndefinedVariableError { line, column, .. } => Some((*line, *column)),
+            _ => None,
+        }
+    }
+}
+
+impl From<ScanError> for Yaml2JsonError {
+    fn from(e: ScanError) -> Self {
+        Yaml2JsonError::ParseError {
+            message: e.info().to_string(),
+            line: e.marker().line(),
+            column: e.marker().col() + 1,
+        }
+    }
 }
 
 /// `Style` defines JSON output formats for `Yaml2Json`.
@@ -59,8 +232,387 @@ pub enum Style {
     PRETTY,
 }
 
+/// `Indent` defines the indentation of each level of `Style::PRETTY` output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    /// `Indent::Spaces(n)` indents each level by `n` spaces. This defaults to 2.
+    Spaces(usize),
+    /// `Indent::Tab` indents each level by a single tab.
+    Tab,
+}
+
+impl Indent {
+    fn to_bytes(self) -> Vec<u8> {
+        match self {
+            Indent::Spaces(n) => vec![b' '; n],
+            Indent::Tab => vec![b'\t'],
+        }
+    }
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+/// `MergeKeys` defines how `Yaml2Json` handles YAML merge keys, i.e. `<<: *anchor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MergeKeys {
+    /// `MergeKeys::Resolve` merges the keys of the mapping, or sequence of mappings, given as the
+    /// value of a `<<` key into the mapping containing it. Keys of the containing mapping take
+    /// precedence over merged keys, and earlier mappings in a sequence take precedence over later
+    /// ones.
+    /// ```
+    /// use yaml2json_rs::{MergeKeys, Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT).merge_keys(MergeKeys::Resolve);
+    /// let input = "base: &base {a: 1, b: 2}\nmerged: {<<: *base, b: 3}";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#"{"base":{"a":1,"b":2},"merged":{"a":1,"b":3}}"#);
+    /// ```
+    Resolve,
+    /// `MergeKeys::Keep` treats `<<` as a regular key. This is the default.
+    #[default]
+    Keep,
+    /// `MergeKeys::Error` fails the conversion of any document containing merge keys, with
+    /// `Yaml2JsonError::MergeKeyError`.
+    Error,
+}
+
+/// `MergeArrays` defines how `merge()` combines an array with the array overriding it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MergeArrays {
+    /// `MergeArrays::Replace` keeps only the overriding array. This is the default.
+    #[default]
+    Replace,
+    /// `MergeArrays::Append` adds the items of the overriding array after the items of the array
+    /// it overrides.
+    Append,
+}
+
+/// `DuplicateKeys` defines what `Yaml2Json` does when a mapping contains the same key twice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// `DuplicateKeys::Error` fails the conversion, reporting the key and its location.
+    Error,
+    /// `DuplicateKeys::First` keeps the value of the first occurrence of each key.
+    First,
+    /// `DuplicateKeys::Last` keeps the value of the last occurrence of each key, in the position
+    /// of the first occurrence. This is the default, as YAML parsers and JSON parsers commonly
+    /// do. `Yaml2Json::reader_to_writer()` holds each mapping in memory until its end to do this.
+    /// ```
+    /// use yaml2json_rs::{DuplicateKeys, Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Last);
+    /// let output = y2j.document_to_string("a: 1\nb: 2\na: 3").unwrap();
+    ///
+    /// assert_eq!(output, r#"{"a":3,"b":2}"#);
+    /// ```
+    #[default]
+    Last,
+}
+
+/// `KeyMode` defines how `Yaml2Json` converts mapping keys which aren't strings, as JSON object
+/// keys must be strings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum KeyMode {
+    /// `KeyMode::Stringify` keeps scalar keys as they are written, e.g. `1`, `0x10` or `~`, and
+    /// uses the JSON text of sequence and mapping keys, e.g. `["a"]`. This is the default.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let output = y2j.document_to_string("{1: a, 0x10: b, ~: c, [d]: e}").unwrap();
+    ///
+    /// assert_eq!(output, r#"{"1":"a","0x10":"b","~":"c","[\"d\"]":"e"}"#);
+    /// ```
+    #[default]
+    Stringify,
+    /// `KeyMode::Error` fails the conversion, reporting the key and its location.
+    Error,
+}
+
+/// `YamlVersion` defines which version of the YAML spec is used to resolve plain scalars, i.e.
+/// unquoted values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum YamlVersion {
+    /// `YamlVersion::V1_1` resolves `y`, `yes` and `on` as true, `n`, `no` and `off` as false,
+    /// `0755` as an octal integer and `1:30` as a sexagesimal (base 60) integer. Many tools,
+    /// including Kubernetes, still use these rules.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style, YamlVersion};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT).yaml_version(YamlVersion::V1_1);
+    /// let output = y2j.document_to_string("[yes, off, 0755, 1:30]").unwrap();
+    ///
+    /// assert_eq!(output, "[true,false,493,90]");
+    /// ```
+    V1_1,
+    /// `YamlVersion::V1_2` resolves plain scalars using the YAML 1.2 core schema, where only
+    /// `true` and `false` are booleans. This is the default.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let output = y2j.document_to_string("[yes, off, 0755, 1:30]").unwrap();
+    ///
+    /// assert_eq!(output, r#"["yes","off","0755","1:30"]"#);
+    /// ```
+    #[default]
+    V1_2,
+}
+
+/// `SpecialFloats` defines how `Yaml2Json` converts `.nan`, `.inf` and `-.inf`, which can't be
+/// represented in JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SpecialFloats {
+    /// `SpecialFloats::Null` outputs `null`. This is the default.
+    #[default]
+    Null,
+    /// `SpecialFloats::String` outputs the strings `"NaN"`, `"Infinity"` and `"-Infinity"`, as
+    /// understood by JavaScript's `Number()` and many other JSON parsers.
+    /// ```
+    /// use yaml2json_rs::{SpecialFloats, Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT).special_floats(SpecialFloats::String);
+    /// let output = y2j.document_to_string("[.nan, .inf, -.inf]").unwrap();
+    ///
+    /// assert_eq!(output, r#"["NaN","Infinity","-Infinity"]"#);
+    /// ```
+    String,
+    /// `SpecialFloats::Error` fails the conversion of any document containing these values.
+    Error,
+}
+
+/// `Binary` defines how `Yaml2Json` converts `!!binary` scalars, which hold base64 encoded data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Binary {
+    /// `Binary::Base64` outputs the base64 data as a string, without any line breaks. This is the
+    /// default.
+    #[default]
+    Base64,
+    /// `Binary::Bytes` outputs the decoded data as an array of byte values.
+    /// ```
+    /// use yaml2json_rs::{Binary, Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT).binary(Binary::Bytes);
+    /// let output = y2j.document_to_string("data: !!binary aGk=").unwrap();
+    ///
+    /// assert_eq!(output, r#"{"data":[104,105]}"#);
+    /// ```
+    Bytes,
+    /// `Binary::Error` fails the conversion of any document containing `!!binary` scalars.
+    Error,
+}
+
+/// `UnknownTags` defines how `Yaml2Json` converts nodes with an application-defined tag, e.g.
+/// `!vault`, which has no `Yaml2JsonOptions::tag_handler()`. Tags of the YAML schemas (`!!str`,
+/// `!!set` etc.) are never unknown, see `Yaml2JsonOptions::strict()` for those.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum UnknownTags {
+    /// `UnknownTags::Error` fails the conversion of any document containing these tags. This is
+    /// the default.
+    #[default]
+    Error,
+    /// `UnknownTags::Ignore` converts the nodes as if they had no tag.
+    /// ```
+    /// use yaml2json_rs::{UnknownTags, Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT).unknown_tags(UnknownTags::Ignore);
+    /// let output = y2j.document_to_string("password: !vault secret").unwrap();
+    ///
+    /// assert_eq!(output, r#"{"password":"secret"}"#);
+    /// ```
+    Ignore,
+}
+
+/// `Input` defines how `Yaml2Json` parses its input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Input {
+    /// `Input::Yaml` parses YAML, which includes most JSON. This is the default.
+    #[default]
+    Yaml,
+    /// `Input::Json` parses JSON. A stream may contain any number of JSON values, e.g. newline
+    /// delimited JSON, and each value is treated as a document.
+    /// ```
+    /// use yaml2json_rs::{Input, Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT).input(Input::Json);
+    /// let input = "{\"hello\": \"world\"}\n{\"hello\": \"rust\"}";
+    /// let output = y2j.reader_to_values(input.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(output.len(), 2);
+    /// ```
+    Json,
+    /// `Input::Auto` parses input starting with `{` or `[` as JSON, falling back to YAML if that
+    /// fails. Other input is parsed as YAML. JSON values are returned as they are read, once their
+    /// line has ended, as YAML can go on after them, e.g. `[a, b]: c`. From the first line which
+    /// isn't JSON, the rest of the input is parsed as YAML.
+    Auto,
+}
+
+/// `Format` defines the encoding of `Yaml2Json` output. Formats other than JSON each need a cargo
+/// feature of the same name: `msgpack`, `cbor` or `toml`.
+///
+/// Every document is converted to a `serde_json::Value` first, so the output is the same as
+/// encoding the JSON output. `Style` and the other output options only apply to JSON, apart from
+/// `Style::PRETTY` TOML, which puts arrays on multiple lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Format {
+    /// `Format::Json` outputs JSON text. This is the default.
+    #[default]
+    Json,
+    /// `Format::MessagePack` outputs binary MessagePack data. Documents are output one after
+    /// another, without separators.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    /// `Format::Cbor` outputs binary CBOR data. Documents are output one after another, without
+    /// separators.
+    #[cfg(feature = "cbor")]
+    Cbor,
+    /// `Format::Toml` outputs TOML text. Only documents which are mappings without any null values
+    /// can be encoded, others fail with `Yaml2JsonError::EncodeError`.
+    #[cfg(feature = "toml")]
+    Toml,
+}
+
+impl Format {
+    /// `is_binary()` returns whether the format can't be output as a String.
+    pub fn is_binary(self) -> bool {
+        match self {
+            Format::Json => false,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => true,
+            #[cfg(feature = "cbor")]
+            Format::Cbor => true,
+            #[cfg(feature = "toml")]
+            Format::Toml => false,
+        }
+    }
+
+    /// `extension()` returns the usual file extension for the format, including the `.`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => ".json",
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => ".msgpack",
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ".cbor",
+            #[cfg(feature = "toml")]
+            Format::Toml => ".toml",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json => "JSON",
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => "MessagePack",
+            #[cfg(feature = "cbor")]
+            Format::Cbor => "CBOR",
+            #[cfg(feature = "toml")]
+            Format::Toml => "TOML",
+        })
+    }
+}
+
+/// `Limit` identifies one of the `Limits`, for `Yaml2JsonError::LimitExceededError`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// See `Limits::max_depth`.
+    Depth,
+    /// See `Limits::max_alias_expansions`.
+    AliasExpansions,
+    /// See `Limits::max_output_size`.
+    OutputSize,
+    /// See `Limits::max_document_size`.
+    DocumentSize,
+}
+
+impl Limit {
+    /// `code()` returns a short name for the limit, as for `Yaml2JsonError::code()`, e.g.
+    /// `alias-expansions`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Limit::Depth => "depth",
+            Limit::AliasExpansions => "alias-expansions",
+            Limit::OutputSize => "output-size",
+            Limit::DocumentSize => "document-size",
+        }
+    }
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "nesting depth",
+            Limit::AliasExpansions => "alias expansion",
+            Limit::OutputSize => "output size",
+            Limit::DocumentSize => "document size",
+        })
+    }
+}
+
+/// `Limits` bound the resources used to convert each YAML document. Aliases make it possible for
+/// a small document to expand into a huge amount of JSON (the "billion laughs" attack), so these
+/// protect against untrusted input. A document which exceeds a limit fails with
+/// `Yaml2JsonError::LimitExceededError`.
+///
+/// ```
+/// use yaml2json_rs::{Limits, Yaml2Json, Style};
+///
+/// let limits = Limits {
+///     max_alias_expansions: Some(1),
+///     ..Limits::default()
+/// };
+/// let y2j = Yaml2Json::new(Style::COMPACT).limits(limits);
+///
+/// assert!(y2j.document_to_string("a: &a 1\nb: *a").is_ok());
+/// assert!(y2j.document_to_string("a: &a 1\nb: *a\nc: *a").is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The deepest nesting of sequences and mappings. Converting recurses once per level, so this
+    /// protects the stack. This defaults to 128, and applies to JSON input too, but can't be raised
+    /// past the parsers' own limits: 255 nested YAML flow collections (`[` or `{`), and 128 levels
+    /// of JSON, which fail with `Yaml2JsonError::ParseError` instead.
+    pub max_depth: usize,
+    /// The number of aliases expanded per document, including aliases within expanded aliases.
+    /// This defaults to 10,000.
+    pub max_alias_expansions: Option<usize>,
+    /// The approximate size of the JSON output per document in bytes, before any indentation.
+    /// This defaults to no limit.
+    pub max_output_size: Option<usize>,
+    /// The size of each YAML document in a stream in bytes, before it is parsed. Documents are
+    /// split from the stream first, so this bounds the memory used by a document which never
+    /// ends. A larger document is skipped without being held in full. This defaults to no limit.
+    pub max_document_size: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 128,
+            max_alias_expansions: Some(10_000),
+            max_output_size: None,
+            max_document_size: None,
+        }
+    }
+}
+
 /// Yaml2Json can convert individual YAML documents into JSON. Each instance can be configured to
-/// have different styles of output.
+/// have different styles of output, with `Yaml2Json::new()` or `Yaml2Json::with_options()`.
+///
+/// Mapping keys are always output in the same order as in the YAML document, unless
+/// `Yaml2Json::sort_keys()` is set. This holds whether a document is converted as it is parsed or
+/// held in memory first (e.g. for `Yaml2Json::query()`), and for every `Format`. When a key is
+/// repeated with `DuplicateKeys::Last`, it keeps the position of its first occurrence, and keys
+/// from merge keys are output in place of the `<<` key. Output is therefore deterministic, and the
+/// same input converts to the same bytes between releases with the same options.
 ///
 /// The JSON output can be returned as a string:
 /// ```
@@ -86,8 +638,9 @@ pub enum Style {
 ///
 /// // {"hello":"world"}
 /// ```
+#[derive(Clone)]
 pub struct Yaml2Json {
-    style: Style,
+    options: Yaml2JsonOptions,
 }
 
 impl Yaml2Json {
@@ -99,7 +652,184 @@ impl Yaml2Json {
     /// let y2j_compact = Yaml2Json::new(Style::COMPACT);
     /// ```
     pub fn new(style: Style) -> Self {
-        Self { style }
+        let pretty = match style {
+            PRETTY => true,
+            COMPACT => false,
+        };
+
+        Self::with_options(Yaml2JsonOptions::new().pretty(pretty))
+    }
+
+    /// `with_options()` creates a new `Yaml2Json` with the given options.
+    /// ```
+    /// use yaml2json_rs::{Indent, Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().indent(Indent::Tab).sort_keys(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let output = y2j.document_to_string("b: 1\na: 2").unwrap();
+    ///
+    /// assert_eq!(output, "{\n\t\"a\": 2,\n\t\"b\": 1\n}");
+    /// ```
+    pub fn with_options(options: Yaml2JsonOptions) -> Self {
+        Self { options }
+    }
+
+    /// `input()` is the same as `Yaml2JsonOptions::input()`.
+    pub fn input(mut self, input: Input) -> Self {
+        self.options = self.options.input(input);
+        self
+    }
+
+    /// `format()` is the same as `Yaml2JsonOptions::format()`.
+    pub fn format(mut self, format: Format) -> Self {
+        self.options = self.options.format(format);
+        self
+    }
+
+    /// `indent()` sets the indentation used for `Style::PRETTY` output, see
+    /// `Yaml2JsonOptions::indent()`. Unlike that, this doesn't change the `Style`.
+    pub fn indent(mut self, indent: Indent) -> Self {
+        self.options.indent = indent;
+        self
+    }
+
+    /// `sort_keys()` is the same as `Yaml2JsonOptions::sort_keys()`.
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.options = self.options.sort_keys(sort_keys);
+        self
+    }
+
+    /// `merge_keys()` is the same as `Yaml2JsonOptions::merge_keys()`.
+    pub fn merge_keys(mut self, merge_keys: MergeKeys) -> Self {
+        self.options = self.options.merge_keys(merge_keys);
+        self
+    }
+
+    /// `duplicate_keys()` is the same as `Yaml2JsonOptions::duplicate_keys()`.
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.options = self.options.duplicate_keys(duplicate_keys);
+        self
+    }
+
+    /// `key_mode()` is the same as `Yaml2JsonOptions::key_mode()`.
+    pub fn key_mode(mut self, key_mode: KeyMode) -> Self {
+        self.options = self.options.key_mode(key_mode);
+        self
+    }
+
+    /// `yaml_version()` is the same as `Yaml2JsonOptions::yaml_version()`.
+    pub fn yaml_version(mut self, yaml_version: YamlVersion) -> Self {
+        self.options = self.options.yaml_version(yaml_version);
+        self
+    }
+
+    /// `binary()` is the same as `Yaml2JsonOptions::binary()`.
+    pub fn binary(mut self, binary: Binary) -> Self {
+        self.options = self.options.binary(binary);
+        self
+    }
+
+    /// `special_floats()` is the same as `Yaml2JsonOptions::special_floats()`.
+    pub fn special_floats(mut self, special_floats: SpecialFloats) -> Self {
+        self.options = self.options.special_floats(special_floats);
+        self
+    }
+
+    /// `unknown_tags()` is the same as `Yaml2JsonOptions::unknown_tags()`.
+    pub fn unknown_tags(mut self, unknown_tags: UnknownTags) -> Self {
+        self.options = self.options.unknown_tags(unknown_tags);
+        self
+    }
+
+    /// `ascii()` is the same as `Yaml2JsonOptions::ascii()`.
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.options = self.options.ascii(ascii);
+        self
+    }
+
+    /// `canonical()` is the same as `Yaml2JsonOptions::canonical()`.
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.options = self.options.canonical(canonical);
+        self
+    }
+
+    /// `preserve_numbers()` is the same as `Yaml2JsonOptions::preserve_numbers()`.
+    #[cfg(feature = "arbitrary-precision")]
+    pub fn preserve_numbers(mut self, preserve_numbers: bool) -> Self {
+        self.options = self.options.preserve_numbers(preserve_numbers);
+        self
+    }
+
+    /// `normalize_timestamps()` is the same as `Yaml2JsonOptions::normalize_timestamps()`.
+    pub fn normalize_timestamps(mut self, normalize_timestamps: bool) -> Self {
+        self.options = self.options.normalize_timestamps(normalize_timestamps);
+        self
+    }
+
+    /// `color()` is the same as `Yaml2JsonOptions::color()`.
+    pub fn color(mut self, color: bool) -> Self {
+        self.options = self.options.color(color);
+        self
+    }
+
+    /// `query()` is the same as `Yaml2JsonOptions::query()`.
+    pub fn query(mut self, query: Query) -> Self {
+        self.options = self.options.query(query);
+        self
+    }
+
+    /// `strict()` is the same as `Yaml2JsonOptions::strict()`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options = self.options.strict(strict);
+        self
+    }
+
+    /// `comments_key()` is the same as `Yaml2JsonOptions::comments_key()`.
+    pub fn comments_key(mut self, key: impl Into<String>) -> Self {
+        self.options = self.options.comments_key(key);
+        self
+    }
+
+    /// `env_subst()` is the same as `Yaml2JsonOptions::env_subst()`.
+    pub fn env_subst(mut self, env: EnvSubst) -> Self {
+        self.options = self.options.env_subst(env);
+        self
+    }
+
+    /// `alias_refs()` is the same as `Yaml2JsonOptions::alias_refs()`.
+    pub fn alias_refs(mut self, key: impl Into<String>) -> Self {
+        self.options = self.options.alias_refs(key);
+        self
+    }
+
+    /// `limits()` is the same as `Yaml2JsonOptions::limits()`.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.options = self.options.limits(limits);
+        self
+    }
+
+    /// `tag_handler()` is the same as `Yaml2JsonOptions::tag_handler()`.
+    pub fn tag_handler<F>(mut self, tag: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&str, Value) -> Value + Send + Sync + 'static,
+    {
+        self.options = self.options.tag_handler(tag, handler);
+        self
+    }
+
+    /// `transform()` is the same as `Yaml2JsonOptions::transform()`.
+    pub fn transform<F>(mut self, pattern: PathPattern, transform: F) -> Self
+    where
+        F: Fn(&str, Value) -> Value + Send + Sync + 'static,
+    {
+        self.options = self.options.transform(pattern, transform);
+        self
+    }
+
+    /// `redact()` is the same as `Yaml2JsonOptions::redact()`.
+    pub fn redact(mut self, pattern: PathPattern) -> Self {
+        self.options = self.options.redact(pattern);
+        self
     }
 
     /// `document_to_string()` takes a YAML document &str and converts it to a JSON String.
@@ -113,17 +843,73 @@ impl Yaml2Json {
     /// assert_eq!(output, r#"{"hello":"world"}"#);
     /// ```
     pub fn document_to_string(&self, document: &str) -> Result<String, Yaml2JsonError> {
-        let s: serde_json::Value = serde_yaml::from_str(document)?;
+        if self.direct(document) {
+            // Safe to unwrap, JSON is only written as valid UTF-8.
+            return Ok(String::from_utf8(self.yaml_document_to_json(document)?).unwrap());
+        }
 
-        let res = match self.style {
-            COMPACT => serde_json::to_string(&s),
-            PRETTY => serde_json::to_string_pretty(&s),
-        };
+        self.value_to_string(&self.document_to_value(document)?)
+    }
 
-        match res {
-            Ok(s) => Ok(s),
-            Err(e) => Err(e.into()),
+    /// `value_to_string()` outputs a `serde_json::Value` in the style of this `Yaml2Json`, e.g. for
+    /// a value from `stream_values()`. Options which affect conversion, such as `sort_keys()`, are
+    /// not applied.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    /// use serde_json::json;
+    ///
+    /// let y2j = Yaml2Json::new(Style::PRETTY);
+    /// let output = y2j.value_to_string(&json!({"hello": "world"})).unwrap();
+    ///
+    /// assert_eq!(output, "{\n  \"hello\": \"world\"\n}");
+    /// ```
+    pub fn value_to_string(&self, value: &Value) -> Result<String, Yaml2JsonError> {
+        if self.options.format.is_binary() {
+            return Err(Yaml2JsonError::UnsupportedError(format!(
+                "{} output is binary and can't be a String",
+                self.options.format
+            )));
+        }
+
+        let mut buf = Vec::new();
+        self.value_to_writer(value, &mut buf)?;
+
+        // Safe to unwrap, the text formats are only written as valid UTF-8.
+        Ok(String::from_utf8(buf).unwrap())
+    }
+
+    /// `value_to_writer()` is like `value_to_string()`, but sends the output to the provided
+    /// writer. This works for every `Format`.
+    pub fn value_to_writer<W: io::Write>(
+        &self,
+        value: &Value,
+        w: &mut W,
+    ) -> Result<(), Yaml2JsonError> {
+        match self.options.format {
+            Format::Json => self.write(value, w)?,
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => rmp_serde::encode::write(w, &plain(value))
+                .map_err(|e| encode_error(self.options.format, e))?,
+            #[cfg(feature = "cbor")]
+            Format::Cbor => ciborium::into_writer(&plain(value), w).map_err(|e| match e {
+                ciborium::ser::Error::Io(e) => Yaml2JsonError::IOError(e),
+                e => encode_error(self.options.format, e),
+            })?,
+            #[cfg(feature = "toml")]
+            Format::Toml => {
+                let s = if self.options.pretty {
+                    toml::to_string_pretty(&plain(value))
+                } else {
+                    toml::to_string(&plain(value))
+                };
+                w.write_all(
+                    s.map_err(|e| encode_error(self.options.format, e))?
+                        .as_bytes(),
+                )?;
+            }
         }
+
+        Ok(())
     }
 
     /// `document_to_writer()` takes a YAML document string, converts it to JSON and sends the output
@@ -146,49 +932,890 @@ impl Yaml2Json {
         document: &str,
         w: &mut W,
     ) -> Result<(), Yaml2JsonError> {
-        let s: serde_json::Value = serde_yaml::from_str(document)?;
+        // Nothing is written unless the whole document converts.
+        if self.direct(document) {
+            return Ok(w.write_all(&self.yaml_document_to_json(document)?)?);
+        }
 
-        let res = match self.style {
-            PRETTY => serde_json::to_writer_pretty(w, &s),
-            COMPACT => serde_json::to_writer(w, &s),
-        };
+        self.value_to_writer(&self.document_to_value(document)?, w)
+    }
 
-        match res {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.into()),
+    /// `document_to_value()` takes a YAML document &str and converts it to a `serde_json::Value`,
+    /// ready for further processing.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world";
+    /// let output = y2j.document_to_value(input).unwrap();
+    ///
+    /// assert_eq!(output["hello"], "world");
+    /// ```
+    pub fn document_to_value(&self, document: &str) -> Result<Value, Yaml2JsonError> {
+        match self.options.input {
+            Input::Yaml => self.yaml_document_to_value(document),
+            Input::Json => self.json_document_to_value(document),
+            Input::Auto if json::looks_like_json(document.as_bytes()) => self
+                .json_document_to_value(document)
+                .or_else(|_| self.yaml_document_to_value(document)),
+            Input::Auto => self.yaml_document_to_value(document),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::{Style, Yaml2Json};
-    use std::io::Cursor;
+    /// `front_matter_to_value()` converts the YAML front matter of a Markdown or other file, which
+    /// is delimited by `---` lines at its start, e.g. `---\ntitle: Hello\n---\n# Hello`. A file
+    /// without front matter is converted as an empty document. With `content_key`, the rest of the
+    /// file is added to the front matter under that key, which needs the front matter to be a
+    /// mapping, or empty.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "---\ntitle: Hello\n---\n# Hello\n";
+    ///
+    /// assert_eq!(y2j.front_matter_to_value(input, None).unwrap()["title"], "Hello");
+    /// let output = y2j.front_matter_to_value(input, Some("content")).unwrap();
+    /// assert_eq!(output["content"], "# Hello\n");
+    /// ```
+    pub fn front_matter_to_value(
+        &self,
+        input: &str,
+        content_key: Option<&str>,
+    ) -> Result<Value, Yaml2JsonError> {
+        let (value, body) = match front_matter::split(input) {
+            Some((yaml, body)) => {
+                // The front matter starts on the second line.
+                let value = self
+                    .yaml_document_to_value(yaml)
+                    .map_err(|e| offset_lines(e, 1))?;
+                (value, body)
+            }
+            None => (self.yaml_document_to_value("")?, input),
+        };
 
-    #[test]
-    fn document_to_string_compact() {
-        let yaml2json = Yaml2Json::new(Style::COMPACT);
-        let input = r#"
----
-abc: def
-"#;
-        let expected = r#"{"abc":"def"}"#;
-        let res = yaml2json.document_to_string(input).unwrap();
-        assert_eq!(expected, res);
+        let key = match content_key {
+            Some(key) => key,
+            None => return Ok(value),
+        };
+        let mut map = match value {
+            Value::Object(map) => map,
+            Value::Null => serde_json::Map::new(),
+            _ => {
+                return Err(Yaml2JsonError::UnsupportedError(
+                    "front matter must be a mapping to add the content to it".to_string(),
+                ))
+            }
+        };
+        map.insert(key.to_string(), Value::String(body.to_string()));
+
+        Ok(Value::Object(map))
     }
 
-    #[test]
-    fn document_to_string_pretty() {
-        let yaml2json = Yaml2Json::new(Style::PRETTY);
-        let input = r#"
----
-abc: def
-"#;
-        let expected = r#"{
-  "abc": "def"
-}"#;
-        let res = yaml2json.document_to_string(input).unwrap();
-        assert_eq!(expected, res);
+    fn json_document_to_value(&self, document: &str) -> Result<Value, Yaml2JsonError> {
+        let value = serde_json::from_str(document).map_err(json::parse_error)?;
+        json::check_depth(&value, self.options.limits.max_depth)?;
+        self.finish(value)
+    }
+
+    /// `direct()` returns whether `document` can be converted straight to JSON text, without
+    /// building a `Value` first. Each scalar is then written as soon as it is parsed, so the
+    /// strings from the parser aren't copied again.
+    fn direct(&self, document: &str) -> bool {
+        let yaml = match self.options.input {
+            Input::Yaml => true,
+            Input::Json => false,
+            Input::Auto => !json::looks_like_json(document.as_bytes()),
+        };
+
+        yaml && !self.materialize()
+    }
+
+    /// `materialize()` returns whether each document must be held in memory as a `Value` before
+    /// it is output, as some options need the whole document.
+    fn materialize(&self) -> bool {
+        self.options.sort_keys
+            || self.options.canonical
+            || self.options.query.is_some()
+            || self.options.comments_key.is_some()
+            || !self.options.transforms.is_empty()
+            || self.options.format != Format::Json
+    }
+
+    /// `yaml_document_to_json()` converts a YAML document to JSON text, see `direct()`. With
+    /// `DuplicateKeys::Last`, a document which turns out to contain a duplicate key is converted
+    /// again as a `Value`, as the earlier value has already been written.
+    fn yaml_document_to_json(&self, document: &str) -> Result<Vec<u8>, Yaml2JsonError> {
+        match self.yaml_document_to_json_direct(document) {
+            Err(Yaml2JsonError::DuplicateKeyError { .. })
+                if self.options.duplicate_keys == DuplicateKeys::Last =>
+            {
+                let mut buf = Vec::new();
+                self.write(&self.yaml_document_to_value(document)?, &mut buf)?;
+                Ok(buf)
+            }
+            res => res,
+        }
+    }
+
+    fn yaml_document_to_json_direct(&self, document: &str) -> Result<Vec<u8>, Yaml2JsonError> {
+        let events = RefCell::new(Events::new(self, document).direct());
+        let mut buf = Vec::new();
+
+        // A stream without any documents, e.g. only comments, is treated as null.
+        let res = if events.borrow_mut().next_document()? {
+            self.write(&Node { events: &events }, &mut buf)
+        } else {
+            self.write(&Value::Null, &mut buf)
+        };
+
+        match res {
+            Ok(()) => {
+                events.borrow_mut().end_document()?;
+                Ok(buf)
+            }
+            Err(e) => Err(events.borrow_mut().take_error(e)),
+        }
+    }
+
+    /// `yaml_document_to_string()` is `document_to_string()` for a document which is known to be
+    /// YAML.
+    fn yaml_document_to_string(&self, document: &str) -> Result<String, Yaml2JsonError> {
+        if self.materialize() {
+            return self.value_to_string(&self.yaml_document_to_value(document)?);
+        }
+
+        // Safe to unwrap, JSON is only written as valid UTF-8.
+        Ok(String::from_utf8(self.yaml_document_to_json(document)?).unwrap())
+    }
+
+    fn yaml_document_to_value(&self, document: &str) -> Result<Value, Yaml2JsonError> {
+        let events = RefCell::new(Events::new(self, document));
+
+        // A stream without any documents, e.g. only comments, is treated as null.
+        let res = if events.borrow_mut().next_document()? {
+            serde_json::to_value(Node { events: &events })
+        } else {
+            Ok(Value::Null)
+        };
+
+        match res {
+            Ok(mut value) => {
+                events.borrow_mut().end_document()?;
+                if let Some(key) = &self.options.comments_key {
+                    comments::embed(&mut value, key, comments::collect(document)?);
+                }
+                self.finish(value)
+            }
+            Err(e) => Err(events.borrow_mut().take_error(e)),
+        }
+    }
+
+    /// `reader_to_writer()` converts every YAML document in `reader` to JSON as it is read, sending
+    /// the output to the provided writer with a newline after each document.
+    ///
+    /// Unlike the other conversions, documents are never held in memory as a whole, so this is
+    /// suitable for very large documents. Only anchored nodes are kept around, to expand the
+    /// aliases which refer to them. As output is written before the rest of the document has been
+    /// read, each document does have to be held in memory when keys are sorted, with a `Query` or
+    /// for formats other than JSON, and unless the input is `Input::Yaml`. With
+    /// `DuplicateKeys::Last` (the default), each mapping is held in memory until its end, as a
+    /// later key can replace an earlier value, but sequences are still written as they are read.
+    /// There is a newline after each document for text formats only.
+    ///
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    /// use std::io;
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world\n---\nhello: rust";
+    /// let mut stdout = io::stdout();
+    ///
+    /// y2j.reader_to_writer(input.as_bytes(), &mut stdout).unwrap();
+    ///
+    /// // {"hello":"world"}
+    /// // {"hello":"rust"}
+    /// ```
+    pub fn reader_to_writer<R: io::Read, W: io::Write>(
+        &self,
+        reader: R,
+        w: &mut W,
+    ) -> Result<(), Yaml2JsonError> {
+        self.reader_to_writer_with(reader, w, |_, _| {})
+    }
+
+    /// `reader_to_writer_with()` is like `reader_to_writer()`, but calls `f` with the writer and
+    /// the index of each document once it has been written, e.g. to flush the output or count the
+    /// documents. An error ends the conversion, after the documents before it.
+    ///
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world\n---\nhello: [rust";
+    /// let mut output = Vec::new();
+    /// let mut docs = 0;
+    ///
+    /// let res = y2j.reader_to_writer_with(input.as_bytes(), &mut output, |_, doc| docs = doc + 1);
+    ///
+    /// assert!(res.is_err());
+    /// assert_eq!(docs, 1);
+    /// assert_eq!(output, b"{\"hello\":\"world\"}\n");
+    /// ```
+    pub fn reader_to_writer_with<R, W, F>(
+        &self,
+        reader: R,
+        w: &mut W,
+        mut f: F,
+    ) -> Result<(), Yaml2JsonError>
+    where
+        R: io::Read,
+        W: io::Write,
+        F: FnMut(&mut W, usize),
+    {
+        if self.split_documents() {
+            for (doc, value) in self.stream_values(reader).enumerate() {
+                self.value_to_writer(&value?, w)?;
+
+                if !self.options.format.is_binary() {
+                    w.write_all(b"\n")?;
+                }
+                f(w, doc);
+            }
+
+            return Ok(());
+        }
+
+        let events = RefCell::new(Events::from_reader(self, reader).hold_mappings());
+        let mut doc = 0;
+
+        while events.borrow_mut().next_document()? {
+            let node = Node { events: &events };
+            let res = if self.materialize() {
+                match serde_json::to_value(node) {
+                    Ok(value) => Ok(self.value_to_writer(&self.finish(value)?, w)?),
+                    Err(e) => Err(e),
+                }
+            } else {
+                self.write(&node, w)
+            };
+
+            if let Err(e) = res {
+                return Err(events.borrow_mut().take_error(e));
+            }
+
+            if !self.options.format.is_binary() {
+                w.write_all(b"\n")?;
+            }
+            f(w, doc);
+            doc += 1;
+        }
+
+        Ok(())
+    }
+
+    /// `reader_to_values()` converts every YAML document in `reader` to a `serde_json::Value`.
+    ///
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world\n---\nhello: rust";
+    /// let output = y2j.reader_to_values(input.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(output.len(), 2);
+    /// assert_eq!(output[1]["hello"], "rust");
+    /// ```
+    pub fn reader_to_values<R: io::Read>(&self, reader: R) -> Result<Vec<Value>, Yaml2JsonError> {
+        if self.split_documents() {
+            return self.stream_values(reader).collect();
+        }
+
+        let events = RefCell::new(Events::from_reader(self, reader));
+        let mut values = Vec::new();
+
+        while events.borrow_mut().next_document()? {
+            match serde_json::to_value(Node { events: &events }) {
+                Ok(value) => values.push(self.finish(value)?),
+                Err(e) => return Err(events.borrow_mut().take_error(e)),
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// `stream()` splits the YAML documents in `reader` and converts each of them to a JSON
+    /// String, in order.
+    ///
+    /// Each document is converted separately, so a document which fails to convert is returned as
+    /// an error without affecting the documents after it. The line numbers in these errors are
+    /// relative to the start of `reader`, not the document. An I/O error from `reader` ends the
+    /// iterator.
+    ///
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world\n---\nhello: [rust\n---\nhello: everyone";
+    /// let mut docs = y2j.stream(input.as_bytes());
+    ///
+    /// assert_eq!(docs.next().unwrap().unwrap(), r#"{"hello":"world"}"#);
+    /// assert!(docs.next().unwrap().is_err());
+    /// assert_eq!(docs.next().unwrap().unwrap(), r#"{"hello":"everyone"}"#);
+    /// assert!(docs.next().is_none());
+    /// ```
+    pub fn stream<'a, R: io::Read + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<String, Yaml2JsonError>> + 'a {
+        let strings: Box<dyn Iterator<Item = _>> =
+            if self.options.input == Input::Yaml && !self.materialize() {
+                Box::new(self.split_map(reader, move |doc, _| self.document_to_string(doc)))
+            } else {
+                Box::new(
+                    self.stream_values(reader)
+                        .map(move |res| res.and_then(|value| self.value_to_string(&value))),
+                )
+            };
+
+        strings
+    }
+
+    /// `stream_values()` is like `stream()`, but converts each document to a `serde_json::Value`.
+    ///
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world\n---\nhello: [rust";
+    /// let mut docs = y2j.stream_values(input.as_bytes());
+    ///
+    /// assert_eq!(docs.next().unwrap().unwrap()["hello"], "world");
+    /// assert!(docs.next().unwrap().is_err());
+    /// assert!(docs.next().is_none());
+    /// ```
+    pub fn stream_values<'a, R: io::Read + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<Value, Yaml2JsonError>> + 'a {
+        let values: Box<dyn Iterator<Item = _>> = match self.options.input {
+            Input::Yaml => Box::new(self.yaml_stream_values(reader)),
+            Input::Json => Box::new(self.json_stream_values(json::values(reader))),
+            Input::Auto => self.auto_stream_values(io::BufReader::new(reader)),
+        };
+
+        values
+    }
+
+    /// `stream_deserialize()` is like `stream_values()`, but deserializes each document into a `T`,
+    /// instead of leaving it as a `serde_json::Value`. A document which doesn't match `T` fails
+    /// with `Yaml2JsonError::SerdeJsonError`, and the stream continues with the next one.
+    /// ```
+    /// use serde::Deserialize;
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Service {
+    ///     name: String,
+    ///     port: u16,
+    /// }
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "name: web\nport: 80\n---\nname: db\nport: high";
+    /// let mut services = y2j.stream_deserialize::<Service, _>(input.as_bytes());
+    ///
+    /// assert_eq!(services.next().unwrap().unwrap().port, 80);
+    /// assert!(services.next().unwrap().is_err());
+    /// assert!(services.next().is_none());
+    /// ```
+    pub fn stream_deserialize<'a, T: DeserializeOwned, R: io::Read + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<T, Yaml2JsonError>> + 'a {
+        self.stream_values(reader)
+            .map(|res| Ok(serde_json::from_value(res?)?))
+    }
+
+    /// `stream_parallel()` is like `stream()`, but converts up to `jobs` documents at once on
+    /// their own threads, or one per CPU when `jobs` is 0. This speeds up large streams of many
+    /// documents. Another thread reads `reader` ahead of the conversion and splits it into
+    /// documents, and the results are still returned in the order of the documents, exactly as
+    /// `stream()` returns them.
+    ///
+    /// Only a few documents per thread are held at once, however far ahead the other threads are
+    /// of a slow document. JSON input, which can't be split ahead of parsing it, is converted on
+    /// the calling thread instead, as by `stream()`. A panic in a `transform()` or `tag_handler()`
+    /// is raised again on the calling thread, in place of the document's result.
+    /// ```
+    /// use std::io::Cursor;
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input: String = (0..100).map(|i| format!("---\nid: {}\n", i)).collect();
+    /// let docs: Vec<_> = y2j
+    ///     .stream_parallel(Cursor::new(input), 4)
+    ///     .map(Result::unwrap)
+    ///     .collect();
+    ///
+    /// assert_eq!(docs[42], r#"{"id":42}"#);
+    /// ```
+    pub fn stream_parallel<'a, R: io::Read + Send + 'static>(
+        &'a self,
+        reader: R,
+        jobs: usize,
+    ) -> impl Iterator<Item = Result<String, Yaml2JsonError>> + 'a {
+        let strings: Box<dyn Iterator<Item = _>> = match self.parallel_input(reader) {
+            Ok(reader) => Box::new(parallel::pipeline(
+                Arc::new(self.clone()),
+                reader,
+                jobs,
+                Self::yaml_document_to_string,
+            )),
+            Err(reader) => Box::new(self.stream(reader)),
+        };
+
+        strings
+    }
+
+    /// `stream_values_parallel()` is like `stream_parallel()`, but converts each document to a
+    /// `serde_json::Value`, as `stream_values()` does.
+    pub fn stream_values_parallel<'a, R: io::Read + Send + 'static>(
+        &'a self,
+        reader: R,
+        jobs: usize,
+    ) -> impl Iterator<Item = Result<Value, Yaml2JsonError>> + 'a {
+        let values: Box<dyn Iterator<Item = _>> = match self.parallel_input(reader) {
+            Ok(reader) => Box::new(parallel::pipeline(
+                Arc::new(self.clone()),
+                reader,
+                jobs,
+                Self::yaml_document_to_value,
+            )),
+            Err(reader) => Box::new(self.stream_values(reader)),
+        };
+
+        values
+    }
+
+    /// `parallel_input()` returns `reader` as `Ok` if it can be split into documents ahead of
+    /// converting them, i.e. it is YAML, or `Err` if it must be converted one value at a time.
+    fn parallel_input<R: io::Read>(&self, reader: R) -> Result<io::BufReader<R>, io::BufReader<R>> {
+        let mut reader = io::BufReader::new(reader);
+
+        match self.options.input {
+            Input::Yaml => Ok(reader),
+            Input::Json => Err(reader),
+            // A read error is left for the splitter to report.
+            Input::Auto => match reader.fill_buf() {
+                Ok(buf) if json::looks_like_json(buf) => Err(reader),
+                _ => Ok(reader),
+            },
+        }
+    }
+
+    fn json_stream_values<'a>(
+        &'a self,
+        values: impl Iterator<Item = Result<Value, Yaml2JsonError>> + 'a,
+    ) -> impl Iterator<Item = Result<Value, Yaml2JsonError>> + 'a {
+        values.map(move |res| res.and_then(|value| self.json_value(value)))
+    }
+
+    /// `json_value()` finishes a parsed JSON value, as YAML documents are.
+    fn json_value(&self, value: Value) -> Result<Value, Yaml2JsonError> {
+        json::check_depth(&value, self.options.limits.max_depth)?;
+        self.finish(value)
+    }
+
+    /// `auto_stream_values()` parses the input as JSON while it is JSON, see `json::Prefix`, and
+    /// the rest of it as YAML.
+    fn auto_stream_values<'a, R: io::Read + 'a>(
+        &'a self,
+        mut reader: io::BufReader<R>,
+    ) -> Box<dyn Iterator<Item = Result<Value, Yaml2JsonError>> + 'a> {
+        match reader.fill_buf() {
+            Ok(buf) if json::looks_like_json(buf) => {}
+            Ok(_) => return Box::new(self.yaml_stream_values(reader)),
+            Err(e) => return Box::new(std::iter::once(Err(e.into()))),
+        }
+
+        let mut prefix = json::Prefix::default();
+        // The reader while the input is JSON, dropped once it has ended or failed.
+        let mut reader = Some(reader);
+        let mut yaml = None;
+
+        Box::new(std::iter::from_fn(move || loop {
+            if let Some(value) = prefix.next() {
+                return Some(self.json_value(value));
+            }
+            if let Some(values) = yaml.as_mut() {
+                return Iterator::next(values);
+            }
+
+            let input = reader.as_mut()?;
+            let (json, ended) = match input.fill_buf() {
+                Ok([]) => (prefix.finish(), true),
+                Ok(buf) => {
+                    let len = buf.len();
+                    let json = prefix.push(buf);
+                    input.consume(len);
+                    (json, false)
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    reader = None;
+                    return Some(Err(e.into()));
+                }
+            };
+
+            if !json {
+                let (rest, lines) = prefix.rest();
+                // Safe to unwrap, it was just read from.
+                let rest = io::Cursor::new(rest).chain(reader.take().unwrap());
+                let values = self.yaml_stream_values(rest);
+                yaml = Some(values.map(move |res| res.map_err(|e| offset_lines(e, lines))));
+            } else if ended {
+                reader = None;
+            }
+        }))
+    }
+
+    fn yaml_stream_values<'a, R: io::Read + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<Value, Yaml2JsonError>> + 'a {
+        self.split_map(reader, move |doc, _| self.yaml_document_to_value(doc))
+    }
+
+    /// `split()` splits a YAML stream into documents, applying `Limits::max_document_size`.
+    fn split<R: io::Read>(&self, reader: R) -> SpannedDocumentIterator<R> {
+        let documents = DocumentIterator::new(reader);
+
+        match self.options.limits.max_document_size {
+            Some(max) => documents.max_document_size(max).with_spans(),
+            None => documents.with_spans(),
+        }
+    }
+
+    /// `split_map()` splits a YAML stream into documents, converting each of them with `convert`,
+    /// which is given the document and the line it starts on. Line numbers in errors are made
+    /// relative to the start of `reader`, and an I/O error ends the iterator.
+    fn split_map<'a, R: io::Read + 'a, T: 'a>(
+        &'a self,
+        reader: R,
+        convert: impl Fn(&str, usize) -> Result<T, Yaml2JsonError> + 'a,
+    ) -> impl Iterator<Item = Result<T, Yaml2JsonError>> + 'a {
+        let mut failed = false;
+
+        self.split(reader).map_while(move |res| {
+            if failed {
+                return None;
+            }
+
+            failed = matches!(res, Err(YamlSplitError::IOError(_)));
+            Some(convert_split(res, &convert))
+        })
+    }
+
+    /// `split_documents()` returns whether the input must be split into documents and converted
+    /// one at a time with `stream_values()`, rather than parsed as a single stream.
+    fn split_documents(&self) -> bool {
+        self.options.input != Input::Yaml
+            || self.options.comments_key.is_some()
+            || self.options.limits.max_document_size.is_some()
+    }
+
+    /// `document_comments()` finds the comments in a YAML document, along with the JSON Pointer of
+    /// the mapping entry or sequence item each of them belongs to. A comment on a line of its own
+    /// belongs to the node after it, and a comment at the end of a line to the node on that line.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "# The image to run.\nimage: nginx\nports: [80] # HTTP only";
+    /// let comments = y2j.document_comments(input).unwrap();
+    ///
+    /// assert_eq!(comments[0].path, "/image");
+    /// assert_eq!(comments[0].text, "The image to run.");
+    /// assert_eq!(comments[1].path, "/ports");
+    /// assert_eq!(comments[1].line, 3);
+    /// ```
+    pub fn document_comments(&self, document: &str) -> Result<Vec<Comment>, Yaml2JsonError> {
+        comments::collect(document)
+    }
+
+    /// `stream_comments()` is like `stream()`, but returns the comments in each document, see
+    /// `document_comments()`. Line numbers are relative to the start of `reader`. JSON input has
+    /// no comments, so this returns nothing for `Input::Json`.
+    pub fn stream_comments<'a, R: io::Read + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<Vec<Comment>, Yaml2JsonError>> + 'a {
+        let comments = self.split_map(reader, |doc, line| {
+            Ok(comments::collect(doc)?
+                .into_iter()
+                .map(|c| Comment {
+                    line: c.line + line - 1,
+                    ..c
+                })
+                .collect())
+        });
+
+        (self.options.input != Input::Json)
+            .then_some(comments)
+            .into_iter()
+            .flatten()
+    }
+
+    /// `finish()` applies the options which need the whole document to have been converted.
+    fn finish(&self, value: Value) -> Result<Value, Yaml2JsonError> {
+        let value = if self.options.transforms.is_empty() {
+            value
+        } else {
+            self.options.transforms.apply(value)
+        };
+
+        let value = match &self.options.query {
+            Some(query) => {
+                query
+                    .select(value)
+                    .ok_or_else(|| Yaml2JsonError::QueryNotFoundError {
+                        query: query.to_string(),
+                    })?
+            }
+            None => value,
+        };
+
+        if self.options.canonical {
+            Ok(canonical::sort_keys(value))
+        } else if self.options.sort_keys {
+            Ok(sort_keys(value))
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn write<T: Serialize, W: io::Write>(&self, value: &T, w: &mut W) -> serde_json::Result<()> {
+        if self.options.canonical {
+            value.serialize(&mut serde_json::Serializer::with_formatter(
+                w,
+                CanonicalFormatter,
+            ))
+        } else if self.options.pretty {
+            let indent = self.options.indent.to_bytes();
+            self.write_with(value, w, PrettyFormatter::with_indent(&indent))
+        } else {
+            self.write_with(value, w, CompactFormatter)
+        }
+    }
+
+    fn write_with<T: Serialize, W: io::Write, F: JsonFormatter>(
+        &self,
+        value: &T,
+        w: &mut W,
+        formatter: F,
+    ) -> serde_json::Result<()> {
+        if self.options.ascii {
+            self.write_colored(value, w, AsciiFormatter::new(formatter))
+        } else {
+            self.write_colored(value, w, formatter)
+        }
+    }
+
+    fn write_colored<T: Serialize, W: io::Write, F: JsonFormatter>(
+        &self,
+        value: &T,
+        w: &mut W,
+        formatter: F,
+    ) -> serde_json::Result<()> {
+        if self.options.color {
+            let formatter = ColorFormatter::new(formatter);
+            value.serialize(&mut serde_json::Serializer::with_formatter(w, formatter))
+        } else {
+            value.serialize(&mut serde_json::Serializer::with_formatter(w, formatter))
+        }
+    }
+}
+
+/// `plain()` prepares a value for the formats other than JSON, see `numbers::PlainNumbers`.
+#[cfg(all(
+    feature = "arbitrary-precision",
+    any(feature = "msgpack", feature = "cbor", feature = "toml")
+))]
+fn plain(value: &Value) -> numbers::PlainNumbers<'_> {
+    numbers::PlainNumbers(value)
+}
+
+#[cfg(all(
+    not(feature = "arbitrary-precision"),
+    any(feature = "msgpack", feature = "cbor", feature = "toml")
+))]
+fn plain(value: &Value) -> &Value {
+    value
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "toml"))]
+fn encode_error(format: Format, e: impl Display) -> Yaml2JsonError {
+    Yaml2JsonError::EncodeError {
+        format,
+        message: e.to_string(),
+    }
+}
+
+/// `from_reader_multi()` deserializes each document in a YAML stream into a `T`, with the default
+/// options, see `Yaml2Json::stream_deserialize()`. This skips converting each document to JSON
+/// text first, only to parse it again.
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Service {
+///     name: String,
+/// }
+///
+/// let input = "name: web\n---\nname: db";
+/// let services = yaml2json_rs::from_reader_multi::<Service, _>(input.as_bytes());
+/// let names: Vec<String> = services.map(|s| s.unwrap().name).collect();
+///
+/// assert_eq!(names, ["web", "db"]);
+/// ```
+pub fn from_reader_multi<'a, T: DeserializeOwned, R: io::Read + 'a>(
+    reader: R,
+) -> impl Iterator<Item = Result<T, Yaml2JsonError>> + 'a {
+    static DEFAULT: OnceLock<Yaml2Json> = OnceLock::new();
+
+    DEFAULT
+        .get_or_init(|| Yaml2Json::new(Style::COMPACT))
+        .stream_deserialize(reader)
+}
+
+/// `convert_split()` converts a document from `Yaml2Json::split()` with `convert`, which is given
+/// the document and the line it starts on. Line numbers in errors are made relative to the start
+/// of the input.
+fn convert_split<T>(
+    res: Result<(String, Span), YamlSplitError>,
+    convert: impl Fn(&str, usize) -> Result<T, Yaml2JsonError>,
+) -> Result<T, Yaml2JsonError> {
+    match res {
+        Ok((doc, span)) => convert(&doc, span.line).map_err(|e| offset_lines(e, span.line - 1)),
+        Err(YamlSplitError::IOError(e)) => Err(e.into()),
+        Err(YamlSplitError::DocumentTooLargeError { max, .. }) => {
+            Err(Yaml2JsonError::LimitExceededError {
+                limit: Limit::DocumentSize,
+                max,
+            })
+        }
+    }
+}
+
+/// `offset_lines()` moves the location of `e` down by `lines`, for documents which don't start at
+/// the beginning of the input.
+fn offset_lines(e: Yaml2JsonError, lines: usize) -> Yaml2JsonError {
+    match e {
+        Yaml2JsonError::ParseError {
+            message,
+            line,
+            column,
+        } => Yaml2JsonError::ParseError {
+            message,
+            line: line + lines,
+            column,
+        },
+        Yaml2JsonError::DuplicateKeyError { key, line, column } => {
+            Yaml2JsonError::DuplicateKeyError {
+                key,
+                line: line + lines,
+                column,
+            }
+        }
+        Yaml2JsonError::NonStringKeyError { key, line, column } => {
+            Yaml2JsonError::NonStringKeyError {
+                key,
+                line: line + lines,
+                column,
+            }
+        }
+        Yaml2JsonError::MergeKeyError { line, column } => Yaml2JsonError::MergeKeyError {
+            line: line + lines,
+            column,
+        },
+        Yaml2JsonError::LossyError {
+            message,
+            line,
+            column,
+        } => Yaml2JsonError::LossyError {
+            message,
+            line: line + lines,
+            column,
+        },
+        Yaml2JsonError::UndefinedVariableError { name, line, column } => {
+            Yaml2JsonError::UndefinedVariableError {
+                name,
+                line: line + lines,
+                column,
+            }
+        }
+        e => e,
+    }
+}
+
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k, sort_keys(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        from_reader_multi, DuplicateKeys, Indent, Input, KeyMode, Limit, Limits, MergeKeys, Style,
+        Yaml2Json, Yaml2JsonError,
+    };
+    use serde::Deserialize;
+    use serde_json::{json, Value};
+    use std::cell::Cell;
+    use std::io::{self, Cursor};
+    use std::rc::Rc;
+
+    /// Keys in reverse order, so no hash or sorted order matches the YAML.
+    const UNORDERED: &str = "zeta: 1\nyank: 2\nxray: {c: 3, b: 4, a: 5}\nwhiskey: 6\nvictor: 7\n";
+    const UNORDERED_JSON: &str =
+        r#"{"zeta":1,"yank":2,"xray":{"c":3,"b":4,"a":5},"whiskey":6,"victor":7}"#;
+
+    #[test]
+    fn document_to_string_compact() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT);
+        let input = r#"
+---
+abc: def
+"#;
+        let expected = r#"{"abc":"def"}"#;
+        let res = yaml2json.document_to_string(input).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn document_to_string_pretty() {
+        let yaml2json = Yaml2Json::new(Style::PRETTY);
+        let input = r#"
+---
+abc: def
+"#;
+        let expected = r#"{
+  "abc": "def"
+}"#;
+        let res = yaml2json.document_to_string(input).unwrap();
+        assert_eq!(expected, res);
     }
 
     #[test]
@@ -224,4 +1851,712 @@ abc: def
         let res = String::from_utf8(buf.into_inner()).unwrap();
         assert_eq!(expected, res);
     }
+
+    #[test]
+    fn reader_to_writer_compact() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT);
+        let input = r#"
+---
+abc: def
+---
+- 1
+- two
+"#;
+        let expected = "{\"abc\":\"def\"}\n[1,\"two\"]\n";
+
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        yaml2json
+            .reader_to_writer(input.as_bytes(), buf.get_mut())
+            .unwrap();
+
+        let res = String::from_utf8(buf.into_inner()).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn reader_to_writer_matches_document_to_string() {
+        let yaml2json = Yaml2Json::new(Style::PRETTY);
+        let input = r#"
+zzz: &anchor
+  - 1.5
+  - ~
+aaa: *anchor
+mmm: {"quoted": 'yes', plain: yes}
+"#;
+        let expected = yaml2json.document_to_string(input).unwrap() + "\n";
+
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        yaml2json
+            .reader_to_writer(input.as_bytes(), buf.get_mut())
+            .unwrap();
+
+        let res = String::from_utf8(buf.into_inner()).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn reader_to_writer_streams() {
+        /// `Input` is a document far larger than `LIMIT`, which fails to read past `LIMIT` until
+        /// some output has been written, as it would if the document was held in memory first.
+        struct Input {
+            yaml: Vec<u8>,
+            pos: usize,
+            written: Rc<Cell<usize>>,
+        }
+
+        impl io::Read for Input {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.pos > LIMIT && self.written.get() == 0 {
+                    return Err(io::Error::other("nothing written yet"));
+                }
+
+                let n = buf.len().min(self.yaml.len() - self.pos);
+                buf[..n].copy_from_slice(&self.yaml[self.pos..self.pos + n]);
+                self.pos += n;
+                Ok(n)
+            }
+        }
+
+        struct Output(Rc<Cell<usize>>);
+
+        impl io::Write for Output {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.set(self.0.get() + buf.len());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        const LIMIT: usize = 64 * 1024;
+        let written = Rc::new(Cell::new(0));
+        let input = Input {
+            yaml: "- item\n".repeat(LIMIT).into_bytes(),
+            pos: 0,
+            written: written.clone(),
+        };
+
+        Yaml2Json::new(Style::COMPACT)
+            .duplicate_keys(DuplicateKeys::Error)
+            .reader_to_writer(input, &mut Output(written.clone()))
+            .unwrap();
+
+        assert_eq!(written.get(), "\"item\",".len() * LIMIT + 2);
+    }
+
+    #[test]
+    fn reader_to_writer_duplicate_keys() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Error);
+        let input = "abc: def\nabc: ghi\n";
+
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        let res = yaml2json.reader_to_writer(input.as_bytes(), buf.get_mut());
+
+        assert!(matches!(
+            res,
+            Err(Yaml2JsonError::DuplicateKeyError { key, line: 2, column: 1 }) if key == "abc"
+        ));
+
+        let yaml2json = Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Last);
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        yaml2json
+            .reader_to_writer(input.as_bytes(), buf.get_mut())
+            .unwrap();
+
+        let res = String::from_utf8(buf.into_inner()).unwrap();
+        assert_eq!("{\"abc\":\"ghi\"}\n", res);
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let input = "a: 1\nb: {c: [2], c: 3}\na: {d: 4}\n";
+        let convert = |y2j: &Yaml2Json| {
+            let string = y2j.document_to_string(input).map_err(|e| e.to_string());
+            let value = y2j.document_to_value(input).map_err(|e| e.to_string());
+            let stream: Vec<_> = y2j
+                .stream(input.as_bytes())
+                .map(|res| res.map_err(|e| e.to_string()))
+                .collect();
+
+            let mut buf = Vec::new();
+            let written = y2j
+                .reader_to_writer(input.as_bytes(), &mut buf)
+                .map(|_| String::from_utf8(buf).unwrap())
+                .map_err(|e| e.to_string());
+
+            assert_eq!(string, value.map(|v| v.to_string()));
+            assert_eq!(vec![string.clone()], stream);
+            assert_eq!(string.clone().map(|s| s + "\n"), written);
+            string
+        };
+
+        let last = r#"{"a":{"d":4},"b":{"c":3}}"#;
+        assert_eq!(
+            Ok(last.to_string()),
+            convert(&Yaml2Json::new(Style::COMPACT))
+        );
+        assert_eq!(
+            Ok(last.to_string()),
+            convert(&Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Last))
+        );
+        assert_eq!(
+            Ok(r#"{"a":1,"b":{"c":[2]}}"#.to_string()),
+            convert(&Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::First))
+        );
+        assert_eq!(
+            Err("duplicate mapping key `c` at line 2 column 13".to_string()),
+            convert(&Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Error))
+        );
+
+        // Read as YAML, the output is written as it is read, holding each mapping until its end.
+        let input = "- {a: 1, b: 2, a: [3]}\n- <<: {c: 4}\n  c: 5\n  c: 6\n";
+        let expected = r#"[{"a":[3],"b":2},{"c":6}]"#;
+        for merge_keys in [MergeKeys::Keep, MergeKeys::Resolve] {
+            let y2j = Yaml2Json::new(Style::COMPACT)
+                .input(Input::Yaml)
+                .merge_keys(merge_keys);
+            let mut buf = Vec::new();
+            y2j.reader_to_writer(input.as_bytes(), &mut buf).unwrap();
+            assert_eq!(
+                y2j.document_to_string(input).unwrap() + "\n",
+                String::from_utf8(buf).unwrap()
+            );
+        }
+        let y2j = Yaml2Json::new(Style::COMPACT).merge_keys(MergeKeys::Resolve);
+        assert_eq!(expected, y2j.document_to_string(input).unwrap());
+
+        // Documents without duplicates are converted as usual.
+        let y2j = Yaml2Json::new(Style::PRETTY);
+        assert_eq!(
+            "{\n  \"a\": [\n    1\n  ]\n}",
+            y2j.document_to_string("a: [1]").unwrap()
+        );
+    }
+
+    #[test]
+    fn reader_to_writer_query() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT).query("/a/1".parse().unwrap());
+        let input = "a: [b, {c: d}]\n---\na: [b]\n";
+
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        let res = yaml2json.reader_to_writer(input.as_bytes(), buf.get_mut());
+
+        assert!(matches!(
+            res,
+            Err(Yaml2JsonError::QueryNotFoundError { query }) if query == "/a/1"
+        ));
+
+        let res = String::from_utf8(buf.into_inner()).unwrap();
+        assert_eq!("{\"c\":\"d\"}\n", res);
+    }
+
+    #[test]
+    fn redact() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT)
+            .redact("**.password".parse().unwrap())
+            .query("db".parse().unwrap());
+        let input = "db: {user: app, password: [a, b]}\n---\n{\"db\": {\"password\": 1}}\n";
+
+        let mut buf = Vec::new();
+        yaml2json
+            .reader_to_writer(input.as_bytes(), &mut buf)
+            .unwrap();
+        assert_eq!(
+            "{\"user\":\"app\",\"password\":\"***\"}\n{\"password\":\"***\"}\n",
+            String::from_utf8(buf).unwrap()
+        );
+
+        let yaml2json = Yaml2Json::new(Style::COMPACT).redact("/env/*_TOKEN".parse().unwrap());
+        let input = "env: {API_TOKEN: a, TOKEN: b, USER: c}";
+        let docs: Vec<_> = yaml2json
+            .stream_parallel(Cursor::new(input), 2)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            vec![r#"{"env":{"API_TOKEN":"***","TOKEN":"b","USER":"c"}}"#],
+            docs
+        );
+    }
+
+    #[test]
+    fn document_to_value() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT);
+        let input = r#"
+---
+abc: def
+list: [1, true, ~]
+"#;
+        let expected = json!({"abc": "def", "list": [1, true, null]});
+        let res = yaml2json.document_to_value(input).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn document_to_value_empty() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT);
+        let res = yaml2json.document_to_value("# only a comment\n").unwrap();
+        assert_eq!(Value::Null, res);
+    }
+
+    #[test]
+    fn document_to_value_multiple_documents() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT);
+        let res = yaml2json.document_to_value("abc: def\n---\nghi: jkl\n");
+        assert!(matches!(res, Err(Yaml2JsonError::UnsupportedError(_))));
+    }
+
+    #[test]
+    fn reader_to_values() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Last);
+        let input = r#"
+abc: def
+abc: last wins
+---
+- 1
+---
+"#;
+        let expected = vec![json!({"abc": "last wins"}), json!([1]), Value::Null];
+        let res = yaml2json.reader_to_values(input.as_bytes()).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    fn stream() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT);
+        let input = r#"%YAML 1.2
+---
+abc: def
+...
+---
+abc: [def
+---
+- 1
+"#;
+        let mut stream = yaml2json.stream(input.as_bytes());
+
+        assert_eq!(r#"{"abc":"def"}"#, stream.next().unwrap().unwrap());
+        assert!(matches!(
+            stream.next().unwrap(),
+            Err(Yaml2JsonError::ParseError { line: 7, .. })
+        ));
+        assert_eq!("[1]", stream.next().unwrap().unwrap());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn input() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT).input(Input::Json);
+        let input = "{\"a\": 1}\n{\"b\":\t[2]}\n";
+        let res: Vec<String> = yaml2json
+            .stream(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![r#"{"a":1}"#, r#"{"b":[2]}"#], res);
+        assert!(yaml2json.document_to_value("a: 1").is_err());
+
+        let yaml2json = Yaml2Json::new(Style::COMPACT).input(Input::Auto);
+        let res: Vec<String> = yaml2json
+            .stream(input.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![r#"{"a":1}"#, r#"{"b":[2]}"#], res);
+
+        /// `Broken` fails to read, in place of input which hasn't arrived yet.
+        struct Broken;
+
+        impl io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("broken"))
+            }
+        }
+
+        // JSON is converted as it is read, and YAML from the first line which isn't JSON.
+        let input = "{\"a\": 1}\n[2]\n[b, c]: d\n---\ne: [\n".as_bytes();
+        let mut values = yaml2json.stream_values(io::Read::chain(input, Broken));
+        assert_eq!(json!({"a": 1}), values.next().unwrap().unwrap());
+        assert_eq!(json!([2]), values.next().unwrap().unwrap());
+        assert_eq!(
+            json!({"[\"b\",\"c\"]": "d"}),
+            values.next().unwrap().unwrap()
+        );
+        assert!(matches!(
+            values.next().unwrap(),
+            Err(Yaml2JsonError::IOError(_))
+        ));
+        assert!(matches!(
+            yaml2json.stream_values(input).nth(3).unwrap(),
+            Err(Yaml2JsonError::ParseError { line: 6, .. })
+        ));
+
+        // Falls back to YAML.
+        for input in ["{a: 1}\n---\n[2]\n", "a: 1\n---\n[2]\n"] {
+            let res = yaml2json.reader_to_values(input.as_bytes()).unwrap();
+            assert_eq!(vec![json!({"a": 1}), json!([2])], res);
+            assert_eq!(
+                json!({"a": 1}),
+                yaml2json
+                    .document_to_value(input.split("---").next().unwrap())
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn sort_keys() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT).sort_keys(true);
+        let input = r#"
+zzz: [{b: 1, a: 2}]
+aaa:
+  ccc: 1
+  bbb: 2
+"#;
+        let expected = r#"{"aaa":{"bbb":2,"ccc":1},"zzz":[{"a":2,"b":1}]}"#;
+        let res = yaml2json.document_to_string(input).unwrap();
+        assert_eq!(expected, res);
+
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        yaml2json
+            .reader_to_writer(input.as_bytes(), buf.get_mut())
+            .unwrap();
+
+        let res = String::from_utf8(buf.into_inner()).unwrap();
+        assert_eq!(format!("{}\n", expected), res);
+    }
+
+    #[test]
+    fn indent() {
+        let input = r#"
+abc:
+  - def
+"#;
+        let yaml2json = Yaml2Json::new(Style::PRETTY).indent(Indent::Tab);
+        let expected = "{\n\t\"abc\": [\n\t\t\"def\"\n\t]\n}";
+        let res = yaml2json.document_to_string(input).unwrap();
+        assert_eq!(expected, res);
+
+        let yaml2json = Yaml2Json::new(Style::PRETTY).indent(Indent::Spaces(0));
+        let expected = "{\n\"abc\": [\n\"def\"\n]\n}";
+        let res = yaml2json.document_to_string(input).unwrap();
+        assert_eq!(expected, res);
+    }
+
+    #[test]
+    #[cfg(feature = "msgpack")]
+    fn format_msgpack() {
+        use crate::Format;
+
+        let yaml2json = Yaml2Json::new(Style::COMPACT).format(Format::MessagePack);
+        let input = "a: [1, true]\n---\nb\n";
+
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        yaml2json
+            .reader_to_writer(input.as_bytes(), buf.get_mut())
+            .unwrap();
+
+        let expected = b"\x81\xa1a\x92\x01\xc3\xa1b";
+        assert_eq!(&expected[..], &buf.into_inner()[..]);
+        assert!(yaml2json.document_to_string(input).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "cbor")]
+    fn format_cbor() {
+        use crate::Format;
+
+        let yaml2json = Yaml2Json::new(Style::COMPACT).format(Format::Cbor);
+        let input = "a: [1, true]\n---\nb\n";
+
+        let mut buf = Cursor::new(Vec::<u8>::new());
+        yaml2json
+            .reader_to_writer(input.as_bytes(), buf.get_mut())
+            .unwrap();
+
+        let expected = b"\xa1\x61a\x82\x01\xf5\x61b";
+        assert_eq!(&expected[..], &buf.into_inner()[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "toml")]
+    fn format_toml() {
+        use crate::Format;
+
+        let input = "a: [1, true]\nb: {c: d}\n";
+
+        let yaml2json = Yaml2Json::new(Style::COMPACT).format(Format::Toml);
+        let res = yaml2json.document_to_string(input).unwrap();
+        assert_eq!("a = [1, true]\n\n[b]\nc = \"d\"\n", res);
+
+        let yaml2json = Yaml2Json::new(Style::PRETTY).format(Format::Toml);
+        let res = yaml2json.document_to_string(input).unwrap();
+        assert_eq!("a = [\n    1,\n    true,\n]\n\n[b]\nc = \"d\"\n", res);
+
+        assert!(matches!(
+            yaml2json.document_to_string("a: null"),
+            Err(Yaml2JsonError::EncodeError {
+                format: Format::Toml,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "arbitrary-precision")]
+    fn preserve_numbers() {
+        let input = "a: 123456789012345678901234567890\nb: [+0.1, 1]\nc: '1.0000000000000000001'";
+
+        let yaml2json = Yaml2Json::new(Style::COMPACT).preserve_numbers(true);
+        let expected =
+            r#"{"a":123456789012345678901234567890,"b":[0.1,1],"c":"1.0000000000000000001"}"#;
+        assert_eq!(expected, yaml2json.document_to_string(input).unwrap());
+
+        let yaml2json = yaml2json.sort_keys(true);
+        assert_eq!(expected, yaml2json.document_to_string(input).unwrap());
+
+        let yaml2json = yaml2json.canonical(true);
+        let expected = r#"{"a":1.2345678901234568e+29,"b":[0.1,1],"c":"1.0000000000000000001"}"#;
+        assert_eq!(expected, yaml2json.document_to_string(input).unwrap());
+        assert!(yaml2json.document_to_string("1e400").is_err());
+
+        let e = Yaml2Json::new(Style::COMPACT)
+            .document_to_value(input)
+            .unwrap_err();
+        assert_eq!(
+            "integer `123456789012345678901234567890` is outside the 64 bit range",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn error_codes() {
+        let yaml2json = Yaml2Json::new(Style::COMPACT)
+            .key_mode(KeyMode::Error)
+            .duplicate_keys(DuplicateKeys::Error)
+            .merge_keys(MergeKeys::Error);
+        let code = |input: &str| {
+            let e = yaml2json.document_to_string(input).unwrap_err();
+            (e.code(), e.location())
+        };
+
+        assert_eq!(("parse", Some((1, 4))), code("a: ]"));
+        assert_eq!(("duplicate-key", Some((2, 1))), code("a: 1\na: 2"));
+        assert_eq!(("non-string-key", Some((1, 1))), code("1: a"));
+        assert_eq!(("merge-key", Some((2, 5))), code("a: 1\nb: {<<: {c: 2}}"));
+        assert_eq!(("unsupported", None), code("a\n---\nb"));
+
+        let e = Yaml2JsonError::LimitExceededError {
+            limit: Limit::AliasExpansions,
+            max: 1,
+        };
+        assert_eq!(
+            ("limit-exceeded", "alias-expansions"),
+            (e.code(), Limit::AliasExpansions.code())
+        );
+    }
+
+    #[test]
+    fn depth_limit_continues() {
+        let limits = Limits {
+            max_depth: 2,
+            ..Limits::default()
+        };
+        let deep = |res: Option<Result<Value, Yaml2JsonError>>| {
+            matches!(
+                res,
+                Some(Err(Yaml2JsonError::LimitExceededError {
+                    limit: Limit::Depth,
+                    max: 2
+                }))
+            )
+        };
+
+        let y2j = Yaml2Json::new(Style::COMPACT).limits(limits);
+        let mut docs = y2j.stream_values("a: [[1]]\n---\na: [1]\n".as_bytes());
+        assert!(deep(docs.next()));
+        assert_eq!(json!({"a": [1]}), docs.next().unwrap().unwrap());
+        assert!(docs.next().is_none());
+
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .limits(limits)
+            .input(Input::Json);
+        let mut docs = y2j.stream_values("{\"a\": [[1]]}\n{\"a\": [1]}".as_bytes());
+        assert!(deep(docs.next()));
+        assert_eq!(json!({"a": [1]}), docs.next().unwrap().unwrap());
+        assert!(docs.next().is_none());
+        assert!(y2j.document_to_string("[[[1]]]").is_err());
+    }
+
+    #[test]
+    fn document_size_limit() {
+        let limits = Limits {
+            max_document_size: Some(12),
+            ..Limits::default()
+        };
+        let y2j = Yaml2Json::new(Style::COMPACT).limits(limits);
+        let input = "a: 1\n---\nb: [1, 2, 3, 4]\n---\nc: 2\n";
+
+        let mut docs = y2j.stream(input.as_bytes());
+        assert_eq!(r#"{"a":1}"#, docs.next().unwrap().unwrap());
+        assert!(matches!(
+            docs.next().unwrap(),
+            Err(Yaml2JsonError::LimitExceededError {
+                limit: Limit::DocumentSize,
+                max: 12
+            })
+        ));
+        assert_eq!(r#"{"c":2}"#, docs.next().unwrap().unwrap());
+        assert!(docs.next().is_none());
+
+        let mut buf = Vec::new();
+        assert!(y2j.reader_to_writer(input.as_bytes(), &mut buf).is_err());
+        assert_eq!("{\"a\":1}\n", String::from_utf8(buf).unwrap());
+
+        // JSON input isn't split into documents.
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .limits(limits)
+            .input(Input::Json);
+        assert!(y2j
+            .reader_to_values(r#"{"b": [1, 2, 3, 4]}"#.as_bytes())
+            .is_ok());
+    }
+
+    #[test]
+    fn direct_matches_value() {
+        let direct = Yaml2Json::new(Style::PRETTY);
+        // Selecting the whole document makes it go through a `Value` first.
+        let value = Yaml2Json::new(Style::PRETTY).query("".parse().unwrap());
+        let inputs = [
+            "a: &a [1, 0x1f, .inf, 08, ~, yes, \"q\"]\nb: *a\nc: {d: !!str 1}",
+            "# only a comment",
+            "[a, {b: c}]\n",
+            "a: 1\na: 2",
+            "a: [1",
+            "a: 1\n---\nb: 2",
+        ];
+
+        for input in inputs {
+            assert_eq!(
+                value.document_to_string(input).map_err(|e| e.to_string()),
+                direct.document_to_string(input).map_err(|e| e.to_string()),
+                "{}",
+                input
+            );
+        }
+
+        let mut buf = Vec::new();
+        assert!(direct.document_to_writer("a: 1\nb: [2", &mut buf).is_err());
+        assert!(buf.is_empty());
+
+        let stream = "a: 1\n---\nb: [2\n---\nc: 3\n";
+        let direct: Vec<_> = direct.stream(stream.as_bytes()).map(Result::ok).collect();
+        let value: Vec<_> = value.stream(stream.as_bytes()).map(Result::ok).collect();
+        assert_eq!(value, direct);
+    }
+
+    #[test]
+    fn front_matter() {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        let convert = |input: &str, content_key| {
+            y2j.front_matter_to_value(input, content_key)
+                .map_err(|e| e.to_string())
+        };
+
+        assert_eq!(
+            Ok(json!({"a": 1, "content": "---\nb: 2\n"})),
+            convert("---\na: 1\n---\n---\nb: 2\n", Some("content"))
+        );
+        assert_eq!(Ok(json!(null)), convert("# Title\n", None));
+        assert_eq!(
+            Ok(json!({"content": "# Title\n"})),
+            convert("# Title\n", Some("content"))
+        );
+        assert_eq!(
+            Err(
+                "while parsing a flow sequence, expected ',' or ']' at line 3 column 1".to_string()
+            ),
+            convert("---\na: [1\n---\n", None)
+        );
+        assert!(convert("---\n- a\n---\n", None).is_ok());
+        assert!(convert("---\n- a\n---\n", Some("content")).is_err());
+    }
+
+    #[test]
+    fn typed_stream() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Item {
+            name: String,
+            #[serde(default)]
+            tags: Vec<String>,
+        }
+        let item = |name: &str, tags: &[&str]| Item {
+            name: name.to_string(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        };
+
+        let input = "name: a\ntags: [x, y]\n---\nname: 1\n---\nname: [c\n---\nname: d\n";
+        let res: Vec<_> = from_reader_multi::<Item, _>(input.as_bytes())
+            .map(|res| res.map_err(|e| e.to_string()))
+            .collect();
+
+        assert_eq!(4, res.len());
+        assert_eq!(Ok(&item("a", &["x", "y"])), res[0].as_ref());
+        assert!(res[1].as_ref().unwrap_err().contains("expected a string"));
+        assert!(res[2].as_ref().unwrap_err().contains("line 7"));
+        assert_eq!(Ok(&item("d", &[])), res[3].as_ref());
+
+        // The options still apply.
+        let y2j = Yaml2Json::new(Style::COMPACT).input(Input::Json);
+        let res: Vec<Item> = y2j
+            .stream_deserialize("{\"name\": \"e\"} {\"name\": \"f\"}".as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(vec![item("e", &[]), item("f", &[])], res);
+    }
+
+    #[test]
+    fn key_order() {
+        let convert = |y2j: Yaml2Json, input: &str| {
+            let mut buf = Vec::new();
+            y2j.reader_to_writer(input.as_bytes(), &mut buf).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+        let expected = format!("{}\n", UNORDERED_JSON);
+
+        assert_eq!(expected, convert(Yaml2Json::new(Style::COMPACT), UNORDERED));
+        assert_eq!(
+            expected,
+            convert(
+                Yaml2Json::new(Style::COMPACT).duplicate_keys(DuplicateKeys::Last),
+                UNORDERED
+            )
+        );
+        assert_eq!(
+            expected,
+            convert(
+                Yaml2Json::new(Style::COMPACT).query("".parse().unwrap()),
+                UNORDERED
+            )
+        );
+        assert_eq!(
+            expected,
+            convert(
+                Yaml2Json::new(Style::COMPACT).input(Input::Json),
+                UNORDERED_JSON
+            )
+        );
+
+        let value = Yaml2Json::new(Style::COMPACT)
+            .document_to_value(UNORDERED)
+            .unwrap();
+        let keys: Vec<_> = value.as_object().unwrap().keys().collect();
+        assert_eq!(vec!["zeta", "yank", "xray", "whiskey", "victor"], keys);
+
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .duplicate_keys(DuplicateKeys::Last)
+            .merge_keys(MergeKeys::Resolve);
+        let input = "base: &base {m: 1, a: 2}\nz: {c: 3, <<: *base, b: 4, c: 5}";
+        let expected = "{\"base\":{\"m\":1,\"a\":2},\"z\":{\"c\":5,\"m\":1,\"a\":2,\"b\":4}}\n";
+        assert_eq!(expected, convert(y2j, input));
+    }
 }
@@ -0,0 +1,94 @@
+use crate::MergeArrays;
+use serde_json::Value;
+
+/// `merge()` deep-merges `overlay` into `base`, e.g. to apply environment specific configuration
+/// to a base configuration. Objects are merged key by key, with values from `overlay` taking
+/// precedence, and keys which are only in `overlay` added after the keys of `base`. Arrays are
+/// combined according to `arrays`, and any other value from `overlay` replaces the value in
+/// `base`, including `null`.
+/// ```
+/// use serde_json::json;
+/// use yaml2json_rs::{merge, MergeArrays};
+///
+/// let mut config = json!({"image": {"name": "web", "tag": "1.0"}, "ports": [80]});
+/// merge(&mut config, json!({"image": {"tag": "1.1"}, "ports": [443]}), MergeArrays::Append);
+///
+/// assert_eq!(config, json!({"image": {"name": "web", "tag": "1.1"}, "ports": [80, 443]}));
+/// ```
+pub fn merge(base: &mut Value, overlay: Value, arrays: MergeArrays) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value, arrays),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(base), Value::Array(overlay)) if arrays == MergeArrays::Append => {
+            base.extend(overlay);
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::merge::merge;
+    use crate::MergeArrays;
+    use serde_json::{json, Value};
+
+    fn merged(base: Value, overlay: Value, arrays: MergeArrays) -> Value {
+        let mut base = base;
+        merge(&mut base, overlay, arrays);
+        base
+    }
+
+    #[test]
+    fn objects() {
+        let base = json!({"a": {"b": 1, "c": {"d": 2}}, "e": 3});
+        let overlay = json!({"f": 4, "a": {"c": {"g": 5}, "b": null}});
+
+        let res = merged(base, overlay, MergeArrays::Replace);
+        assert_eq!(
+            json!({"a": {"b": null, "c": {"d": 2, "g": 5}}, "e": 3, "f": 4}),
+            res
+        );
+        // Existing keys keep their place.
+        let keys: Vec<_> = res["a"].as_object().unwrap().keys().collect();
+        assert_eq!(vec!["b", "c"], keys);
+    }
+
+    #[test]
+    fn arrays() {
+        let base = json!({"a": [1, 2], "b": [3]});
+        let overlay = json!({"a": [4], "b": "c"});
+
+        assert_eq!(
+            json!({"a": [4], "b": "c"}),
+            merged(base.clone(), overlay.clone(), MergeArrays::Replace)
+        );
+        assert_eq!(
+            json!({"a": [1, 2, 4], "b": "c"}),
+            merged(base, overlay, MergeArrays::Append)
+        );
+    }
+
+    #[test]
+    fn other_values() {
+        assert_eq!(
+            json!(2),
+            merged(json!({"a": 1}), json!(2), MergeArrays::Replace)
+        );
+        assert_eq!(
+            json!({"a": 1}),
+            merged(json!([1]), json!({"a": 1}), MergeArrays::Append)
+        );
+        assert_eq!(
+            json!("b"),
+            merged(json!("a"), json!("b"), MergeArrays::Append)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::YamlVersion;
+use serde::{Serialize, Serializer};
+use serde_json::{Number, Value};
+use std::str::FromStr;
+
+/// The number of significant digits which every IEEE 754 double holds exactly.
+const DOUBLE_DIGITS: usize = 15;
+
+/// `exact()` returns a plain decimal scalar as a number with exactly the same value, for
+/// `Yaml2JsonOptions::preserve_numbers()`. This is `None` for anything which converts exactly
+/// without help, i.e. integers within the 64 bit range and decimals with at most 15 significant
+/// digits, and for anything which isn't a decimal number, e.g. `0x1f`, `.inf` or `1:30`.
+pub(crate) fn exact(s: &str, version: YamlVersion) -> Option<Number> {
+    let separated;
+    let s = if version == YamlVersion::V1_1 {
+        separated = s.replace('_', "");
+        &separated
+    } else {
+        s
+    };
+
+    let (sign, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], Some(&unsigned[i + 1..])),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], Some(&mantissa[i + 1..])),
+        None => (mantissa, None),
+    };
+
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    let valid_exponent = exponent.is_none_or(|e| {
+        let e = e.strip_prefix(['+', '-']).unwrap_or(e);
+        !e.is_empty() && digits(e)
+    });
+
+    if !digits(whole)
+        || !fraction.is_none_or(digits)
+        || (whole.is_empty() && fraction.is_none_or(str::is_empty))
+        || !valid_exponent
+    {
+        return None;
+    }
+
+    let significant = whole.trim_start_matches('0');
+
+    if fraction.is_none() && exponent.is_none() {
+        // Leading zeros make a YAML 1.2 string, or a YAML 1.1 octal integer.
+        if whole.len() > 1 && whole.starts_with('0') {
+            return None;
+        }
+
+        let fits = if sign.is_empty() {
+            whole.parse::<u64>().is_ok()
+        } else {
+            format!("-{}", whole).parse::<i64>().is_ok()
+        };
+        if fits {
+            return None;
+        }
+    } else {
+        // YAML 1.1 floats must contain a `.`.
+        if version == YamlVersion::V1_1 && fraction.is_none() {
+            return None;
+        }
+
+        let all = format!("{}{}", significant, fraction.unwrap_or(""));
+        let all = all.trim_start_matches('0').trim_end_matches('0');
+        let f = s.parse::<f64>().ok()?;
+        let representable = f.is_finite() && (f != 0.0 || all.is_empty());
+
+        if all.len() <= DOUBLE_DIGITS && representable {
+            return None;
+        }
+    }
+
+    // JSON doesn't allow a `+`, leading zeros, or a `.` without digits on both sides.
+    let mut json = format!(
+        "{}{}",
+        sign,
+        if significant.is_empty() {
+            "0"
+        } else {
+            significant
+        }
+    );
+    if let Some(fraction) = fraction.filter(|f| !f.is_empty()) {
+        json.push('.');
+        json.push_str(fraction);
+    }
+    if let Some(exponent) = exponent {
+        json.push('e');
+        json.push_str(exponent);
+    }
+
+    Number::from_str(&json).ok()
+}
+
+/// `PlainNumbers` serializes a `Value` with each number as an integer or float, for the formats
+/// other than JSON. With `arbitrary_precision`, `serde_json` serializes numbers as a private
+/// struct which only it understands. Numbers beyond the 64 bit integers become floats.
+pub(crate) struct PlainNumbers<'a>(pub(crate) &'a Value);
+
+impl Serialize for PlainNumbers<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Number(n) => {
+                if let Some(n) = n.as_u64() {
+                    serializer.serialize_u64(n)
+                } else if let Some(n) = n.as_i64() {
+                    serializer.serialize_i64(n)
+                } else {
+                    // Every valid number parses to a float, if only an infinite one.
+                    serializer.serialize_f64(n.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            Value::Array(seq) => serializer.collect_seq(seq.iter().map(PlainNumbers)),
+            Value::Object(map) => {
+                serializer.collect_map(map.iter().map(|(k, v)| (k, PlainNumbers(v))))
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::numbers::{exact, PlainNumbers};
+    use crate::YamlVersion;
+    use serde_json::json;
+
+    fn exact_1_2(s: &str) -> Option<String> {
+        exact(s, YamlVersion::V1_2).map(|n| n.to_string())
+    }
+
+    #[test]
+    fn exact_numbers() {
+        for (s, expected) in [
+            (
+                "123456789012345678901234567890",
+                "123456789012345678901234567890",
+            ),
+            ("-9223372036854775809", "-9223372036854775809"),
+            ("+18446744073709551616", "18446744073709551616"),
+            ("0.1234567890123456789", "0.1234567890123456789"),
+            ("12345678901234567.50", "12345678901234567.50"),
+            (".12345678901234567", "0.12345678901234567"),
+            ("0012345678901234567.", "12345678901234567"),
+            ("1e400", "1e+400"),
+            ("-1.5E-400", "-1.5e-400"),
+        ] {
+            assert_eq!(Some(expected.to_string()), exact_1_2(s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn inexact_numbers() {
+        for s in [
+            "18446744073709551615",
+            "-9223372036854775808",
+            "0.1",
+            "1.5e300",
+            "0.000000000000000000000000001",
+            "0.0",
+            "0123456789012345678901234567890",
+            "0x123456789012345678901234567890",
+            "123_456_789_012_345_678_901",
+            "1.2.3",
+            ".",
+            "1e",
+            ".inf",
+        ] {
+            assert_eq!(None, exact_1_2(s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn exact_numbers_1_1() {
+        let exact_1_1 = |s| exact(s, YamlVersion::V1_1).map(|n| n.to_string());
+
+        assert_eq!(
+            Some("123456789012345678901".to_string()),
+            exact_1_1("123_456_789_012_345_678_901")
+        );
+        assert_eq!(None, exact_1_1("0123456789012345678901234567890"));
+        assert_eq!(None, exact_1_1("1e400"));
+    }
+
+    #[test]
+    fn plain_numbers() {
+        let big = exact(&"9".repeat(30), YamlVersion::V1_2).unwrap();
+        let value = json!({"a": [1, -1, 1.5], "b": big});
+        let plain = serde_json::to_value(PlainNumbers(&value)).unwrap();
+
+        assert_eq!(json!({"a": [1, -1, 1.5], "b": 1e30}), plain);
+    }
+}
@@ -0,0 +1,502 @@
+use crate::transform::Transforms;
+use crate::{
+    Binary, DuplicateKeys, EnvSubst, Format, Indent, Input, KeyMode, Limits, MergeKeys,
+    PathPattern, Query, SpecialFloats, UnknownTags, YamlVersion,
+};
+use core::fmt::{self, Debug, Formatter};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use yaml_rust2::parser::Tag;
+
+/// `Yaml2JsonOptions` configures a `Yaml2Json`, see `Yaml2Json::with_options()`. Every option
+/// defaults to compact JSON output which matches the YAML input as closely as possible.
+/// ```
+/// use yaml2json_rs::{DuplicateKeys, Yaml2Json, Yaml2JsonOptions};
+///
+/// let options = Yaml2JsonOptions::new()
+///     .pretty(true)
+///     .duplicate_keys(DuplicateKeys::Last)
+///     .ascii(true);
+/// let y2j = Yaml2Json::with_options(options);
+/// let output = y2j.document_to_string("a: café\na: thé").unwrap();
+///
+/// assert_eq!(output, "{\n  \"a\": \"th\\u00e9\"\n}");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Yaml2JsonOptions {
+    pub(crate) pretty: bool,
+    pub(crate) input: Input,
+    pub(crate) format: Format,
+    pub(crate) indent: Indent,
+    pub(crate) sort_keys: bool,
+    pub(crate) merge_keys: MergeKeys,
+    pub(crate) duplicate_keys: DuplicateKeys,
+    pub(crate) key_mode: KeyMode,
+    pub(crate) yaml_version: YamlVersion,
+    pub(crate) binary: Binary,
+    pub(crate) special_floats: SpecialFloats,
+    pub(crate) unknown_tags: UnknownTags,
+    pub(crate) normalize_timestamps: bool,
+    pub(crate) color: bool,
+    pub(crate) ascii: bool,
+    pub(crate) canonical: bool,
+    #[cfg(feature = "arbitrary-precision")]
+    pub(crate) preserve_numbers: bool,
+    pub(crate) query: Option<Query>,
+    pub(crate) limits: Limits,
+    pub(crate) tag_handlers: TagHandlers,
+    pub(crate) transforms: Transforms,
+    pub(crate) comments_key: Option<String>,
+    pub(crate) alias_refs: Option<String>,
+    pub(crate) env_subst: Option<EnvSubst>,
+    pub(crate) strict: bool,
+}
+
+type TagHandler = dyn Fn(&str, Value) -> Value + Send + Sync;
+
+/// `TagHandlers` holds the handlers registered with `Yaml2JsonOptions::tag_handler()`, by the full
+/// name of their tag.
+#[derive(Clone, Default)]
+pub(crate) struct TagHandlers(HashMap<String, Arc<TagHandler>>);
+
+impl TagHandlers {
+    /// `get()` returns the name of `tag` and its handler, if one is registered.
+    pub(crate) fn get(&self, tag: &Tag) -> Option<(String, &TagHandler)> {
+        if self.0.is_empty() {
+            return None;
+        }
+
+        let name = format!("{}{}", tag.handle, tag.suffix);
+        let handler = self.0.get(&name)?;
+        Some((name, handler.as_ref()))
+    }
+}
+
+impl Debug for TagHandlers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+impl Yaml2JsonOptions {
+    /// `new()` creates the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `pretty()` sets whether JSON is output on multiple lines, with indentation, see
+    /// `Style::PRETTY`.
+    pub fn pretty(mut self, pretty: bool) -> Self {
+        self.pretty = pretty;
+        self
+    }
+
+    /// `input()` sets how the input is parsed, see `Input`. The other options still apply to JSON
+    /// input, as far as they are relevant, e.g. `sort_keys()` and `query()`.
+    pub fn input(mut self, input: Input) -> Self {
+        self.input = input;
+        self
+    }
+
+    /// `format()` sets the encoding of the output, see `Format`. Conversions to a String fail for
+    /// binary formats with `Yaml2JsonError::UnsupportedError`, use the writer conversions instead.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// `indent()` sets the indentation of pretty output, and turns pretty output on.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions, Indent};
+    ///
+    /// let options = Yaml2JsonOptions::new().indent(Indent::Spaces(4));
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "hello: world";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, "{\n    \"hello\": \"world\"\n}");
+    /// ```
+    pub fn indent(mut self, indent: Indent) -> Self {
+        self.indent = indent;
+        self.pretty = true;
+        self
+    }
+
+    /// `sort_keys()` sets whether the keys of every JSON object are sorted lexicographically,
+    /// instead of following the order of the YAML document. This gives deterministic output, e.g.
+    /// for diffing.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().sort_keys(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "b: 1\na: {d: 2, c: 3}";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#"{"a":{"c":3,"d":2},"b":1}"#);
+    /// ```
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// `merge_keys()` sets how YAML merge keys are handled, see `MergeKeys`.
+    pub fn merge_keys(mut self, merge_keys: MergeKeys) -> Self {
+        self.merge_keys = merge_keys;
+        self
+    }
+
+    /// `duplicate_keys()` sets what happens when a mapping contains the same key twice, see
+    /// `DuplicateKeys`. By default, the last value is kept.
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    /// `key_mode()` sets how mapping keys which aren't strings are converted, see `KeyMode`.
+    pub fn key_mode(mut self, key_mode: KeyMode) -> Self {
+        self.key_mode = key_mode;
+        self
+    }
+
+    /// `yaml_version()` sets which version of the YAML spec is used to resolve plain scalars, see
+    /// `YamlVersion`. `%YAML` directives in the input do not affect this.
+    pub fn yaml_version(mut self, yaml_version: YamlVersion) -> Self {
+        self.yaml_version = yaml_version;
+        self
+    }
+
+    /// `binary()` sets how `!!binary` scalars are converted, see `Binary`.
+    pub fn binary(mut self, binary: Binary) -> Self {
+        self.binary = binary;
+        self
+    }
+
+    /// `special_floats()` sets how NaN and infinite floats are converted, see `SpecialFloats`.
+    pub fn special_floats(mut self, special_floats: SpecialFloats) -> Self {
+        self.special_floats = special_floats;
+        self
+    }
+
+    /// `unknown_tags()` sets how nodes with an application-defined tag but no `tag_handler()` are
+    /// converted, see `UnknownTags`.
+    pub fn unknown_tags(mut self, unknown_tags: UnknownTags) -> Self {
+        self.unknown_tags = unknown_tags;
+        self
+    }
+
+    /// `normalize_timestamps()` sets whether timestamps are converted to RFC 3339 strings. This
+    /// applies to scalars tagged `!!timestamp` and plain scalars which look like timestamps, e.g.
+    /// `2001-12-14` or `2001-12-14 21:59:43.10 -5`. Dates, and times without a timezone, are
+    /// treated as UTC.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().normalize_timestamps(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "[2001-12-14, 2001-12-14 21:59:43.10 -5]";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#"["2001-12-14T00:00:00Z","2001-12-14T21:59:43.1-05:00"]"#);
+    /// ```
+    pub fn normalize_timestamps(mut self, normalize_timestamps: bool) -> Self {
+        self.normalize_timestamps = normalize_timestamps;
+        self
+    }
+
+    /// `color()` sets whether the output is highlighted with ANSI escape codes, for display in a
+    /// terminal. Keys, strings, numbers, booleans and null each have their own color.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().color(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "hello: 1";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, "{\x1b[1;34m\"hello\"\x1b[0m:\x1b[36m1\x1b[0m}");
+    /// ```
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// `query()` sets a `Query` which selects the value to output from each document, instead of
+    /// the whole document. Documents without a value at `query` fail with
+    /// `Yaml2JsonError::QueryNotFoundError`. Each document has to be held in memory to do this.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().query("spec.image".parse().unwrap());
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "spec: {image: rust, replicas: 2}";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#""rust""#);
+    /// ```
+    pub fn query(mut self, query: Query) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    /// `limits()` sets the `Limits` applied to each document, see `Limits` for the defaults.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// `ascii()` sets whether every non-ASCII character in JSON strings is escaped as `\uXXXX`,
+    /// for consumers which can't handle UTF-8. Characters outside the Basic Multilingual Plane are
+    /// escaped as a UTF-16 surrogate pair.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().ascii(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let output = y2j.document_to_string("[é, 🦀]").unwrap();
+    ///
+    /// assert_eq!(output, r#"["\u00e9","\ud83e\udd80"]"#);
+    /// ```
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    /// `canonical()` sets whether JSON is output in the canonical form of RFC 8785 (JCS), so that
+    /// equal documents convert to the same bytes, e.g. to hash or compare them. Keys are sorted by
+    /// their UTF-16 code units, there is no whitespace, and numbers are formatted as in
+    /// ECMAScript, as the IEEE 754 doubles RFC 8785 requires, so integers beyond 2^53 lose
+    /// precision. This overrides `pretty()`, `ascii()` and `color()`. Keys are also sorted for the
+    /// other formats.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().canonical(true).pretty(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let output = y2j.document_to_string("b: 1.0e2\na: [0.000001, 1e21]").unwrap();
+    ///
+    /// assert_eq!(output, r#"{"a":[0.000001,1e+21],"b":100}"#);
+    /// ```
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// `preserve_numbers()` sets whether numbers which can't be converted exactly are output as
+    /// written, e.g. for IDs and amounts of money. Without it, integers beyond the 64 bit range are
+    /// an error, and decimals with more than 15 significant digits become the nearest float. Any
+    /// `+`, leading zeros or `.` without digits on both sides are removed to make them valid JSON,
+    /// and hexadecimal, octal and binary integers are output in decimal. This needs the
+    /// `arbitrary-precision` feature, which also keeps every number in JSON input as written. Other
+    /// formats still get the nearest float.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().preserve_numbers(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let output = y2j
+    ///     .document_to_string("id: 123456789012345678901234567890\ntotal: 0.1000000000000000055")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     output,
+    ///     r#"{"id":123456789012345678901234567890,"total":0.1000000000000000055}"#
+    /// );
+    /// ```
+    #[cfg(feature = "arbitrary-precision")]
+    pub fn preserve_numbers(mut self, preserve_numbers: bool) -> Self {
+        self.preserve_numbers = preserve_numbers;
+        self
+    }
+
+    /// `strict()` sets whether every conversion which would silently lose information is an error
+    /// instead. This turns on `KeyMode::Error`, `DuplicateKeys::Error`, `SpecialFloats::Error`,
+    /// `Binary::Error` and `UnknownTags::Error`, which later calls can override. Integers outside
+    /// of the 64 bit range, and floats which aren't exactly the decimal they're written as, are
+    /// rejected too, unless `preserve_numbers()` keeps them as written. It also rejects the tags of
+    /// the YAML schemas which have no meaning in JSON, i.e. any other than a core schema tag
+    /// (`!!str`, `!!int`, `!!seq` etc.). These errors are `Yaml2JsonError::LossyError`, with the
+    /// location of the node.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonError, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().strict(true);
+    /// let y2j = Yaml2Json::with_options(options);
+    ///
+    /// assert!(y2j.document_to_string("a: !!str 1").is_ok());
+    /// assert!(matches!(
+    ///     y2j.document_to_string("a: !vault secret"),
+    ///     Err(Yaml2JsonError::LossyError { line: 1, column: 11, .. })
+    /// ));
+    /// assert!(y2j.document_to_string("a: .nan").is_err());
+    /// assert!(y2j.document_to_string("a: 0.1000000000000000055").is_err());
+    /// assert!(y2j.document_to_string("1: a").is_err());
+    /// ```
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+
+        if strict {
+            self.key_mode = KeyMode::Error;
+            self.duplicate_keys = DuplicateKeys::Error;
+            self.special_floats = SpecialFloats::Error;
+            self.binary = Binary::Error;
+            self.unknown_tags = UnknownTags::Error;
+        }
+
+        self
+    }
+
+    /// `comments_key()` sets a key under which the comments in each document are added to its
+    /// root mapping, as an object from the JSON Pointer of each commented node to its comments,
+    /// see `Yaml2Json::document_comments()`. The key replaces any existing key of the same name.
+    /// Documents which aren't mappings, or have no comments, are left as they are. Each document
+    /// has to be held in memory to do this.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().comments_key("$comments");
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "# Seconds.\ntimeout: 30";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#"{"timeout":30,"$comments":{"/timeout":"Seconds."}}"#);
+    /// ```
+    pub fn comments_key(mut self, key: impl Into<String>) -> Self {
+        self.comments_key = Some(key.into());
+        self
+    }
+
+    /// `env_subst()` sets whether references to environment variables in scalars, `${NAME}` and
+    /// `${NAME:-default}`, are replaced with their values, see `EnvSubst`. This happens after the
+    /// YAML is parsed, so the values can't break its syntax, and before plain scalars are resolved,
+    /// so `${PORT}` can become a number. `$${` is a literal `${`. This doesn't apply to JSON input.
+    /// ```
+    /// use yaml2json_rs::{EnvSubst, Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let env = EnvSubst {
+    ///     vars: Some([("PORT".to_string(), "8080".to_string())].into()),
+    ///     ..EnvSubst::default()
+    /// };
+    /// let options = Yaml2JsonOptions::new().env_subst(env);
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "port: ${PORT}\nhost: '${HOST:-localhost}: #1'";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#"{"port":8080,"host":"localhost: #1"}"#);
+    /// ```
+    pub fn env_subst(mut self, env: EnvSubst) -> Self {
+        self.env_subst = Some(env);
+        self
+    }
+
+    /// `alias_refs()` sets a key with which aliases are output as references to their anchored
+    /// node, e.g. `{"$ref": "#/base"}`, instead of as copies of it. The reference is the JSON
+    /// Pointer (RFC 6901) of the place where the anchored node was output, as a URI fragment. This
+    /// also allows aliases within their own anchored node, which can't be expanded.
+    ///
+    /// Aliases used as keys or by merge keys (`<<`) are still expanded, as are aliases of nodes
+    /// which weren't output, e.g. the value of a skipped or replaced duplicate key. Pointers are
+    /// relative to the whole document, even with `query()`, and won't follow changes made by a
+    /// `tag_handler()`.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new().alias_refs("$ref");
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "defaults: &defaults {replicas: 2}\nweb: *defaults";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r##"{"defaults":{"replicas":2},"web":{"$ref":"#/defaults"}}"##);
+    /// ```
+    pub fn alias_refs(mut self, key: impl Into<String>) -> Self {
+        self.alias_refs = Some(key.into());
+        self
+    }
+
+    /// `tag_handler()` registers a handler for nodes with an application-defined tag, e.g. `!vault`
+    /// or CloudFormation's `!Ref`. The handler is called with the tag and the node's value, and
+    /// returns the JSON value to use instead. Scalars are passed as strings, sequences and mappings
+    /// as they would be converted without the handler. A handler takes priority over the built-in
+    /// handling of the tag, if there is any.
+    ///
+    /// Local tags are named as written, e.g. `!Ref`. Tags using a `%TAG` directive, or the `!!`
+    /// shorthand, are named in full, e.g. `!!str` is `tag:yaml.org,2002:str`.
+    /// ```
+    /// use serde_json::{json, Value};
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new()
+    ///     .tag_handler("!Ref", |_, value| json!({ "Ref": value }))
+    ///     .tag_handler("!GetAtt", |_, value| match value {
+    ///         Value::String(s) => json!({ "Fn::GetAtt": s.split('.').collect::<Vec<_>>() }),
+    ///         value => json!({ "Fn::GetAtt": value }),
+    ///     });
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "[!Ref Bucket, !GetAtt Bucket.Arn, !GetAtt [Role, Arn]]";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(
+    ///     output,
+    ///     r#"[{"Ref":"Bucket"},{"Fn::GetAtt":["Bucket","Arn"]},{"Fn::GetAtt":["Role","Arn"]}]"#
+    /// );
+    /// ```
+    pub fn tag_handler<F>(mut self, tag: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&str, Value) -> Value + Send + Sync + 'static,
+    {
+        self.tag_handlers.0.insert(tag.into(), Arc::new(handler));
+        self
+    }
+
+    /// `transform()` registers a transformation of the values whose path matches `pattern`, see
+    /// `PathPattern`. It is called with the JSON Pointer of each value and the value, and returns
+    /// the value to output instead. Values are transformed after the values within them, and
+    /// transformations which match the same value run in the order they were registered. The
+    /// result isn't matched again.
+    ///
+    /// This applies to JSON input too. Paths are relative to the whole document, even with
+    /// `query()`, which selects from the transformed document. Each document has to be held in
+    /// memory to do this.
+    /// ```
+    /// use serde_json::{json, Value};
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new()
+    ///     .transform("**.image".parse().unwrap(), |path, value| match value {
+    ///         Value::String(image) => json!(format!("mirror.example.com/{}", image)),
+    ///         value => panic!("{} isn't a string: {}", path, value),
+    ///     });
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "containers: [{name: app, image: nginx}]";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#"{"containers":[{"name":"app","image":"mirror.example.com/nginx"}]}"#);
+    /// ```
+    pub fn transform<F>(mut self, pattern: PathPattern, transform: F) -> Self
+    where
+        F: Fn(&str, Value) -> Value + Send + Sync + 'static,
+    {
+        self.transforms.0.push((pattern, Arc::new(transform)));
+        self
+    }
+
+    /// `redact()` replaces the values whose path matches `pattern` with `"***"`, e.g. to scrub
+    /// credentials from the output, see `PathPattern` and `transform()`. Mappings and sequences
+    /// are replaced as a whole. This can be called again to redact more paths.
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Yaml2JsonOptions};
+    ///
+    /// let options = Yaml2JsonOptions::new()
+    ///     .redact("**.password".parse().unwrap())
+    ///     .redact("*_token".parse().unwrap());
+    /// let y2j = Yaml2Json::with_options(options);
+    /// let input = "db: {user: app, password: hunter2}\napi_token: abc";
+    /// let output = y2j.document_to_string(input).unwrap();
+    ///
+    /// assert_eq!(output, r#"{"db":{"user":"app","password":"***"},"api_token":"***"}"#);
+    /// ```
+    pub fn redact(self, pattern: PathPattern) -> Self {
+        self.transform(pattern, |_, _| Value::String("***".to_string()))
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{convert_split, Yaml2Json, Yaml2JsonError};
+use std::collections::HashMap;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, Result as ThreadResult};
+use yaml_split::YamlSplitError;
+
+/// `Convert` converts a single YAML document, on one of the worker threads.
+pub(crate) type Convert<T> = fn(&Yaml2Json, &str) -> Result<T, Yaml2JsonError>;
+
+/// `pipeline()` converts the documents in `reader` with `convert` on `jobs` worker threads, or
+/// one per CPU for 0. Another thread reads and splits the input, and `Ordered` puts the results
+/// back in order as they arrive. At most two documents per worker are in flight at once,
+/// counting those waiting behind a slow one to be returned, so memory use stays bounded.
+///
+/// A panic while converting a document, e.g. in a `transform()`, is raised again by `Ordered` in
+/// place of its result, as it would be without worker threads.
+///
+/// Dropping the `Ordered` stops the threads, once the splitter's current read returns.
+pub(crate) fn pipeline<T, R>(
+    y2j: Arc<Yaml2Json>,
+    reader: R,
+    jobs: usize,
+    convert: Convert<T>,
+) -> Ordered<T>
+where
+    T: Send + 'static,
+    R: io::Read + Send + 'static,
+{
+    let jobs = match jobs {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        jobs => jobs,
+    };
+    let capacity = jobs * 2;
+
+    // A token is taken for each document read, and returned once its result is.
+    let (tokens, tokens_rx) = mpsc::sync_channel(capacity);
+    for _ in 0..capacity {
+        // Safe to unwrap, the channel has room and its receiver is alive.
+        tokens.send(()).unwrap();
+    }
+
+    let (work, work_rx) = mpsc::sync_channel(capacity);
+    let work_rx = Arc::new(Mutex::new(work_rx));
+    let (results, results_rx) = mpsc::channel();
+
+    let splitter = Arc::clone(&y2j);
+    thread::spawn(move || {
+        for (i, res) in splitter.split(reader).enumerate() {
+            // Nothing more can be read after an I/O error.
+            let failed = matches!(res, Err(YamlSplitError::IOError(_)));
+
+            // Either fails once the output has been dropped.
+            if tokens_rx.recv().is_err() || work.send((i, res)).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    for _ in 0..jobs {
+        let y2j = Arc::clone(&y2j);
+        let work_rx = Arc::clone(&work_rx);
+        let results = results.clone();
+
+        thread::spawn(move || loop {
+            // The lock is only held while waiting for the next document. Safe to unwrap, nothing
+            // can panic while holding it.
+            let job = work_rx.lock().unwrap().recv();
+            let (i, res) = match job {
+                Ok(job) => job,
+                Err(_) => return,
+            };
+
+            let res = panic::catch_unwind(AssertUnwindSafe(|| {
+                convert_split(res, |doc, _| convert(&y2j, doc))
+            }));
+            if results.send((i, res)).is_err() {
+                return;
+            }
+        });
+    }
+
+    Ordered {
+        results: results_rx,
+        pending: HashMap::new(),
+        next: 0,
+        tokens,
+    }
+}
+
+/// `Ordered` is an iterator over the results of `pipeline()`, in the order of the documents.
+pub(crate) struct Ordered<T> {
+    results: Receiver<(usize, ThreadResult<Result<T, Yaml2JsonError>>)>,
+    /// Results which arrived before those of earlier documents.
+    pending: HashMap<usize, ThreadResult<Result<T, Yaml2JsonError>>>,
+    next: usize,
+    tokens: SyncSender<()>,
+}
+
+impl<T> Iterator for Ordered<T> {
+    type Item = Result<T, Yaml2JsonError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(res) = self.pending.remove(&self.next) {
+                self.next += 1;
+                // Only fails if the splitter has finished.
+                let _ = self.tokens.send(());
+                return Some(res.unwrap_or_else(|payload| panic::resume_unwind(payload)));
+            }
+
+            // Every worker has finished once this fails, so nothing is pending.
+            let (i, res) = self.results.recv().ok()?;
+            self.pending.insert(i, res);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Limit, Limits, Style, Yaml2Json, Yaml2JsonError};
+    use std::io::{self, Read};
+    use std::panic::{self, AssertUnwindSafe};
+
+    /// `corpus()` is a stream of `n` documents, where every tenth one fails to convert. They vary
+    /// in size, so that they finish converting out of order.
+    fn corpus(n: usize) -> String {
+        (0..n)
+            .map(|i| match i % 10 {
+                9 => format!("---\nid: [{}\n", i),
+                _ => format!("---\nid: {}\nitems: {:?}\n", i, vec![i; (n - i) % 50]),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_stream() {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        let input = corpus(500);
+        let expected: Vec<_> = y2j
+            .stream(input.as_bytes())
+            .map(|res| res.map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(500, expected.len());
+
+        for jobs in [0, 1, 4] {
+            let res: Vec<_> = y2j
+                .stream_parallel(io::Cursor::new(input.clone()), jobs)
+                .map(|res| res.map_err(|e| e.to_string()))
+                .collect();
+            assert_eq!(expected, res, "{} jobs", jobs);
+
+            let values: Vec<_> = y2j
+                .stream_values_parallel(io::Cursor::new(input.clone()), jobs)
+                .collect();
+            assert_eq!(expected.len(), values.len());
+        }
+    }
+
+    #[test]
+    fn errors() {
+        /// `Broken` fails after its input.
+        struct Broken(io::Cursor<&'static str>);
+
+        impl Read for Broken {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.read(buf)? {
+                    0 => Err(io::Error::other("broken")),
+                    n => Ok(n),
+                }
+            }
+        }
+
+        let y2j = Yaml2Json::new(Style::COMPACT).limits(Limits {
+            max_document_size: Some(10),
+            ..Limits::default()
+        });
+        let input = Broken(io::Cursor::new("a: 1\n---\nb: [cccccccccc]\n---\nd: [\n"));
+        let mut docs = y2j.stream_parallel(input, 2);
+
+        assert_eq!(r#"{"a":1}"#, docs.next().unwrap().unwrap());
+        assert!(matches!(
+            docs.next().unwrap(),
+            Err(Yaml2JsonError::LimitExceededError {
+                limit: Limit::DocumentSize,
+                ..
+            })
+        ));
+        assert!(matches!(
+            docs.next().unwrap(),
+            Err(Yaml2JsonError::IOError(_))
+        ));
+        assert!(docs.next().is_none());
+    }
+
+    #[test]
+    fn panics() {
+        let y2j = Yaml2Json::new(Style::COMPACT).transform("id".parse().unwrap(), |_, value| {
+            assert_ne!(Some(3), value.as_u64(), "bad id");
+            value
+        });
+
+        for n in [4, 39] {
+            let input = corpus(n);
+            let mut docs = y2j.stream_parallel(io::Cursor::new(input), 2);
+            for _ in 0..3 {
+                assert!(docs.next().is_some());
+            }
+
+            let panic = panic::catch_unwind(AssertUnwindSafe(|| docs.next()));
+            let message = panic.unwrap_err().downcast::<String>().unwrap();
+            assert!(message.contains("bad id"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn early_drop() {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        let input = corpus(10_000);
+
+        let first: Vec<_> = y2j
+            .stream_parallel(io::Cursor::new(input), 2)
+            .take(3)
+            .collect();
+        assert_eq!(r#"{"id":0,"items":[]}"#, first[0].as_ref().unwrap());
+    }
+}
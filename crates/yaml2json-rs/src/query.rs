@@ -0,0 +1,183 @@
+use crate::Yaml2JsonError;
+use core::fmt::{self, Display, Formatter};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// `Query` selects a single value within each document, see `Yaml2Json::query()`.
+///
+/// A query starting with `/` is a JSON Pointer (RFC 6901), e.g. `/spec/containers/0/image`.
+/// Anything else is a dot path, e.g. `spec.containers.0.image`, with an optional leading `.`.
+/// Array elements are selected by their index, starting from 0. The empty query, and the dot path
+/// `.`, select the whole document.
+/// ```
+/// use yaml2json_rs::Query;
+///
+/// let pointer: Query = "/spec/containers/0".parse().unwrap();
+/// let dot_path: Query = ".spec.containers.0".parse().unwrap();
+///
+/// assert_eq!(pointer, dot_path);
+/// assert!("/a~2b".parse::<Query>().is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Query {
+    query: String,
+    segments: Vec<String>,
+}
+
+impl Query {
+    /// `select()` returns the value at this query within `value`, or `None` if there isn't one.
+    pub(crate) fn select(&self, mut value: Value) -> Option<Value> {
+        for segment in &self.segments {
+            value = match value {
+                Value::Object(mut map) => map.remove(segment)?,
+                Value::Array(mut seq) => {
+                    let index = parse_index(segment)?;
+                    if index >= seq.len() {
+                        return None;
+                    }
+                    seq.swap_remove(index)
+                }
+                _ => return None,
+            };
+        }
+
+        Some(value)
+    }
+}
+
+/// `parse_index()` parses an array index, which must not have a sign or leading zeros.
+fn parse_index(s: &str) -> Option<usize> {
+    let canonical = s.bytes().all(|b| b.is_ascii_digit()) && (s == "0" || !s.starts_with('0'));
+
+    if canonical {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+impl FromStr for Query {
+    type Err = Yaml2JsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Query {
+            query: s.to_string(),
+            segments: segments(s)
+                .ok_or_else(|| Yaml2JsonError::InvalidQueryError(s.to_string()))?,
+        })
+    }
+}
+
+/// `segments()` splits a JSON Pointer or dot path into its keys and indexes, see `Query`.
+pub(crate) fn segments(s: &str) -> Option<Vec<String>> {
+    if s.is_empty() || s == "." {
+        Some(Vec::new())
+    } else if let Some(pointer) = s.strip_prefix('/') {
+        pointer.split('/').map(unescape).collect()
+    } else {
+        let path = s.strip_prefix('.').unwrap_or(s);
+        let segments: Vec<String> = path.split('.').map(String::from).collect();
+
+        (!segments.iter().any(String::is_empty)).then_some(segments)
+    }
+}
+
+/// `pointer()` joins `segments` into a JSON Pointer, escaping `~` and `/` within them.
+pub(crate) fn pointer<'a>(segments: impl IntoIterator<Item = &'a str>) -> String {
+    let mut pointer = String::new();
+
+    for segment in segments {
+        pointer.push('/');
+        pointer.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+    }
+
+    pointer
+}
+
+/// `unescape()` replaces `~1` with `/` and `~0` with `~` in a JSON Pointer segment. Any other use
+/// of `~` is invalid.
+fn unescape(segment: &str) -> Option<String> {
+    let mut out = String::with_capacity(segment.len());
+    let mut chars = segment.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next()? {
+                '0' => out.push('~'),
+                '1' => out.push('/'),
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+
+    Some(out)
+}
+
+/// Queries are equal if they select the same value, regardless of their syntax.
+impl PartialEq for Query {
+    fn eq(&self, other: &Self) -> bool {
+        self.segments == other.segments
+    }
+}
+
+impl Eq for Query {}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::{pointer, Query};
+    use serde_json::json;
+
+    fn select(query: &str) -> Option<serde_json::Value> {
+        let value = json!({"a": {"b/c": [1, {"~d": true}], "e.f": "g"}, "": null});
+        query.parse::<Query>().unwrap().select(value)
+    }
+
+    #[test]
+    fn pointers() {
+        assert_eq!(Some(json!(1)), select("/a/b~1c/0"));
+        assert_eq!(Some(json!(true)), select("/a/b~1c/1/~0d"));
+        assert_eq!(Some(json!("g")), select("/a/e.f"));
+        assert_eq!(Some(json!(null)), select("/"));
+        assert_eq!(Some(json!([1, {"~d": true}])), select("/a/b~1c"));
+        assert_eq!(None, select("/a/b~1c/2"));
+        assert_eq!(None, select("/a/b~1c/01"));
+        assert_eq!(None, select("/a/b~1c/-"));
+        assert_eq!(None, select("/a/e.f/0"));
+        assert_eq!(None, select("/b"));
+    }
+
+    #[test]
+    fn dot_paths() {
+        assert_eq!(Some(json!(1)), select("a.b/c.0"));
+        assert_eq!(Some(json!(true)), select(".a.b/c.1.~d"));
+        assert_eq!(None, select("a.e.f"));
+        assert_eq!(None, select("a.b/c.+1"));
+    }
+
+    #[test]
+    fn whole_document() {
+        assert!(select("").unwrap().is_object());
+        assert!(select(".").unwrap().is_object());
+    }
+
+    #[test]
+    fn pointers_from_segments() {
+        assert_eq!("", pointer([]));
+        assert_eq!("/a/b~1c/~0d/0", pointer(["a", "b/c", "~d", "0"]));
+        assert_eq!(Some(json!(true)), select(&pointer(["a", "b/c", "1", "~d"])));
+    }
+
+    #[test]
+    fn invalid() {
+        for query in ["/a~", "/a~2", "a..b", "a.", ".."] {
+            assert!(query.parse::<Query>().is_err(), "{}", query);
+        }
+    }
+}
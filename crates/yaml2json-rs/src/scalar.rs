@@ -0,0 +1,860 @@
+#[cfg(feature = "arbitrary-precision")]
+use crate::numbers;
+use crate::timestamp;
+use crate::{Binary, SpecialFloats, UnknownTags, Yaml2Json, Yaml2JsonError, YamlVersion};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{Number, Value};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::num::IntErrorKind;
+use yaml_rust2::parser::Tag;
+use yaml_rust2::scanner::TScalarStyle;
+
+pub(crate) const CORE_TAG_HANDLE: &str = "tag:yaml.org,2002:";
+
+/// `resolve()` turns a YAML scalar into the JSON value it represents.
+///
+/// Quoted and block scalars are always strings. Plain scalars are resolved using the YAML 1.2 core
+/// schema, i.e. `null`, `true`, `1`, `0x1f` and `1.5e3` become JSON null, booleans and numbers.
+/// With `YamlVersion::V1_1`, the YAML 1.1 types are used instead, which also resolve `yes`, `off`,
+/// `0755` (octal) and `1:30` (sexagesimal). Integers outside of the 64 bit range, in any base,
+/// are an error unless `preserve_numbers()` keeps them, see `big_int()`. The core schema tags (`!!str`, `!!int` etc.) force a
+/// specific type, and `!!binary` is handled according to `Binary`. Timestamps are converted to
+/// RFC 3339 when `normalize_timestamps` is set. A tag with a registered handler is passed to it
+/// instead. Other tags are rejected according to `UnknownTags`, or for unsupported schema tags,
+/// with `Yaml2JsonOptions::strict()`, and ignored otherwise.
+pub(crate) fn resolve(
+    value: String,
+    style: TScalarStyle,
+    tag: Option<&Tag>,
+    y2j: &Yaml2Json,
+) -> Result<Value, Yaml2JsonError> {
+    let version = y2j.options.yaml_version;
+
+    if let Some((name, handler)) = tag.and_then(|tag| y2j.options.tag_handlers.get(tag)) {
+        return Ok(handler(&name, Value::String(value)));
+    }
+    check_tag(tag, SCALAR_TAGS, y2j)?;
+
+    if let Some(tag) = tag.filter(|t| t.handle == CORE_TAG_HANDLE) {
+        let invalid = || {
+            Yaml2JsonError::UnsupportedError(format!(
+                "invalid value for !!{}: {:?}",
+                tag.suffix, value
+            ))
+        };
+
+        return match tag.suffix.as_str() {
+            "str" => Ok(Value::String(value)),
+            "null" if value.is_empty() || is_null(&value) => Ok(Value::Null),
+            "bool" => parse_bool(&value, version)
+                .map(Value::Bool)
+                .ok_or_else(invalid),
+            "int" => match parse_int(&value, version) {
+                Some(Integer::Exact(n)) => Ok(n),
+                Some(Integer::Big(text)) => big_int(text, value, y2j),
+                None => Err(invalid()),
+            },
+            "float" => {
+                let (text, f) = match parse_int(&value, version) {
+                    Some(Integer::Exact(n)) => (Cow::Owned(n.to_string()), n.as_f64()),
+                    Some(Integer::Big(text)) => {
+                        let f = text.parse::<f64>().ok().filter(|f| f.is_finite());
+                        (Cow::Owned(text), f)
+                    }
+                    None => (Cow::Borrowed(value.as_str()), parse_float(&value, version)),
+                };
+                let f = f.ok_or_else(invalid)?;
+
+                check_exact(&text, f, y2j)?;
+                float(f, y2j.options.special_floats)
+            }
+            "null" => Err(invalid()),
+            "binary" => binary(&value, y2j.options.binary).ok_or_else(invalid)?,
+            "timestamp" if y2j.options.normalize_timestamps => timestamp::normalize(&value)
+                .map(Value::String)
+                .ok_or_else(invalid),
+            _ => resolve_untagged(value, style, y2j),
+        };
+    }
+
+    resolve_untagged(value, style, y2j)
+}
+
+/// The core schema tags which can be used on scalars.
+const SCALAR_TAGS: &[&str] = &[
+    "str",
+    "null",
+    "bool",
+    "int",
+    "float",
+    "binary",
+    "timestamp",
+    "merge",
+];
+
+/// `check_tag()` rejects a tag which would be ignored, with `UnknownTags::Error` for tags outside
+/// of the YAML schemas, or `Yaml2JsonOptions::strict()` for the rest. `core` lists the core schema
+/// tags which mean something for the node. Tags with a handler must have been handled already.
+pub(crate) fn check_tag(
+    tag: Option<&Tag>,
+    core: &[&str],
+    y2j: &Yaml2Json,
+) -> Result<(), Yaml2JsonError> {
+    let tag = match tag {
+        Some(tag) => tag,
+        None => return Ok(()),
+    };
+
+    if tag.handle != CORE_TAG_HANDLE {
+        return match y2j.options.unknown_tags {
+            UnknownTags::Error => Err(Yaml2JsonError::UnsupportedError(format!(
+                "unknown tag `{}{}`",
+                tag.handle, tag.suffix
+            ))),
+            UnknownTags::Ignore => Ok(()),
+        };
+    }
+
+    if !y2j.options.strict || core.contains(&tag.suffix.as_str()) {
+        Ok(())
+    } else {
+        Err(Yaml2JsonError::UnsupportedError(format!(
+            "unsupported tag `!!{}`",
+            tag.suffix
+        )))
+    }
+}
+
+fn resolve_untagged(
+    value: String,
+    style: TScalarStyle,
+    y2j: &Yaml2Json,
+) -> Result<Value, Yaml2JsonError> {
+    let version = y2j.options.yaml_version;
+
+    if style != TScalarStyle::Plain {
+        return Ok(Value::String(value));
+    }
+
+    if value.is_empty() || is_null(&value) {
+        return Ok(Value::Null);
+    }
+
+    if let Some(b) = parse_bool(&value, version) {
+        return Ok(Value::Bool(b));
+    }
+
+    // Most scalars are strings, which can be told apart from numbers by their first character.
+    if !maybe_number(&value) {
+        return resolve_string(value, y2j);
+    }
+
+    #[cfg(feature = "arbitrary-precision")]
+    if y2j.options.preserve_numbers {
+        if let Some(n) = numbers::exact(&value, version) {
+            return Ok(Value::Number(n));
+        }
+    }
+
+    match parse_int(&value, version) {
+        Some(Integer::Exact(n)) => return Ok(n),
+        Some(Integer::Big(text)) => return big_int(text, value, y2j),
+        None => {}
+    }
+
+    if version == YamlVersion::V1_1 || !digits_but_not_number(&value) {
+        if let Some(f) = parse_float(&value, version) {
+            check_exact(&value, f, y2j)?;
+            return float(f, y2j.options.special_floats);
+        }
+    }
+
+    resolve_string(value, y2j)
+}
+
+/// `resolve_string()` resolves a plain scalar which isn't anything else, which is a timestamp or
+/// a string.
+fn resolve_string(value: String, y2j: &Yaml2Json) -> Result<Value, Yaml2JsonError> {
+    if y2j.options.normalize_timestamps {
+        if let Some(t) = timestamp::normalize(&value) {
+            return Ok(Value::String(t));
+        }
+    }
+
+    Ok(Value::String(value))
+}
+
+/// `binary()` converts the base64 data of a `!!binary` scalar, returning `None` if it isn't valid
+/// base64. Line breaks and other whitespace within the data are ignored.
+fn binary(value: &str, mode: Binary) -> Option<Result<Value, Yaml2JsonError>> {
+    if mode == Binary::Error {
+        return Some(Err(Yaml2JsonError::UnsupportedError(
+            "binary data (!!binary) is not allowed".to_string(),
+        )));
+    }
+
+    let data: String = value.split_whitespace().collect();
+    let bytes = STANDARD.decode(&data).ok()?;
+
+    Some(Ok(match mode {
+        Binary::Bytes => Value::from(bytes),
+        _ => Value::String(data),
+    }))
+}
+
+/// JSON has no representation for NaN or infinity, these are handled according to
+/// `SpecialFloats`.
+fn float(f: f64, mode: SpecialFloats) -> Result<Value, Yaml2JsonError> {
+    if let Some(n) = Number::from_f64(f) {
+        return Ok(Value::Number(n));
+    }
+
+    let name = if f.is_nan() {
+        "NaN"
+    } else if f > 0.0 {
+        "Infinity"
+    } else {
+        "-Infinity"
+    };
+
+    match mode {
+        SpecialFloats::Null => Ok(Value::Null),
+        SpecialFloats::String => Ok(Value::String(name.to_string())),
+        SpecialFloats::Error => Err(Yaml2JsonError::UnsupportedError(format!(
+            "{} can't be represented in JSON",
+            name
+        ))),
+    }
+}
+
+/// `check_exact()` rejects a float which isn't exactly the decimal number `text` it was parsed
+/// from, with `Yaml2JsonOptions::strict()`, e.g. `0.1000000000000000055` becomes `0.1`. Floats
+/// such as sexagesimal ones, which aren't written in decimal, aren't checked.
+fn check_exact(text: &str, f: f64, y2j: &Yaml2Json) -> Result<(), Yaml2JsonError> {
+    if !y2j.options.strict || !f.is_finite() {
+        return Ok(());
+    }
+
+    // `{:e}` formats the shortest decimal which parses to the same float.
+    match (decimal(text), decimal(&format!("{:e}", f))) {
+        (Some(written), Some(parsed)) if written != parsed => {
+            Err(Yaml2JsonError::UnsupportedError(format!(
+                "`{}` can't be represented exactly as a float",
+                text
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `decimal()` normalizes a decimal number, e.g. `-01.50e1`, to its sign, significant digits and
+/// exponent, e.g. `(true, "15", 0)`, ignoring any `_` separators. Zero is `(false, "", 0)`.
+fn decimal(s: &str) -> Option<(bool, String, i64)> {
+    let s = s.replace('_', "");
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(&s)),
+    };
+    let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+        Some(i) => (&unsigned[..i], unsigned[i + 1..].parse::<i64>().ok()?),
+        None => (unsigned, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    if !whole
+        .bytes()
+        .chain(fraction.bytes())
+        .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let digits = format!("{}{}", whole, fraction);
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+
+    let trailing = (digits.len() - digits.trim_end_matches('0').len()) as i64;
+    let exponent = exponent
+        .checked_sub(fraction.len() as i64)?
+        .checked_add(trailing)?;
+
+    Some((negative, significant.to_string(), exponent))
+}
+
+fn is_null(s: &str) -> bool {
+    matches!(s, "null" | "Null" | "NULL" | "~")
+}
+
+fn parse_bool(s: &str, version: YamlVersion) -> Option<bool> {
+    match (s, version) {
+        ("true" | "True" | "TRUE", _) => Some(true),
+        ("false" | "False" | "FALSE", _) => Some(false),
+        ("y" | "Y" | "yes" | "Yes" | "YES" | "on" | "On" | "ON", YamlVersion::V1_1) => Some(true),
+        ("n" | "N" | "no" | "No" | "NO" | "off" | "Off" | "OFF", YamlVersion::V1_1) => Some(false),
+        _ => None,
+    }
+}
+
+/// `Integer` is an integer scalar, see `parse_int()`.
+enum Integer {
+    /// An integer within the 64 bit range.
+    Exact(Value),
+    /// An integer outside of the 64 bit range, as decimal digits with an optional `-`.
+    Big(String),
+}
+
+/// `parse_int()` parses an integer in any base, or `None` if `s` isn't one.
+fn parse_int(s: &str, version: YamlVersion) -> Option<Integer> {
+    let (negative, digits, radix) = match version {
+        YamlVersion::V1_1 => split_int_1_1(s)?,
+        YamlVersion::V1_2 => split_int(s)?,
+    };
+
+    // Sexagesimal integers don't have a base to convert from.
+    if radix == 60 {
+        let n = match sexagesimal(&digits)? {
+            (n, None) if negative => -n,
+            (n, None) => n,
+            _ => return None,
+        };
+
+        return Some(match (u64::try_from(n), i64::try_from(n)) {
+            (Ok(n), _) => Integer::Exact(Value::from(n)),
+            (_, Ok(n)) => Integer::Exact(Value::from(n)),
+            _ => Integer::Big(n.to_string()),
+        });
+    }
+
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+
+    let n = match u64::from_str_radix(&digits, radix) {
+        Ok(n) => n,
+        Err(e) if *e.kind() == IntErrorKind::PosOverflow => {
+            let decimal = to_decimal(&digits, radix)?;
+            return Some(Integer::Big(if negative {
+                format!("-{}", decimal)
+            } else {
+                decimal
+            }));
+        }
+        Err(_) => return None,
+    };
+
+    Some(if !negative {
+        Integer::Exact(Value::from(n))
+    } else if let Ok(n) = i64::try_from(-i128::from(n)) {
+        Integer::Exact(Value::from(n))
+    } else {
+        Integer::Big(format!("-{}", n))
+    })
+}
+
+/// `split_int()` splits a YAML 1.2 integer into its sign, digits and base. These are decimal, `0x`
+/// hexadecimal, `0o` octal or `0b` binary, with an optional sign.
+fn split_int(s: &str) -> Option<(bool, Cow<'_, str>, u32)> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        if let Some(digits) = unsigned.strip_prefix(prefix) {
+            return Some((negative, Cow::Borrowed(digits), radix));
+        }
+    }
+
+    if digits_but_not_number(s) {
+        return None;
+    }
+
+    Some((negative, Cow::Borrowed(unsigned), 10))
+}
+
+/// `split_int_1_1()` splits a YAML 1.1 integer into its sign, digits and base, without its `_`
+/// separators. These are decimal, `0b` binary, `0` octal, `0x` hexadecimal or sexagesimal (base
+/// 60, e.g. `1:30`).
+fn split_int_1_1(s: &str) -> Option<(bool, Cow<'_, str>, u32)> {
+    let (negative, unsigned) = split_sign(s)?;
+    let digits = if unsigned.contains('_') {
+        Cow::Owned(unsigned.replace('_', ""))
+    } else {
+        Cow::Borrowed(unsigned)
+    };
+
+    let strip = |prefix: &str| match &digits {
+        Cow::Borrowed(d) => d.strip_prefix(prefix).map(Cow::Borrowed),
+        Cow::Owned(d) => d.strip_prefix(prefix).map(|d| Cow::Owned(d.to_string())),
+    };
+
+    Some(if let Some(d) = strip("0b") {
+        (negative, d, 2)
+    } else if let Some(d) = strip("0x") {
+        (negative, d, 16)
+    } else if digits.contains(':') {
+        // Sexagesimal integers can't start with 0, as octal numbers can't contain `:`.
+        if digits.starts_with('0') {
+            return None;
+        }
+        (negative, digits, 60)
+    } else if let Some(d) = strip("0").filter(|d| !d.is_empty()) {
+        (negative, d, 8)
+    } else {
+        (negative, digits, 10)
+    })
+}
+
+/// The most digits, after any leading zeros, which are converted from a base other than 10 by
+/// `to_decimal()`. This is more than any float can hold, even in binary.
+const MAX_BIG_DIGITS: usize = 1100;
+
+/// `to_decimal()` converts the digits of an unsigned integer in base `radix` to decimal, or `None`
+/// if they aren't valid. Inputs with more than `MAX_BIG_DIGITS` digits aren't converted, as this
+/// takes quadratic time.
+fn to_decimal(digits: &str, radix: u32) -> Option<String> {
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+
+    let digits = digits.trim_start_matches('0');
+    if radix == 10 {
+        return Some(digits.to_string());
+    }
+    if digits.len() > MAX_BIG_DIGITS {
+        return None;
+    }
+
+    // Groups of 9 decimal digits, starting from the least significant.
+    const GROUP: u64 = 1_000_000_000;
+    let mut groups: Vec<u64> = Vec::new();
+
+    for c in digits.chars() {
+        // Safe to unwrap, every digit was checked above.
+        let mut carry = u64::from(c.to_digit(radix).unwrap());
+        for group in groups.iter_mut() {
+            let n = *group * u64::from(radix) + carry;
+            *group = n % GROUP;
+            carry = n / GROUP;
+        }
+        if carry > 0 {
+            groups.push(carry);
+        }
+    }
+
+    let mut groups = groups.iter().rev();
+    let mut decimal = groups
+        .next()
+        .map_or_else(|| "0".to_string(), u64::to_string);
+    for group in groups {
+        decimal.push_str(&format!("{:09}", group));
+    }
+
+    Some(decimal)
+}
+
+/// `big_int()` converts an integer outside of the 64 bit range, given as decimal `text`, which was
+/// written as `value`. This is exact with `preserve_numbers()`, and is otherwise an error, as JSON
+/// parsers differ on what such a number means.
+#[cfg_attr(not(feature = "arbitrary-precision"), allow(unused_variables))]
+fn big_int(text: String, value: String, y2j: &Yaml2Json) -> Result<Value, Yaml2JsonError> {
+    #[cfg(feature = "arbitrary-precision")]
+    if y2j.options.preserve_numbers {
+        if let Ok(n) = text.parse::<Number>() {
+            return Ok(Value::Number(n));
+        }
+    }
+
+    Err(Yaml2JsonError::UnsupportedError(format!(
+        "integer `{}` is outside the 64 bit range",
+        value
+    )))
+}
+
+/// `split_sign()` separates the optional sign of a YAML 1.1 number, which must be followed by a
+/// digit (or a `.` for floats).
+fn split_sign(s: &str) -> Option<(bool, &str)> {
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+
+    match unsigned.bytes().next() {
+        Some(b'0'..=b'9' | b'.') => Some((negative, unsigned)),
+        _ => None,
+    }
+}
+
+fn from_digits(digits: &str, radix: u32) -> Option<i128> {
+    if digits.is_empty() || digits.starts_with(['+', '-']) {
+        return None;
+    }
+
+    i128::from_str_radix(digits, radix).ok()
+}
+
+/// `sexagesimal()` parses base 60 numbers such as `190:20:30`, returning the integer value and
+/// the fraction of the last part, if any, e.g. `.5` in `1:30.5`.
+fn sexagesimal(digits: &str) -> Option<(i128, Option<&str>)> {
+    let mut parts = digits.split(':');
+    let mut n = from_digits(parts.next()?, 10)?;
+    let mut fraction = None;
+
+    for part in parts {
+        if fraction.is_some() {
+            return None;
+        }
+
+        let (whole, frac) = match part.find('.') {
+            Some(i) => (&part[..i], Some(&part[i..])),
+            None => (part, None),
+        };
+
+        if whole.is_empty() || whole.len() > 2 || !whole.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let whole = from_digits(whole, 10).filter(|n| *n < 60)?;
+        n = n.checked_mul(60)?.checked_add(whole)?;
+        fraction = frac;
+    }
+
+    Some((n, fraction))
+}
+
+fn parse_float(s: &str, version: YamlVersion) -> Option<f64> {
+    let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+
+    match unsigned {
+        ".inf" | ".Inf" | ".INF" if s.starts_with('-') => return Some(f64::NEG_INFINITY),
+        ".inf" | ".Inf" | ".INF" => return Some(f64::INFINITY),
+        ".nan" | ".NaN" | ".NAN" if unsigned == s => return Some(f64::NAN),
+        _ => {}
+    }
+
+    if version == YamlVersion::V1_1 {
+        return parse_float_1_1(s);
+    }
+
+    if unsigned.starts_with(['+', '-']) {
+        return None;
+    }
+
+    // Rust also accepts "inf", "nan" and friends, which YAML does not.
+    s.parse::<f64>().ok().filter(|f| f.is_finite())
+}
+
+/// `parse_float_1_1()` parses YAML 1.1 floats, which may contain `_` separators and must contain a
+/// `.`, e.g. `1.5`, `1.5e+3` or `.5`. These may also be sexagesimal, e.g. `1:30.5`.
+fn parse_float_1_1(s: &str) -> Option<f64> {
+    let (negative, unsigned) = split_sign(s)?;
+    let digits = unsigned.replace('_', "");
+
+    let f = if digits.contains(':') {
+        let (n, fraction) = sexagesimal(&digits)?;
+        let fraction = match fraction {
+            Some(".") | None => 0.0,
+            Some(fraction) => format!("0{}", fraction).parse::<f64>().ok()?,
+        };
+
+        n as f64 + fraction
+    } else {
+        let valid = digits.contains('.')
+            && digits
+                .bytes()
+                .all(|b| matches!(b, b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-'));
+
+        if !valid {
+            return None;
+        }
+
+        digits.parse::<f64>().ok().filter(|f| f.is_finite())?
+    };
+
+    Some(if negative { -f } else { f })
+}
+
+/// `maybe_number()` returns whether `s` could be an integer or float, in either YAML version. These
+/// all start with a digit or `.` after the optional sign, including `.inf` and `.nan`.
+fn maybe_number(s: &str) -> bool {
+    let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s);
+    matches!(unsigned.bytes().next(), Some(b'0'..=b'9' | b'.'))
+}
+
+/// Leading zero(s) followed by digits is a string according to the YAML 1.2 spec.
+fn digits_but_not_number(s: &str) -> bool {
+    let s = s.strip_prefix(['-', '+']).unwrap_or(s);
+    s.len() > 1 && s.starts_with('0') && s[1..].bytes().all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scalar::resolve;
+    use crate::{Binary, SpecialFloats, Style, Yaml2Json, Yaml2JsonError, YamlVersion};
+    use serde_json::{json, Value};
+    use yaml_rust2::parser::Tag;
+    use yaml_rust2::scanner::TScalarStyle;
+
+    fn plain(s: &str) -> Value {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        resolve(s.to_string(), TScalarStyle::Plain, None, &y2j).unwrap()
+    }
+
+    fn plain_1_1(s: &str) -> Value {
+        let y2j = Yaml2Json::new(Style::COMPACT).yaml_version(YamlVersion::V1_1);
+        resolve(s.to_string(), TScalarStyle::Plain, None, &y2j).unwrap()
+    }
+
+    fn core_tag(suffix: &str) -> Tag {
+        Tag {
+            handle: "tag:yaml.org,2002:".to_string(),
+            suffix: suffix.to_string(),
+        }
+    }
+
+    #[test]
+    fn plain_scalars() {
+        assert_eq!(Value::Null, plain(""));
+        assert_eq!(Value::Null, plain("~"));
+        assert_eq!(json!(true), plain("True"));
+        assert_eq!(json!(12), plain("12"));
+        assert_eq!(json!(-12), plain("-12"));
+        assert_eq!(json!(31), plain("0x1f"));
+        assert_eq!(json!(-8), plain("-0o10"));
+        assert_eq!(json!(1500.0), plain("1.5e3"));
+        assert_eq!(json!("0123"), plain("0123"));
+        assert_eq!(json!("yes"), plain("yes"));
+        assert_eq!(json!("nan"), plain("nan"));
+        assert_eq!(Value::Null, plain(".nan"));
+        assert_eq!(Value::Null, plain("-.inf"));
+    }
+
+    #[test]
+    fn plain_scalars_1_1() {
+        assert_eq!(Value::Null, plain_1_1("~"));
+        assert_eq!(json!(true), plain_1_1("yes"));
+        assert_eq!(json!(false), plain_1_1("Off"));
+        assert_eq!(json!(false), plain_1_1("n"));
+        assert_eq!(json!(1000), plain_1_1("1_000"));
+        assert_eq!(json!(493), plain_1_1("0755"));
+        assert_eq!(json!(-5), plain_1_1("-0b101"));
+        assert_eq!(json!(31), plain_1_1("0x1f"));
+        assert_eq!(json!(5430), plain_1_1("1:30:30"));
+        assert_eq!(json!(90.5), plain_1_1("1:30.5"));
+        assert_eq!(json!(0.5), plain_1_1(".5"));
+        assert_eq!(json!(1500.0), plain_1_1("1.5e+3"));
+        assert_eq!(json!("1e3"), plain_1_1("1e3"));
+        assert_eq!(json!("0o10"), plain_1_1("0o10"));
+        assert_eq!(json!("089"), plain_1_1("089"));
+        assert_eq!(json!("1:60"), plain_1_1("1:60"));
+        assert_eq!(json!("yesterday"), plain_1_1("yesterday"));
+    }
+
+    #[test]
+    fn big_integers() {
+        let too_big = format!("0x{}", "f".repeat(300));
+
+        for version in [YamlVersion::V1_2, YamlVersion::V1_1] {
+            let y2j = Yaml2Json::new(Style::COMPACT).yaml_version(version);
+            let resolve = |s: &str| resolve(s.to_string(), TScalarStyle::Plain, None, &y2j);
+
+            for s in [
+                "123456789012345678901234567890",
+                "0x123456789012345678901234567890",
+                &format!("-0b{}", "1".repeat(70)),
+                "-18446744073709551615",
+                "18446744073709551616",
+                &too_big,
+            ] {
+                let message = format!("integer `{}` is outside the 64 bit range", s);
+                assert!(
+                    matches!(resolve(s), Err(Yaml2JsonError::UnsupportedError(m)) if m == message),
+                    "{}",
+                    s
+                );
+            }
+
+            assert_eq!(
+                json!(18446744073709551615u64),
+                resolve("18446744073709551615").unwrap()
+            );
+            assert_eq!(
+                json!(-9223372036854775808i64),
+                resolve("-9223372036854775808").unwrap()
+            );
+            // A float is never out of range.
+            assert_eq!(json!(1e20), resolve("1.0e+20").unwrap());
+        }
+
+        let y2j = Yaml2Json::new(Style::COMPACT).yaml_version(YamlVersion::V1_1);
+        for s in [
+            "123_456_789_012_345_678_901_234_567_890",
+            "0777777777777777777777777",
+        ] {
+            assert!(
+                resolve(s.to_string(), TScalarStyle::Plain, None, &y2j).is_err(),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[cfg(feature = "arbitrary-precision")]
+    #[test]
+    fn preserved_big_integers() {
+        let preserved = |s: &str, version| {
+            let y2j = Yaml2Json::new(Style::COMPACT)
+                .yaml_version(version)
+                .preserve_numbers(true);
+            let value = resolve(s.to_string(), TScalarStyle::Plain, None, &y2j).unwrap();
+            value.to_string()
+        };
+
+        assert_eq!(
+            "94522879687365475552814062743484560",
+            preserved("0x123456789012345678901234567890", YamlVersion::V1_2)
+        );
+        assert_eq!(
+            "-37778931862957161709567",
+            preserved("-0o7777777777777777777777777", YamlVersion::V1_2)
+        );
+        assert_eq!(
+            "4722366482869645213695",
+            preserved("0_777_777_777_777_777_777_777_777", YamlVersion::V1_1)
+        );
+        assert_eq!(
+            "18446744073709551616",
+            preserved("0x10000000000000000", YamlVersion::V1_1)
+        );
+
+        // Nothing is lost, so these aren't rejected with `strict()`.
+        let y2j = Yaml2Json::new(Style::COMPACT)
+            .preserve_numbers(true)
+            .strict(true);
+        for s in [
+            "123456789012345678901234567890",
+            "0.1000000000000000055",
+            "-0x1ffffffffffffffff",
+        ] {
+            let value = resolve(s.to_string(), TScalarStyle::Plain, None, &y2j);
+            assert!(value.is_ok(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn special_floats() {
+        let special = |s: &str, mode| {
+            let y2j = Yaml2Json::new(Style::COMPACT).special_floats(mode);
+            resolve(s.to_string(), TScalarStyle::Plain, None, &y2j)
+        };
+
+        assert_eq!(Value::Null, special(".nan", SpecialFloats::Null).unwrap());
+        assert_eq!(
+            json!("NaN"),
+            special(".NaN", SpecialFloats::String).unwrap()
+        );
+        assert_eq!(
+            json!("Infinity"),
+            special("+.inf", SpecialFloats::String).unwrap()
+        );
+        assert_eq!(
+            json!("-Infinity"),
+            special("-.Inf", SpecialFloats::String).unwrap()
+        );
+        assert!(special(".inf", SpecialFloats::Error).is_err());
+        assert_eq!(json!(1.5), special("1.5", SpecialFloats::Error).unwrap());
+    }
+
+    #[test]
+    fn quoted_scalars() {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        let res = resolve("12".to_string(), TScalarStyle::DoubleQuoted, None, &y2j).unwrap();
+        assert_eq!(json!("12"), res);
+
+        let y2j = y2j.yaml_version(YamlVersion::V1_1);
+        let res = resolve("yes".to_string(), TScalarStyle::SingleQuoted, None, &y2j).unwrap();
+        assert_eq!(json!("yes"), res);
+    }
+
+    #[test]
+    fn tagged_scalars() {
+        let tagged = |s: &str, suffix: &str, y2j: &Yaml2Json| {
+            let tag = core_tag(suffix);
+            resolve(s.to_string(), TScalarStyle::Plain, Some(&tag), y2j)
+        };
+        let y2j = Yaml2Json::new(Style::COMPACT);
+
+        let res = tagged("12", "str", &y2j).unwrap();
+        assert_eq!(json!("12"), res);
+
+        let res = tagged("12", "float", &y2j).unwrap();
+        assert_eq!(json!(12.0), res);
+
+        assert!(tagged("abc", "int", &y2j).is_err());
+        assert!(tagged("yes", "bool", &y2j).is_err());
+
+        let y2j = y2j.yaml_version(YamlVersion::V1_1);
+        let res = tagged("yes", "bool", &y2j).unwrap();
+        assert_eq!(json!(true), res);
+    }
+
+    #[test]
+    fn timestamp_scalars() {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        let res = resolve("2001-12-14".to_string(), TScalarStyle::Plain, None, &y2j).unwrap();
+        assert_eq!(json!("2001-12-14"), res);
+
+        let y2j = y2j.normalize_timestamps(true);
+        let res = resolve("2001-12-14".to_string(), TScalarStyle::Plain, None, &y2j).unwrap();
+        assert_eq!(json!("2001-12-14T00:00:00Z"), res);
+
+        let res = resolve(
+            "2001-12-14".to_string(),
+            TScalarStyle::DoubleQuoted,
+            None,
+            &y2j,
+        )
+        .unwrap();
+        assert_eq!(json!("2001-12-14"), res);
+
+        let tag = core_tag("timestamp");
+        let res = resolve(
+            "2001-12-14 1:02:03 +1".to_string(),
+            TScalarStyle::DoubleQuoted,
+            Some(&tag),
+            &y2j,
+        )
+        .unwrap();
+        assert_eq!(json!("2001-12-14T01:02:03+01:00"), res);
+
+        let res = resolve(
+            "yesterday".to_string(),
+            TScalarStyle::Plain,
+            Some(&tag),
+            &y2j,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn binary_scalars() {
+        let binary = |s: &str, mode| {
+            let y2j = Yaml2Json::new(Style::COMPACT).binary(mode);
+            let tag = core_tag("binary");
+            resolve(s.to_string(), TScalarStyle::Literal, Some(&tag), &y2j)
+        };
+
+        let res = binary("aGVs\nbG8=\n", Binary::Base64).unwrap();
+        assert_eq!(json!("aGVsbG8="), res);
+
+        let res = binary("aGVs\nbG8=\n", Binary::Bytes).unwrap();
+        assert_eq!(json!([104, 101, 108, 108, 111]), res);
+
+        assert!(binary("aGVsbG8=", Binary::Error).is_err());
+        assert!(binary("not base64!", Binary::Base64).is_err());
+    }
+}
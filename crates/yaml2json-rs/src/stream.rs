@@ -0,0 +1,322 @@
+use crate::json;
+use crate::{offset_lines, Input, Limit, Yaml2Json, Yaml2JsonError};
+use futures_util::future;
+use futures_util::io::{
+    AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, Cursor,
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde_json::Value;
+use std::io;
+use std::pin::Pin;
+use yaml_split::{DocumentStream, YamlSplitError};
+
+type Values<'a> = Pin<Box<dyn Stream<Item = Result<Value, Yaml2JsonError>> + 'a>>;
+
+/// `Auto` is how far `auto_stream_values_async()` is through its input.
+struct Auto<'a, R> {
+    /// The reader while the input is JSON, dropped once it has ended or failed.
+    reader: Option<BufReader<R>>,
+    /// Whether the start of the input has been checked for JSON.
+    sniffed: bool,
+    prefix: json::Prefix,
+    /// The YAML documents, once the input turns out not to be JSON.
+    yaml: Option<Values<'a>>,
+}
+
+/// The async conversions, with the `async` feature. These read from a futures `AsyncRead` and
+/// write to an `AsyncWrite`, so they never block the thread waiting for I/O. Converting each
+/// document is still synchronous, once it has been read.
+impl Yaml2Json {
+    /// `stream_async()` is the async equivalent of `stream()`, a `Stream` of each document in
+    /// `reader` converted to a JSON String.
+    ///
+    /// ```
+    /// use futures_util::stream::StreamExt;
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world\n---\nhello: [rust\n---\nhello: everyone";
+    /// let docs: Vec<_> = futures_executor::block_on(y2j.stream_async(input.as_bytes()).collect());
+    ///
+    /// assert_eq!(docs[0].as_ref().unwrap(), r#"{"hello":"world"}"#);
+    /// assert!(docs[1].is_err());
+    /// assert_eq!(docs[2].as_ref().unwrap(), r#"{"hello":"everyone"}"#);
+    /// ```
+    pub fn stream_async<'a, R: AsyncRead + Unpin + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Stream<Item = Result<String, Yaml2JsonError>> + 'a {
+        self.stream_values_async(reader)
+            .map(move |res| res.and_then(|value| self.value_to_string(&value)))
+    }
+
+    /// `stream_values_async()` is like `stream_async()`, but converts each document to a
+    /// `serde_json::Value`. Documents are read one at a time, except with `Input::Json`, which is
+    /// read as a whole first.
+    pub fn stream_values_async<'a, R: AsyncRead + Unpin + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Stream<Item = Result<Value, Yaml2JsonError>> + 'a {
+        let values: Values<'a> = match self.options.input {
+            Input::Yaml => Box::pin(self.yaml_stream_values_async(reader)),
+            Input::Auto => Box::pin(self.auto_stream_values_async(reader)),
+            Input::Json => Box::pin(stream::once(read_all(reader)).flat_map(move |res| {
+                let values: Vec<_> = match res {
+                    Ok(input) => self.stream_values(&input[..]).collect(),
+                    Err(e) => vec![Err(e)],
+                };
+
+                stream::iter(values)
+            })),
+        };
+
+        values
+    }
+
+    /// `auto_stream_values_async()` is the async equivalent of `auto_stream_values()`.
+    fn auto_stream_values_async<'a, R: AsyncRead + Unpin + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Stream<Item = Result<Value, Yaml2JsonError>> + 'a {
+        let auto = Auto {
+            reader: Some(BufReader::new(reader)),
+            sniffed: false,
+            prefix: json::Prefix::default(),
+            yaml: None,
+        };
+
+        stream::unfold(auto, move |mut auto| async move {
+            loop {
+                if let Some(value) = auto.prefix.next() {
+                    return Some((self.json_value(value), auto));
+                }
+                if let Some(values) = auto.yaml.as_mut() {
+                    let res = values.next().await?;
+                    return Some((res, auto));
+                }
+
+                let input = auto.reader.as_mut()?;
+                let res = input.fill_buf().await;
+                let (json, ended) = match res {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        auto.reader = None;
+                        return Some((Err(e.into()), auto));
+                    }
+                    Ok(buf) if !auto.sniffed => {
+                        auto.sniffed = true;
+                        (json::looks_like_json(buf), false)
+                    }
+                    Ok([]) => (auto.prefix.finish(), true),
+                    Ok(buf) => {
+                        let len = buf.len();
+                        let json = auto.prefix.push(buf);
+                        input.consume_unpin(len);
+                        (json, false)
+                    }
+                };
+
+                if !json {
+                    let (rest, lines) = auto.prefix.rest();
+                    // Safe to unwrap, it was just read from.
+                    let rest = Cursor::new(rest).chain(auto.reader.take().unwrap());
+                    let values = self.yaml_stream_values_async(rest);
+                    auto.yaml = Some(Box::pin(
+                        values.map(move |res| res.map_err(|e| offset_lines(e, lines))),
+                    ));
+                } else if ended {
+                    auto.reader = None;
+                }
+            }
+        })
+    }
+
+    fn yaml_stream_values_async<'a, R: AsyncRead + Unpin + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Stream<Item = Result<Value, Yaml2JsonError>> + 'a {
+        let documents = DocumentStream::new(reader);
+        let documents = match self.options.limits.max_document_size {
+            Some(max) => documents.max_document_size(max),
+            None => documents,
+        };
+
+        documents.with_spans().scan(false, move |failed, res| {
+            let value = match res {
+                _ if *failed => None,
+                Ok((doc, span)) => Some(
+                    self.yaml_document_to_value(&doc)
+                        .map_err(|e| offset_lines(e, span.line - 1)),
+                ),
+                Err(YamlSplitError::IOError(e)) => {
+                    *failed = true;
+                    Some(Err(e.into()))
+                }
+                Err(YamlSplitError::DocumentTooLargeError { max, .. }) => {
+                    Some(Err(Yaml2JsonError::LimitExceededError {
+                        limit: Limit::DocumentSize,
+                        max,
+                    }))
+                }
+            };
+
+            future::ready(value)
+        })
+    }
+
+    /// `document_to_async_writer()` is the async equivalent of `document_to_writer()`.
+    pub async fn document_to_async_writer<W: AsyncWrite + Unpin>(
+        &self,
+        document: &str,
+        w: &mut W,
+    ) -> Result<(), Yaml2JsonError> {
+        let mut buf = Vec::new();
+        self.document_to_writer(document, &mut buf)?;
+        w.write_all(&buf).await?;
+
+        Ok(())
+    }
+
+    /// `reader_to_async_writer()` is the async equivalent of `reader_to_writer()`. Each document
+    /// is held in memory, and written once it has been converted.
+    ///
+    /// ```
+    /// use yaml2json_rs::{Yaml2Json, Style};
+    ///
+    /// let y2j = Yaml2Json::new(Style::COMPACT);
+    /// let input = "hello: world\n---\nhello: rust";
+    /// let mut output = Vec::new();
+    ///
+    /// futures_executor::block_on(y2j.reader_to_async_writer(input.as_bytes(), &mut output))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(output, b"{\"hello\":\"world\"}\n{\"hello\":\"rust\"}\n");
+    /// ```
+    pub async fn reader_to_async_writer<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+        &self,
+        reader: R,
+        w: &mut W,
+    ) -> Result<(), Yaml2JsonError> {
+        let values = self.stream_values_async(reader);
+        futures_util::pin_mut!(values);
+
+        while let Some(value) = values.next().await {
+            let mut buf = Vec::new();
+            self.value_to_writer(&value?, &mut buf)?;
+
+            if !self.options.format.is_binary() {
+                buf.push(b'\n');
+            }
+            w.write_all(&buf).await?;
+        }
+
+        w.flush().await?;
+        Ok(())
+    }
+}
+
+async fn read_all<R: AsyncRead + Unpin>(mut reader: R) -> Result<Vec<u8>, Yaml2JsonError> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input).await?;
+
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Input, Style, Yaml2Json, Yaml2JsonError};
+    use futures_executor::block_on;
+    use futures_util::future::FutureExt;
+    use futures_util::io::AsyncRead;
+    use futures_util::stream::StreamExt;
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    const INPUT: &str = "a: 1\n---\nb: [2\n---\n- c\n";
+
+    #[test]
+    fn matches_stream() {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        let expected: Vec<_> = y2j.stream(INPUT.as_bytes()).map(|res| res.ok()).collect();
+        let res: Vec<_> = block_on(y2j.stream_async(INPUT.as_bytes()).collect::<Vec<_>>());
+
+        assert_eq!(
+            expected,
+            res.iter()
+                .map(|res| res.as_ref().ok().cloned())
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            res[1],
+            Err(Yaml2JsonError::ParseError { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn writers() {
+        let y2j = Yaml2Json::new(Style::COMPACT);
+        let mut output = Vec::new();
+        block_on(y2j.document_to_async_writer("a: [1, 2]", &mut output)).unwrap();
+        assert_eq!(br#"{"a":[1,2]}"#, &output[..]);
+
+        let input = "a: 1\n---\n- b\n";
+        let mut expected = Vec::new();
+        y2j.reader_to_writer(input.as_bytes(), &mut expected)
+            .unwrap();
+        let mut output = Vec::new();
+        block_on(y2j.reader_to_async_writer(input.as_bytes(), &mut output)).unwrap();
+        assert_eq!(expected, output);
+
+        let mut output = Vec::new();
+        let res = block_on(y2j.reader_to_async_writer(INPUT.as_bytes(), &mut output));
+        assert!(res.is_err());
+        assert_eq!(b"{\"a\":1}\n", &output[..]);
+    }
+
+    #[test]
+    fn json_input() {
+        let y2j = Yaml2Json::new(Style::COMPACT).input(Input::Json);
+        let input = "{\"a\": 1}\n[2]\n";
+        let res: Vec<_> = block_on(y2j.stream_async(input.as_bytes()).collect::<Vec<_>>());
+
+        assert_eq!(2, res.len());
+        assert_eq!(r#"{"a":1}"#, res[0].as_ref().unwrap());
+        assert_eq!("[2]", res[1].as_ref().unwrap());
+    }
+
+    /// `Endless` returns its input, then waits for more which never arrives.
+    struct Endless(&'static [u8]);
+
+    impl AsyncRead for Endless {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            if self.0.is_empty() {
+                return Poll::Pending;
+            }
+
+            let n = self.0.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    #[test]
+    fn auto_input() {
+        let y2j = Yaml2Json::new(Style::COMPACT).input(Input::Auto);
+
+        // Documents are converted as they are read, whether they turn out to be JSON or YAML.
+        for input in ["a: 1\n---\nb: 2\n", "{a: 1}\n---\n", "{\"a\": 1}\n{\"b\""] {
+            let docs = y2j.stream_async(Endless(input.as_bytes()));
+            futures_util::pin_mut!(docs);
+            assert_eq!(
+                Some(r#"{"a":1}"#.to_string()),
+                docs.next().now_or_never().flatten().map(Result::unwrap)
+            );
+        }
+    }
+}
@@ -0,0 +1,215 @@
+/// `normalize()` converts a YAML timestamp into an RFC 3339 string, or returns `None` if `s` isn't
+/// a valid timestamp.
+///
+/// YAML timestamps may be a date (`2001-12-14`), or a date and time separated by `T`, `t` or
+/// spaces. The time may have a fraction of a second and a timezone, e.g. `2001-12-14 21:59:43.10
+/// -5`. Dates are treated as midnight UTC, as are times without a timezone. The output always has
+/// the form `2001-12-14T21:59:43.1-05:00`, with `Z` for UTC and no trailing zeros in the fraction.
+pub(crate) fn normalize(s: &str) -> Option<String> {
+    let mut p = Parser(s.as_bytes());
+
+    let year = p.digits(4, 4)?;
+    p.expect(b'-')?;
+    let month = p.digits(1, 2)?;
+    p.expect(b'-')?;
+    let day = p.digits(1, 2)?;
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let date = format!("{:04}-{:02}-{:02}", year, month, day);
+
+    if p.0.is_empty() {
+        // A date on its own must use 2 digit months and days.
+        return (s.len() == 10).then(|| format!("{}T00:00:00Z", date));
+    }
+
+    if !p.separator() {
+        return None;
+    }
+
+    let hour = p.digits(1, 2)?;
+    p.expect(b':')?;
+    let minute = p.digits(2, 2)?;
+    p.expect(b':')?;
+    // 60 allows for leap seconds.
+    let second = p.digits(2, 2)?;
+
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut fraction = String::new();
+    if p.0.first() == Some(&b'.') {
+        p.0 = &p.0[1..];
+        let len = p.0.iter().take_while(|b| b.is_ascii_digit()).count();
+        fraction = String::from_utf8_lossy(&p.0[..len])
+            .trim_end_matches('0')
+            .to_string();
+        p.0 = &p.0[len..];
+
+        if !fraction.is_empty() {
+            fraction.insert(0, '.');
+        }
+    }
+
+    let offset = p.offset()?;
+
+    Some(format!(
+        "{}T{:02}:{:02}:{:02}{}{}",
+        date, hour, minute, second, fraction, offset
+    ))
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// A minimal parser over the remaining bytes of a timestamp.
+struct Parser<'a>(&'a [u8]);
+
+impl Parser<'_> {
+    /// `digits()` parses a number of between `min` and `max` digits.
+    fn digits(&mut self, min: usize, max: usize) -> Option<u32> {
+        let len = self
+            .0
+            .iter()
+            .take(max)
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+
+        if len < min {
+            return None;
+        }
+
+        let n = self.0[..len]
+            .iter()
+            .fold(0, |n, b| n * 10 + u32::from(b - b'0'));
+        self.0 = &self.0[len..];
+
+        Some(n)
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        let (first, rest) = self.0.split_first()?;
+
+        if *first != b {
+            return None;
+        }
+
+        self.0 = rest;
+        Some(())
+    }
+
+    fn skip_whitespace(&mut self) -> usize {
+        let len = self
+            .0
+            .iter()
+            .take_while(|b| matches!(b, b' ' | b'\t'))
+            .count();
+        self.0 = &self.0[len..];
+        len
+    }
+
+    /// `separator()` parses the `T`, `t` or whitespace between the date and time.
+    fn separator(&mut self) -> bool {
+        match self.0.first() {
+            Some(b'T' | b't') => {
+                self.0 = &self.0[1..];
+                true
+            }
+            _ => self.skip_whitespace() > 0,
+        }
+    }
+
+    /// `offset()` parses the optional timezone at the end of a timestamp, as an RFC 3339 offset.
+    fn offset(&mut self) -> Option<String> {
+        let whitespace = self.skip_whitespace();
+
+        let offset = match self.0.first() {
+            None if whitespace == 0 => "Z".to_string(),
+            Some(b'Z') => {
+                self.0 = &self.0[1..];
+                "Z".to_string()
+            }
+            Some(sign @ (b'+' | b'-')) => {
+                let sign = char::from(*sign);
+                self.0 = &self.0[1..];
+
+                let hours = self.digits(1, 2)?;
+                let minutes = match self.0.first() {
+                    Some(b':') => {
+                        self.0 = &self.0[1..];
+                        self.digits(2, 2)?
+                    }
+                    _ => 0,
+                };
+
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+
+                match (hours, minutes) {
+                    (0, 0) => "Z".to_string(),
+                    _ => format!("{}{:02}:{:02}", sign, hours, minutes),
+                }
+            }
+            _ => return None,
+        };
+
+        self.0.is_empty().then_some(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timestamp::normalize;
+
+    #[test]
+    fn timestamps() {
+        let cases = [
+            ("2001-12-14", "2001-12-14T00:00:00Z"),
+            (
+                "2001-12-14t21:59:43.10-05:00",
+                "2001-12-14T21:59:43.1-05:00",
+            ),
+            ("2001-12-14 21:59:43.10 -5", "2001-12-14T21:59:43.1-05:00"),
+            ("2001-12-15T02:59:43.1Z", "2001-12-15T02:59:43.1Z"),
+            ("2001-12-15 2:59:43.000", "2001-12-15T02:59:43Z"),
+            ("2001-1-5 02:59:43 +00", "2001-01-05T02:59:43Z"),
+            ("2000-02-29T00:00:00+13:30", "2000-02-29T00:00:00+13:30"),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(Some(expected.to_string()), normalize(input), "{}", input);
+        }
+    }
+
+    #[test]
+    fn not_timestamps() {
+        let cases = [
+            "2001-1-5",
+            "2001-13-01",
+            "2001-02-29",
+            "2001-12-14 ",
+            "2001-12-14T",
+            "2001-12-14T25:00:00",
+            "2001-12-14T21:59",
+            "2001-12-14T21:59:43 PST",
+            "12-14-2001",
+            "20011214",
+        ];
+
+        for input in cases {
+            assert_eq!(None, normalize(input), "{}", input);
+        }
+    }
+}
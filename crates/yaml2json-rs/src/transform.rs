@@ -0,0 +1,261 @@
+use crate::query::{pointer, segments};
+use crate::Yaml2JsonError;
+use core::fmt::{self, Debug, Display, Formatter};
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// `PathPattern` matches the paths of values within each document, see
+/// `Yaml2JsonOptions::transform()` and `Yaml2JsonOptions::redact()`.
+///
+/// Patterns are written as a `Query`, either as a dot path, e.g. `db.password`, or as a JSON
+/// Pointer, e.g. `/db/password`. A `*` within a segment matches any characters in a key or index,
+/// e.g. `*_token` or `users.*.password`, and a `**` segment matches any number of keys and indexes,
+/// including none, e.g. `**.password` matches `password` at any depth. A `*` can't be escaped, but
+/// it also matches itself. Patterns which would match the document itself, e.g. `.` or `**`, are
+/// invalid.
+/// ```
+/// use yaml2json_rs::PathPattern;
+///
+/// let pattern: PathPattern = "**.password".parse().unwrap();
+/// let pointer: PathPattern = "/**/password".parse().unwrap();
+///
+/// assert_eq!(pattern, pointer);
+/// assert!("a..b".parse::<PathPattern>().is_err());
+/// assert!(".".parse::<PathPattern>().is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PathPattern {
+    pattern: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    /// `**`, which matches any number of segments.
+    Any,
+    /// A key or index, in which each `*` matches any characters.
+    Glob(String),
+}
+
+impl PathPattern {
+    /// `matches()` returns whether `path`, the keys and indexes leading to a value, matches.
+    pub(crate) fn matches(&self, path: &[String]) -> bool {
+        matches(&self.segments, path)
+    }
+}
+
+fn matches(segments: &[Segment], path: &[String]) -> bool {
+    match segments.split_first() {
+        None => path.is_empty(),
+        Some((Segment::Any, rest)) => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+        Some((Segment::Glob(glob), rest)) => path
+            .split_first()
+            .is_some_and(|(key, path)| glob_matches(glob, key) && matches(rest, path)),
+    }
+}
+
+/// `glob_matches()` returns whether `s` matches `glob`, in which each `*` matches any characters.
+fn glob_matches(glob: &str, s: &str) -> bool {
+    if !glob.contains('*') {
+        return glob == s;
+    }
+
+    let (glob, s) = (glob.as_bytes(), s.as_bytes());
+    let (mut g, mut i) = (0, 0);
+    // The position in `glob` after the last `*`, and the position in `s` it has matched up to.
+    let mut star = None;
+
+    while i < s.len() {
+        if glob.get(g) == Some(&b'*') {
+            g += 1;
+            star = Some((g, i));
+        } else if glob.get(g) == Some(&s[i]) {
+            g += 1;
+            i += 1;
+        } else if let Some((after, matched)) = star {
+            // Let the last `*` match one more character, and try the rest of `glob` again.
+            g = after;
+            i = matched + 1;
+            star = Some((after, i));
+        } else {
+            return false;
+        }
+    }
+
+    glob[g..].iter().all(|&b| b == b'*')
+}
+
+impl FromStr for PathPattern {
+    type Err = Yaml2JsonError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Yaml2JsonError::InvalidPatternError(s.to_string());
+        let segments: Vec<Segment> = segments(s)
+            .ok_or_else(invalid)?
+            .into_iter()
+            .map(|segment| match segment.as_str() {
+                "**" => Segment::Any,
+                _ => Segment::Glob(segment),
+            })
+            .collect();
+
+        // Transforming or redacting the whole document is never what was meant.
+        if segments.iter().all(|segment| *segment == Segment::Any) {
+            return Err(invalid());
+        }
+
+        Ok(PathPattern {
+            pattern: s.to_string(),
+            segments,
+        })
+    }
+}
+
+/// Patterns are equal if they match the same paths, regardless of their syntax.
+impl PartialEq for PathPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.segments == other.segments
+    }
+}
+
+impl Eq for PathPattern {}
+
+impl Display for PathPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+type Transform = dyn Fn(&str, Value) -> Value + Send + Sync;
+
+/// `Transforms` holds the transformations registered with `Yaml2JsonOptions::transform()`, in the
+/// order they were registered.
+#[derive(Clone, Default)]
+pub(crate) struct Transforms(pub(crate) Vec<(PathPattern, Arc<Transform>)>);
+
+impl Transforms {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `apply()` transforms every value in `value` whose path matches a pattern.
+    pub(crate) fn apply(&self, value: Value) -> Value {
+        self.apply_at(value, &mut Vec::new())
+    }
+
+    /// `apply_at()` transforms the values within `value` first, then `value` itself, which is at
+    /// `path`. The result of a transformation isn't searched again.
+    fn apply_at(&self, mut value: Value, path: &mut Vec<String>) -> Value {
+        match &mut value {
+            Value::Object(map) => {
+                for (key, v) in map.iter_mut() {
+                    path.push(key.clone());
+                    *v = self.apply_at(std::mem::take(v), path);
+                    path.pop();
+                }
+            }
+            Value::Array(seq) => {
+                for (i, v) in seq.iter_mut().enumerate() {
+                    path.push(i.to_string());
+                    *v = self.apply_at(std::mem::take(v), path);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+
+        self.0
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(path))
+            .fold(value, |value, (_, transform)| {
+                transform(&pointer(path.iter().map(String::as_str)), value)
+            })
+    }
+}
+
+impl Debug for Transforms {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|(pattern, _)| pattern))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transform::{glob_matches, PathPattern, Transforms};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn matches(pattern: &str, path: &[&str]) -> bool {
+        let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+        pattern.parse::<PathPattern>().unwrap().matches(&path)
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("password", "password"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*_token", "api_token"));
+        assert!(glob_matches("*pass*", "db_password"));
+        assert!(glob_matches("a*b*c", "axxbxxbxc"));
+        assert!(glob_matches("é*", "été"));
+        assert!(!glob_matches("*_token", "api_tokens"));
+        assert!(!glob_matches("a*b*c", "axxbxxbx"));
+        assert!(!glob_matches("password", "Password"));
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(matches("db.password", &["db", "password"]));
+        assert!(matches("/db/password", &["db", "password"]));
+        assert!(matches("users.*.password", &["users", "0", "password"]));
+        assert!(matches("**.password", &["password"]));
+        assert!(matches("**.password", &["a", "b", "password"]));
+        assert!(matches("a.**", &["a"]));
+        assert!(matches("a.**.b.**.c", &["a", "b", "x", "b", "c"]));
+        assert!(matches("/", &[""]));
+        assert!(!matches("db.password", &["db", "password", "x"]));
+        assert!(!matches("users.*.password", &["users", "password"]));
+        assert!(!matches("**.password", &["password", "x"]));
+        assert!(!matches("a.**.b.**.c", &["a", "c", "b"]));
+    }
+
+    #[test]
+    fn invalid() {
+        for pattern in ["a..b", "a.", "/a~2", "", ".", "**", "**.**", "/**"] {
+            assert!(pattern.parse::<PathPattern>().is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn apply() {
+        let pattern = |s: &str| s.parse::<PathPattern>().unwrap();
+        let transforms = Transforms(vec![
+            (pattern("**.password"), Arc::new(|_: &str, _| json!("***"))),
+            (
+                pattern("users.*"),
+                Arc::new(|path: &str, mut value: Value| {
+                    value["path"] = json!(path);
+                    value
+                }),
+            ),
+        ]);
+
+        let value = json!({
+            "password": "a",
+            "users": [{"name": "b", "password": "c"}],
+            "db": {"password": {"file": "d"}},
+        });
+
+        assert_eq!(
+            json!({
+                "password": "***",
+                "users": [{"name": "b", "password": "***", "path": "/users/0"}],
+                "db": {"password": "***"},
+            }),
+            transforms.apply(value)
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use wasm_bindgen::prelude::*;
+use yaml2json_rs::{Style, Yaml2Json};
+
+fn yaml2json(pretty: bool) -> Yaml2Json {
+    Yaml2Json::new(if pretty {
+        Style::PRETTY
+    } else {
+        Style::COMPACT
+    })
+}
+
+/// `convert()` converts a single YAML document to a JSON string, with the default options of
+/// `yaml2json_rs`. A YAML error is thrown as a JS `Error`.
+#[wasm_bindgen]
+pub fn convert(yaml: &str, pretty: bool) -> Result<String, JsError> {
+    Ok(yaml2json(pretty).document_to_string(yaml)?)
+}
+
+/// `convertAll()` converts every document in a multi-document YAML string, returning an array of
+/// JSON strings. The first document which fails to convert is thrown as a JS `Error`.
+#[wasm_bindgen(js_name = convertAll)]
+pub fn convert_all(yaml: &str, pretty: bool) -> Result<Vec<String>, JsError> {
+    Ok(yaml2json(pretty)
+        .stream(yaml.as_bytes())
+        .collect::<Result<_, _>>()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{convert, convert_all};
+
+    // Only successful conversions can be tested outside of a JS runtime, as a `JsError` needs one.
+
+    #[test]
+    fn single_document() {
+        assert_eq!(r#"{"a":[1,true]}"#, convert("a: [1, true]", false).unwrap());
+        assert_eq!("{\n  \"a\": 1\n}", convert("a: 1", true).unwrap());
+    }
+
+    #[test]
+    fn multiple_documents() {
+        let res = convert_all("a: 1\n---\n- b\n---\n", false).unwrap();
+        assert_eq!(vec![r#"{"a":1}"#, r#"["b"]"#, "null"], res);
+    }
+}